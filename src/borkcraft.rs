@@ -1,2 +1,6 @@
 pub mod borkcraft_app;
+pub mod connection;
+pub mod logging;
+pub mod profiles;
 pub mod sessions;
+pub mod ui_state;
@@ -1,25 +1,33 @@
 // My Trash Imports
 use crate::{
+    connection::{connection_indicator, health_window, reset_circuits, HealthPanel},
     eframe_tools::ModalMachine,
     get_tokio_runtime,
     increment::Inc,
+    logging::{init_tracing, log_level, set_log_level},
     option,
     pages::{
         login::{login_page, LoginForm},
         nether_portals_page::{
             display_images::{change_image_by_user_input, display_nether_portal_images},
             download_images::*,
+            download_npt::is_downloading,
             page::display_nether_portals_page,
             portals::NetherPortals,
+            tags::tag_filter,
         },
     },
-    sessions::{current_session_time, SessionInfo, SessionTime},
-    time_of_day,
+    profiles::{profiles_window, world_switcher, Profiles},
+    sessions::SessionInfo,
+    thread_tools::{cancel_all_tasks, set_repaint, WakingSender},
+    ui_state::UiState,
     windows::{
         client_windows::{GenericWindow, Loglet},
         error_messages::ErrorMessage,
+        settings::{settings_button, settings_window, Settings},
+        tasks::{tasks_button, tasks_window, TaskManager},
     },
-    HandleOption, Realm,
+    HandleError, HandleOption, Realm,
 };
 
 // Emilk Imports
@@ -29,13 +37,6 @@ use eframe::egui::{self, Context, ScrollArea, Ui};
 // use tokio::runtime::Runtime;
 
 // Godly Standard Library Imports
-use std::sync::{
-    mpsc::{channel, Receiver, Sender},
-    Once,
-};
-
-// GLOBALS
-static START: Once = Once::new();
 
 fn set_base_page() -> ModalMachine {
     let options = vec!["Login".to_string(), "Nether Portals".to_string()];
@@ -50,26 +51,38 @@ pub struct BorkCraft {
     runtime: tokio::runtime::Runtime,
     login_form: LoginForm,
     session_info: SessionInfo,
+    profiles: Profiles,
     base_page: ModalMachine,
     nether_portals: NetherPortals,
     err_msg: ErrorMessage,
+    tasks: TaskManager,
+    health: HealthPanel,
+    settings: Settings,
+    // Saved by eframe on exit; the selected pairs are kept up to date while the app runs
+    ui_state: UiState,
+    // The pair to select once the world's portals are in
+    restore_pair: Option<i32>,
 }
 
 impl Default for BorkCraft {
     fn default() -> Self {
-        // Session Information
-        let (sender, receiver) = channel();
-        let session_info = SessionInfo::new(Some(receiver));
+        // Session Information; polled from update_updaters
+        let session_info = SessionInfo::default().display_namae("Session Time");
+
+        // Profiles; the active world has to be set before anything makes a request or picks a path
+        let profiles = Profiles::default();
+        profiles.activate_current();
 
         // Login Form
-        let (key_sender, key_receiver) = channel();
         let login_form = LoginForm {
-            sender: Some(key_sender),
+            username: profiles.active_profile_ref().username.clone(),
+            password: profiles.active_profile_ref().password.clone(),
             ..LoginForm::default()
         };
 
         // Error Message
-        let err_msg = ErrorMessage::new();
+        let mut err_msg = ErrorMessage::new();
+        err_msg.display.namae("Error Messages");
 
         // Tokio
         let runtime = get_tokio_runtime();
@@ -80,44 +93,163 @@ impl Default for BorkCraft {
         // ModalMachines
         let base_page = set_base_page();
 
-        START.call_once(|| {
-            real_init(sender, key_receiver, err_msg.sender_clone());
-        });
         Self {
             unique: Inc::new(),
             runtime,
             login_form,
             session_info,
+            profiles,
             nether_portals,
             base_page,
             err_msg,
+            tasks: TaskManager::default(),
+            health: HealthPanel::default(),
+            settings: Settings::default(),
+            ui_state: UiState::default(),
+            restore_pair: None,
         }
     }
 }
 
 fn realm_modal(nps: &mut NetherPortals, id: i64, ui: &mut Ui) {
+    tag_filter(nps, ui);
     nps.realm_modal_mut().modal_machine(id, ui);
 
     option(|| {
         // Check for an event; if None, return
         nps.realm_modal_mut().use_event()?;
 
-        // Get user selected netherportals
-        let pos = nps.realm_modal_mut().get_selected_option();
+        // Get user selected pair
+        let id = nps.realm_modal_mut().get_selected_option()?;
 
-        // set the current netherportal to the recently selected
-        nps.realm_pos_set2(pos);
+        // set the current pair to the recently selected
+        tracing::debug!(id, "Selected pair");
+        nps.select(id);
 
         Some(())
     });
 }
 
 impl BorkCraft {
-    fn update_updaters(&mut self) {
+    pub fn new(cc: &eframe::CreationContext) -> Self {
+        //! Background jobs get the ui's Context from here, so they can wake it up
+        set_repaint(&cc.egui_ctx);
+        let mut app = Self::default();
+        // Events show up in the "Error Messages" window too
+        init_tracing(app.err_msg.sender_clone());
+        if let Some(err) = app.profiles.take_load_error() {
+            app.err_msg.push_error(err);
+        }
+        if let Some(state) = cc
+            .storage
+            .and_then(|storage| eframe::get_value::<UiState>(storage, eframe::APP_KEY))
+        {
+            app.restore(&cc.egui_ctx, state);
+        }
+        app
+    }
+
+    fn windows_mut(&mut self) -> Vec<&mut GenericWindow> {
+        let mut windows = vec![
+            &mut self.session_info.display,
+            &mut self.err_msg.display,
+            &mut self.tasks.display,
+            &mut self.health.display,
+            &mut self.settings.display,
+            &mut self.profiles.display,
+        ];
+        windows.extend(self.nether_portals.windows_mut());
+        windows
+    }
+
+    fn restore(&mut self, ctx: &Context, state: UiState) {
+        //! Put the ui back the way it was left
+        self.base_page.select_option(&state.page);
+        state.restore_windows(self.windows_mut());
+        self.settings.theme = state.theme;
+        self.settings.theme.apply(ctx);
+        if let Some(level) = state.log_level {
+            set_log_level(level);
+        }
+        self.restore_pair = state
+            .selected_pairs
+            .get(&self.profiles.active_label())
+            .copied();
+        self.ui_state = state;
+    }
+
+    fn track_selected_pair(&mut self, ctx: &Context, downloading: bool) {
+        //! Select the pair from last time once it is loaded, after that remember what is selected
+        //!
+        //! The pair is waited for until the download (downloading) is over; if it still is not loaded then, it is gone
+        match self.restore_pair {
+            Some(id) if self.nether_portals.select(id).is_some() => {
+                self.restore_pair = None;
+                tracing::debug!(id, "Restored the selected pair");
+                load_pair_images(
+                    &mut self.nether_portals,
+                    &self.runtime,
+                    ctx,
+                    &mut self.unique,
+                    self.err_msg.sender_clone(),
+                );
+            }
+            Some(id) if !downloading && self.nether_portals.selected().is_some() => {
+                self.restore_pair = None;
+                tracing::debug!(id, "The pair from last time is not loaded");
+            }
+            Some(_) => {}
+            None => {
+                if let Some(id) = self.nether_portals.selected() {
+                    self.ui_state
+                        .selected_pairs
+                        .insert(self.profiles.active_label(), id);
+                }
+            }
+        }
+    }
+
+    fn switch_world(&mut self) {
+        //! Everything loaded from the old world is dropped; the new one starts fresh with its own servers & folder
+        self.profiles.activate_current();
+        tracing::info!(world = %self.profiles.active_label(), "Switched world");
+        let profile = self.profiles.active_profile_ref();
+        self.login_form.username = profile.username.clone();
+        self.login_form.password = profile.password.clone();
+
+        // The old session key means nothing to the new server
+        self.session_info.key = String::default();
+        self.session_info.is_logged_in = false;
+        self.session_info.username = String::default();
+
+        // Nothing the old world asked for is wanted anymore & its servers say nothing about the new ones
+        cancel_all_tasks();
+        reset_circuits();
+        self.health.clear();
+        self.nether_portals = NetherPortals::default();
+        self.restore_pair = self
+            .ui_state
+            .selected_pairs
+            .get(&self.profiles.active_label())
+            .copied();
+    }
+
+    fn update_updaters(&mut self, ctx: &Context) {
         self.unique.reset();
         self.err_msg.try_update_log();
-        self.session_info.try_update().ok();
+        self.session_info
+            .try_update(&self.runtime, ctx)
+            .consume_error(&mut self.err_msg);
+        // Checked before the portals are taken in, so a download that just ended has nothing left unsent
+        let downloading = is_downloading();
         self.nether_portals.try_update_npt().ok();
+        self.nether_portals.poll_requests();
+        self.health.poll(&self.runtime, ctx);
+        let audit_log = self.nether_portals.audit_log_mut();
+        audit_log.set_username(&self.session_info.username);
+        audit_log.try_update().consume_error(&mut self.err_msg);
+        self.nether_portals.try_update_saved();
+        self.track_selected_pair(ctx, downloading);
 
         // If there is a mm event, update the image that should be displayed
         change_image_by_user_input(&mut self.nether_portals, &Realm::Overworld);
@@ -145,7 +277,6 @@ impl BorkCraft {
                 );
                 display_nether_portals_page(
                     &mut self.nether_portals,
-                    &mut self.unique,
                     &mut self.err_msg,
                     &self.runtime,
                     ui,
@@ -169,33 +300,6 @@ impl BorkCraft {
     }
 }
 
-fn real_init(
-    session_info_sender: Sender<(SessionTime, Loglet)>,
-    key_receiver: Receiver<String>,
-    err_sender: Sender<Loglet>,
-) {
-    // Consider give this thread a ctx? so that i can wake up the ui thread on an error or on a session update
-    std::thread::spawn(move || {
-        let mut key = String::default();
-        loop {
-            // Update Key if the user logged in again
-            if let Ok(new_key) = key_receiver.try_recv() {
-                key = new_key;
-            }
-
-            // Fetch session time. Ok() => update key; Err() => Sender error
-            match current_session_time(&session_info_sender, key.clone()) {
-                Ok(maybe_new_key) => key = maybe_new_key,
-                Err(err) => err_sender
-                    .send(Loglet::new("Error", &err.to_string(), &time_of_day()))
-                    .unwrap(),
-            }
-
-            std::thread::sleep(std::time::Duration::from_secs(3));
-        }
-    });
-}
-
 fn display_session_time_left(session_info: &mut SessionInfo, id: i64, ui: &mut Ui, ctx: Context) {
     // Set name and show: Button, GenericWindow Glory
     session_info.display.namae("Session Time");
@@ -211,42 +315,56 @@ fn handle_base_page(base_page: &mut ModalMachine, id: i64, ui: &mut Ui) {
     base_page.modal_machine(id, ui);
 }
 
+fn load_pair_images(
+    nps: &mut NetherPortals,
+    runtime: &tokio::runtime::Runtime,
+    ctx: &Context,
+    unique: &mut Inc,
+    err_msg_sender: WakingSender<Loglet>,
+) {
+    //! Download the images of the selected pair (if they are not already)
+    if let Some((np, position)) = should_we_reload_ow_images(nps) {
+        load_images(np, position, runtime, ctx, err_msg_sender.clone());
+    }
+
+    if let Some((np, position)) = should_we_reload_nether_images(nps) {
+        load_images(np, position, runtime, ctx, err_msg_sender.clone());
+    }
+
+    // Coming back to a pair whose downloads were cancelled
+    resume_images(nps, runtime, ctx, err_msg_sender);
+
+    // Reload/Remake ModalMachines
+    reload_image_mm(nps, &Realm::Overworld, unique.up_str());
+    reload_image_mm(nps, &Realm::Nether, unique.up_str());
+}
+
 fn move_back_or_forth_buttons(
     nps: &mut NetherPortals,
     runtime: &tokio::runtime::Runtime,
     unique: &mut Inc,
-    err_msg_sender: Sender<Loglet>,
+    err_msg_sender: WakingSender<Loglet>,
     ui: &mut Ui,
 ) {
     let mut clicked = false;
+    let ctx = ui.ctx().clone();
     ui.horizontal(|ui| {
         // Buttons to move the nether portals selections back or forth
         if ui.button("Go Back").clicked() {
-            nps.ow_pos_down();
-            nps.neth_pos_down();
+            tracing::debug!("Go Back");
+            nps.pos_down();
             clicked = true;
         }
 
         if ui.button("Go Forth").clicked() {
-            nps.ow_pos_up();
-            nps.neth_pos_up();
+            tracing::debug!("Go Forth");
+            nps.pos_up();
             clicked = true;
         }
     });
     // On change, you need to check if there are images that should be downloaded
     if clicked {
-        // Download the images
-        if let Some((np, position)) = should_we_reload_ow_images(nps) {
-            load_images(np, position, runtime, err_msg_sender.clone());
-        }
-
-        if let Some((np, position)) = should_we_reload_nether_images(nps) {
-            load_images(np, position, runtime, err_msg_sender);
-        }
-
-        // Reload/Remake ModalMachines
-        reload_image_mm(nps, &Realm::Overworld, unique.up_str());
-        reload_image_mm(nps, &Realm::Nether, unique.up_str());
+        load_pair_images(nps, runtime, &ctx, unique, err_msg_sender);
     }
 
     //// Display the ModalMachines
@@ -257,6 +375,17 @@ fn move_back_or_forth_buttons(
 }
 
 impl eframe::App for BorkCraft {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        //! Called by eframe every so often & on exit
+        let mut state = std::mem::take(&mut self.ui_state);
+        state.page = self.base_page.get_selected_option();
+        state.remember_windows(self.windows_mut());
+        state.theme = self.settings.theme;
+        state.log_level = Some(log_level());
+        eframe::set_value(storage, eframe::APP_KEY, &state);
+        self.ui_state = state;
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::TopBottomPanel::top("TopBoi").show(ctx, |ui| {
             ui.horizontal(|ui| {
@@ -272,24 +401,36 @@ impl eframe::App for BorkCraft {
                 );
 
                 display_err_msgs(&mut self.err_msg, self.unique.up(), ui, ctx.clone());
+                tasks_button(&mut self.tasks, ui);
+                connection_indicator(&mut self.health, ui);
+                settings_button(&mut self.settings, ui);
+                if world_switcher(&mut self.profiles, ui) {
+                    self.switch_world();
+                }
             });
         });
-
-        egui::SidePanel::left(self.unique.up_str()).show(ctx, |ui| {
-            ScrollArea::vertical()
-                .id_source(self.unique.up())
-                .show(ui, |ui| {
-                    // Is this good?
-                    self.handle_pages(ui);
-                });
+        let reload = profiles_window(&mut self.profiles, ctx);
+        if let Ok(true) = reload {
+            self.switch_world();
+        }
+        reload.consume_error(&mut self.err_msg);
+        tasks_window(&mut self.tasks, ctx);
+        health_window(&mut self.health, &self.runtime, ctx);
+        settings_window(&mut self.settings, ctx);
+
+        // Fixed ids; egui keeps the panel's width under it between restarts
+        egui::SidePanel::left("pages").show(ctx, |ui| {
+            ScrollArea::vertical().id_source("pages").show(ui, |ui| {
+                // Is this good?
+                self.handle_pages(ui);
+            });
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
             self.handle_image_pages(ui);
         });
 
-        // update
-        self.update_updaters();
-        ctx.request_repaint();
+        // update; background jobs wake the ui when they have something, so no repaint every frame
+        self.update_updaters(ctx);
     }
 }
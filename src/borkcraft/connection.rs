@@ -0,0 +1,631 @@
+use crate::{
+    thread_tools::{wake, Request, RequestState},
+    url_tools::{Routes, Urls},
+    windows::client_windows::GenericWindow,
+    BorkError,
+};
+use eframe::egui::{Color32, Context, Grid, Label, RichText, Sense, Ui};
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::runtime::Runtime;
+
+// The servers a world talks to; each one gets its own circuit breaker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Service {
+    Text,
+    Image,
+    Portals,
+}
+
+impl Service {
+    pub const ALL: [Service; 3] = [Service::Text, Service::Image, Service::Portals];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Service::Text => "Text",
+            Service::Image => "Image",
+            Service::Portals => "Portals",
+        }
+    }
+    fn index(&self) -> usize {
+        *self as usize
+    }
+    pub fn url(&self, route: Routes) -> String {
+        match self {
+            Service::Text => Urls::default(route),
+            Service::Image => Urls::default_i(route),
+            Service::Portals => Urls::default_p(route),
+        }
+    }
+}
+
+// How often & how long to wait before trying a failed call again
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    // Counting the first try
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(4),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn delay(&self, attempt: u32) -> Duration {
+        //! base_delay doubled for every attempt so far, then somewhere between half & all of that
+        //!
+        //! The jitter keeps every image download from hitting a server that just came back at once
+        let doubled = self.base_delay.saturating_mul(2u32.saturating_pow(attempt));
+        let delay = doubled.min(self.max_delay);
+        delay.mul_f64(0.5 + 0.5 * jitter())
+    }
+}
+
+fn jitter() -> f64 {
+    //! 0.0 to 1.0; every RandomState is seeded differently, which is plenty random for this
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(0);
+    (hasher.finish() % 1000) as f64 / 1000.0
+}
+
+// Failures in a row before a service counts as down
+const FAILURE_THRESHOLD: u32 = 3;
+// How long a down service is left alone before one call is let through to check on it
+const COOLDOWN: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CircuitState {
+    // Calls go through
+    Closed,
+    // The service is down; calls fail right away until (until)
+    Open { until: Instant },
+    // The cooldown is over; the next call decides if the service is back
+    HalfOpen,
+}
+
+#[derive(Clone, Copy)]
+struct Breaker {
+    state: CircuitState,
+    failures: u32,
+    // HalfOpen lets one call through; every other call fails fast until it is back
+    probing: bool,
+}
+
+impl Breaker {
+    const NEW: Breaker = Breaker {
+        state: CircuitState::Closed,
+        failures: 0,
+        probing: false,
+    };
+
+    fn end_cooldown(&mut self) {
+        if let CircuitState::Open { until } = self.state {
+            if Instant::now() >= until {
+                self.state = CircuitState::HalfOpen;
+                self.probing = false;
+            }
+        }
+    }
+    fn is_available(&self) -> bool {
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::HalfOpen => !self.probing,
+            CircuitState::Open { .. } => false,
+        }
+    }
+}
+
+// Calls are made from other threads, so every breaker lives in here
+static BREAKERS: Mutex<[Breaker; 3]> = Mutex::new([Breaker::NEW; 3]);
+
+pub fn circuit_state(service: Service) -> CircuitState {
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = &mut breakers[service.index()];
+    breaker.end_cooldown();
+    breaker.state
+}
+
+pub fn is_available(service: Service) -> bool {
+    //! false while the service is down & its cooldown is not over, or while its one probe is out
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = &mut breakers[service.index()];
+    breaker.end_cooldown();
+    breaker.is_available()
+}
+
+fn acquire(service: Service) -> bool {
+    //! Take the go-ahead for one call; in HalfOpen only the first caller gets it (the probe)
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = &mut breakers[service.index()];
+    breaker.end_cooldown();
+    let available = breaker.is_available();
+    if available && breaker.state == CircuitState::HalfOpen {
+        breaker.probing = true;
+    }
+    available
+}
+
+pub fn retry_now(service: Service) {
+    //! Let the next call through, even when the cooldown is not over
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = &mut breakers[service.index()];
+    if let CircuitState::Open { .. } = breaker.state {
+        breaker.state = CircuitState::HalfOpen;
+        breaker.probing = false;
+    }
+}
+
+pub fn reset_circuits() {
+    //! Forget what is known about every service (a new world has new servers)
+    *BREAKERS.lock().unwrap() = [Breaker::NEW; 3];
+}
+
+fn record(service: Service, result: &Result<(), &BorkError>) {
+    //! Only failures that say something about the server (it can not be reached, or it is having a bad time) count
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = &mut breakers[service.index()];
+    let before = breaker.state;
+    match result {
+        Err(err) if err.is_retriable() => {
+            breaker.failures += 1;
+            breaker.probing = false;
+            if breaker.state == CircuitState::HalfOpen || breaker.failures >= FAILURE_THRESHOLD {
+                breaker.state = CircuitState::Open {
+                    until: Instant::now() + COOLDOWN,
+                };
+            }
+        }
+        _ => *breaker = Breaker::NEW,
+    }
+    // The indicator in the top panel has to change
+    if breaker.state != before {
+        match breaker.state {
+            CircuitState::Open { .. } => tracing::warn!(
+                failures = breaker.failures,
+                "The |{}| server is down, waiting {}s before trying it again",
+                service.name(),
+                COOLDOWN.as_secs()
+            ),
+            CircuitState::Closed => tracing::info!("The |{}| server is back up", service.name()),
+            CircuitState::HalfOpen => {}
+        }
+        wake();
+    }
+}
+
+fn should_retry(err: &BorkError, idempotent: bool) -> bool {
+    //! A call that is not idempotent (adding a portal) could have gone through before it failed,
+    //! so it is only tried again when the server said it did not do anything
+    match idempotent {
+        true => err.is_retriable(),
+        false => matches!(
+            err,
+            BorkError::Http {
+                status: 429 | 503,
+                ..
+            }
+        ),
+    }
+}
+
+pub fn call<T>(
+    service: Service,
+    idempotent: bool,
+    f: impl Fn() -> Result<T, BorkError>,
+) -> Result<T, BorkError> {
+    //! Make a call to (service) with the default RetryPolicy, through its circuit breaker
+    //!
+    //! Blocks while it waits between tries, so only use it from a blocking thread (spawn_blocking),
+    //! never from the ui thread or an async task
+    call_unless(service, idempotent, || false, f)
+}
+
+fn wait_unless(delay: Duration, is_cancelled: &impl Fn() -> bool) {
+    //! Sleep for (delay), waking up now & then to stop early once (is_cancelled)
+    let until = Instant::now() + delay;
+    while !is_cancelled() {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return;
+        }
+        std::thread::sleep(left.min(Duration::from_millis(50)));
+    }
+}
+
+pub fn call_unless<T>(
+    service: Service,
+    idempotent: bool,
+    is_cancelled: impl Fn() -> bool,
+    f: impl Fn() -> Result<T, BorkError>,
+) -> Result<T, BorkError> {
+    //! call(), for jobs that can be cancelled: no more tries (or waiting for one) once (is_cancelled)
+    let policy = RetryPolicy::default();
+    let _span = tracing::debug_span!("call", service = service.name()).entered();
+    let mut attempt = 0;
+    loop {
+        if is_cancelled() {
+            return Err(BorkError::cancelled());
+        }
+        if !acquire(service) {
+            return Err(BorkError::Network(format!(
+                "The |{}| server is down, waiting before trying it again",
+                service.name()
+            )));
+        }
+
+        let result = f();
+        record(service, &result.as_ref().map(|_| ()));
+        attempt += 1;
+        match result {
+            Err(err) if attempt < policy.max_attempts && should_retry(&err, idempotent) => {
+                let delay = policy.delay(attempt);
+                tracing::debug!(
+                    attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    "Trying |{}| again after: {}",
+                    service.name(),
+                    err
+                );
+                wait_unless(delay, &is_cancelled);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn state_label(state: CircuitState) -> (Color32, &'static str) {
+    match state {
+        CircuitState::Closed => (Color32::GREEN, "up"),
+        CircuitState::HalfOpen => (Color32::YELLOW, "checking"),
+        CircuitState::Open { .. } => (Color32::RED, "down"),
+    }
+}
+
+// What a health check found out
+#[derive(Clone, Debug)]
+pub struct Health {
+    pub latency: Duration,
+    // None when the server does not say
+    pub version: Option<String>,
+}
+
+pub fn ping(service: Service) -> Result<Health, BorkError> {
+    //! One timed GET of the health route; it counts toward the service's circuit breaker like any other call
+    let started = Instant::now();
+    let result = ureq::get(&service.url(Routes::Health))
+        .call()
+        .map_err(BorkError::from);
+    let latency = started.elapsed();
+    record(service, &result.as_ref().map(|_| ()));
+    tracing::debug!(
+        latency_ms = latency.as_millis() as u64,
+        ok = result.is_ok(),
+        "Pinged |{}|",
+        service.name()
+    );
+
+    let response = match result {
+        Ok(response) => response,
+        // Older servers have no health route, but they did answer
+        Err(BorkError::Http { status: 404, .. }) => {
+            return Ok(Health {
+                latency,
+                version: None,
+            })
+        }
+        Err(err) => return Err(err),
+    };
+    // {"version": "1.2.0"}; anything else is a server that is up but does not say
+    let version = response
+        .into_string()
+        .ok()
+        .and_then(|body| serde_json::from_str::<HashMap<String, serde_json::Value>>(&body).ok())
+        .and_then(|json| json.get("version").cloned())
+        .map(|version| match version {
+            serde_json::Value::String(version) => version,
+            version => version.to_string(),
+        });
+
+    Ok(Health { latency, version })
+}
+
+// How often every service is pinged while the "Server Status" window is open
+const HEALTH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct ServiceCheck {
+    request: Request<Health, BorkError>,
+    last: Option<Health>,
+    last_success: Option<Instant>,
+    last_check: Option<Instant>,
+    last_error: Option<BorkError>,
+}
+
+pub struct HealthPanel {
+    checks: [ServiceCheck; 3],
+    pub display: GenericWindow,
+}
+
+impl Default for HealthPanel {
+    fn default() -> Self {
+        Self {
+            checks: Default::default(),
+            display: GenericWindow::new("Server Status"),
+        }
+    }
+}
+
+impl HealthPanel {
+    pub fn clear(&mut self) {
+        //! Forget every check; they were about the servers of another world
+        self.checks = Default::default();
+    }
+
+    fn check(&mut self, service: Service, runtime: &Runtime, ctx: &Context) {
+        let check = &mut self.checks[service.index()];
+        let name = format!("Health |{}|", service.name());
+        check
+            .request
+            .spawn(runtime, ctx, &name, move |_| ping(service));
+        check.last_check = Some(Instant::now());
+    }
+
+    pub fn poll(&mut self, runtime: &Runtime, ctx: &Context) {
+        //! Take in finished pings & start the ones that are due (only while the window is open)
+        for service in Service::ALL {
+            let check = &mut self.checks[service.index()];
+            check.request.poll();
+            match check.request.state_ref() {
+                RequestState::Success(_) => {
+                    check.last = check.request.take_ready();
+                    check.last_success = Some(Instant::now());
+                    check.last_error = None;
+                }
+                RequestState::Error(err) => {
+                    check.last_error = Some(err.clone());
+                    check.request.reset();
+                }
+                _ => {}
+            }
+
+            let is_due = check
+                .last_check
+                .is_none_or(|last_check| last_check.elapsed() >= HEALTH_INTERVAL);
+            if self.display.is_window_open && check.request.is_idle() && is_due {
+                self.check(service, runtime, ctx);
+            }
+        }
+        if self.display.is_window_open {
+            ctx.request_repaint_after(HEALTH_INTERVAL);
+        }
+    }
+
+    fn show(&mut self, runtime: &Runtime, ui: &mut Ui) {
+        ui.label("Logins & sessions go through the Text server");
+        // Keep "Last Success" ticking
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+        let mut retry = None;
+        Grid::new("server status").striped(true).show(ui, |ui| {
+            ui.strong("Server");
+            ui.strong("State");
+            ui.strong("Latency");
+            ui.strong("Last Success");
+            ui.strong("Version");
+            ui.end_row();
+
+            for service in Service::ALL {
+                let check = &self.checks[service.index()];
+                ui.label(service.name())
+                    .on_hover_text(service.url(Routes::Health));
+                let (color, state) = state_label(circuit_state(service));
+                let state = ui.label(RichText::new(state).color(color));
+                if let Some(err) = &check.last_error {
+                    state.on_hover_text(err.to_string());
+                }
+                match (&check.last, check.request.is_pending()) {
+                    (_, true) => ui.spinner(),
+                    (Some(health), false) => ui.label(format!("{} ms", health.latency.as_millis())),
+                    (None, false) => ui.label("-"),
+                };
+                match check.last_success {
+                    Some(at) => ui.label(format!("{}s ago", at.elapsed().as_secs())),
+                    None => ui.label("never"),
+                };
+                let version = check
+                    .last
+                    .as_ref()
+                    .and_then(|health| health.version.clone());
+                ui.label(version.unwrap_or_else(|| "unknown".to_string()));
+                if ui.button("Retry Now").clicked() {
+                    retry = Some(service);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(service) = retry {
+            retry_now(service);
+            self.check(service, runtime, ui.ctx());
+        }
+    }
+}
+
+pub fn connection_indicator(health: &mut HealthPanel, ui: &mut Ui) {
+    //! A colored dot for every service in the top panel; a click opens the "Server Status" window
+    for service in Service::ALL {
+        let (color, state) = state_label(circuit_state(service));
+        let dot = ui.add(Label::new(RichText::new("●").color(color)).sense(Sense::click()));
+        if dot
+            .on_hover_text(format!("{} server: {}", service.name(), state))
+            .clicked()
+        {
+            health.display.is_window_open = !health.display.is_window_open;
+        }
+    }
+}
+
+pub fn health_window(health: &mut HealthPanel, runtime: &Runtime, ctx: &Context) {
+    //! Shows the "Server Status" window (if its open)
+    let mut display = std::mem::take(&mut health.display);
+
+    display.show_with(ctx, |ui| health.show(runtime, ui));
+
+    health.display = display;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_breaker(service: Service, breaker: Breaker) {
+        BREAKERS.lock().unwrap()[service.index()] = breaker;
+    }
+    fn server_error() -> BorkError {
+        BorkError::Http {
+            status: 503,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_max_with_jitter() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..10 {
+            let full = policy
+                .base_delay
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(policy.max_delay);
+            let delay = policy.delay(attempt);
+            assert!(delay >= full / 2, "attempt {}: {:?}", attempt, delay);
+            assert!(delay <= full, "attempt {}: {:?}", attempt, delay);
+        }
+        // Huge attempts do not overflow
+        assert!(policy.delay(u32::MAX) <= policy.max_delay);
+    }
+
+    #[test]
+    fn breaker_opens_after_the_threshold_and_closes_on_success() {
+        let service = Service::Text;
+        set_breaker(service, Breaker::NEW);
+
+        for _ in 1..FAILURE_THRESHOLD {
+            record(service, &Err(&server_error()));
+            assert_eq!(circuit_state(service), CircuitState::Closed);
+        }
+        // Failures that say nothing about the server do not count
+        record(service, &Err(&BorkError::validation("bad form")));
+        assert_eq!(circuit_state(service), CircuitState::Closed);
+        for _ in 0..FAILURE_THRESHOLD {
+            record(service, &Err(&server_error()));
+        }
+        assert!(matches!(circuit_state(service), CircuitState::Open { .. }));
+        assert!(!is_available(service));
+
+        // The cooldown is over
+        set_breaker(
+            service,
+            Breaker {
+                state: CircuitState::Open {
+                    until: Instant::now(),
+                },
+                failures: FAILURE_THRESHOLD,
+                probing: false,
+            },
+        );
+        assert_eq!(circuit_state(service), CircuitState::HalfOpen);
+
+        record(service, &Ok(()));
+        assert_eq!(circuit_state(service), CircuitState::Closed);
+        assert!(is_available(service));
+    }
+
+    #[test]
+    fn cancelled_calls_stop_trying() {
+        let tries = std::sync::atomic::AtomicU32::new(0);
+        let err = call_unless(
+            Service::Portals,
+            true,
+            || true,
+            || {
+                tries.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                Ok(())
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), "Aborted");
+        assert_eq!(tries.into_inner(), 0);
+
+        // Waiting for the next try ends as soon as it is cancelled
+        let started = Instant::now();
+        wait_unless(Duration::from_secs(5), &|| {
+            started.elapsed() >= Duration::from_millis(100)
+        });
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn half_open_lets_one_probe_through() {
+        let service = Service::Image;
+        set_breaker(
+            service,
+            Breaker {
+                state: CircuitState::HalfOpen,
+                failures: FAILURE_THRESHOLD,
+                probing: false,
+            },
+        );
+
+        assert!(acquire(service));
+        // Everybody else fails fast while the probe is out
+        assert!(!acquire(service));
+        assert!(!is_available(service));
+        let err = call(service, true, || Ok(())).unwrap_err();
+        assert_eq!(err.kind(), "Network");
+
+        // A failed probe opens it again for another cooldown
+        record(service, &Err(&server_error()));
+        assert!(matches!(circuit_state(service), CircuitState::Open { .. }));
+
+        // Retry Now lets a new probe through
+        retry_now(service);
+        assert!(acquire(service));
+        record(service, &Ok(()));
+        assert_eq!(circuit_state(service), CircuitState::Closed);
+        assert!(acquire(service) && acquire(service));
+    }
+
+    #[test]
+    fn status_follows_the_portals_breaker() {
+        let service = Service::Portals;
+        set_breaker(service, Breaker::NEW);
+        assert_eq!(state_label(circuit_state(service)), (Color32::GREEN, "up"));
+
+        for _ in 0..FAILURE_THRESHOLD {
+            record(service, &Err(&BorkError::Network("timed out".to_string())));
+        }
+        assert_eq!(state_label(circuit_state(service)), (Color32::RED, "down"));
+        assert!(!is_available(service));
+
+        retry_now(service);
+        assert_eq!(
+            state_label(circuit_state(service)),
+            (Color32::YELLOW, "checking")
+        );
+        // The probe came back fine
+        assert!(acquire(service));
+        record(service, &Ok(()));
+        assert_eq!(state_label(circuit_state(service)), (Color32::GREEN, "up"));
+    }
+}
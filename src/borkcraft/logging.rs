@@ -0,0 +1,284 @@
+use crate::{
+    thread_tools::WakingSender,
+    windows::client_windows::{Level, Loglet, LOGS_DIR},
+};
+use std::{
+    fmt::Debug,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+use tracing::{field::Field, Event, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{
+    field::Visit,
+    filter::LevelFilter,
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    reload,
+    util::SubscriberInitExt,
+    Layer, Registry,
+};
+
+// What is logged when the app starts; changed from the "Settings" window
+pub const DEFAULT_LEVEL: Level = Level::Info;
+
+// borkcraft.log is rotated once it gets bigger than this
+const MAX_LOG_FILE: u64 = 1024 * 1024;
+// How many rotated files are kept (borkcraft.log.1 is the newest)
+const LOG_ROTATIONS: usize = 3;
+
+// Changes the level of every output at once, while the app runs
+static FILTER: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+// The file writer stops when this is dropped, so it is kept for as long as the app runs
+static FILE_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+fn level_filter(level: Level) -> LevelFilter {
+    match level {
+        Level::Debug => LevelFilter::DEBUG,
+        Level::Info => LevelFilter::INFO,
+        Level::Warn => LevelFilter::WARN,
+        Level::Error => LevelFilter::ERROR,
+    }
+}
+
+pub fn init_tracing(window: WakingSender<Loglet>) {
+    //! Send every event to stderr, to logs/borkcraft.log (rotated by size) & to the "Error Messages" window
+    //!
+    //! Only the first call does anything
+    if FILTER.get().is_some() {
+        return;
+    }
+    let (filter, handle) = reload::Layer::new(level_filter(DEFAULT_LEVEL));
+
+    let (file, guard) = tracing_appender::non_blocking(RotatingFile::new(
+        Path::new(LOGS_DIR).join("borkcraft.log"),
+        MAX_LOG_FILE,
+    ));
+
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(file),
+        )
+        .with(WindowLayer { sender: window })
+        .try_init();
+
+    // Somebody else (a test harness) already set one; their subscriber gets the events
+    if result.is_ok() {
+        FILTER.set(handle).ok();
+        FILE_GUARD.set(guard).ok();
+    }
+}
+
+// The one writer of log files; the windows' events reach it through tracing too
+struct RotatingFile {
+    path: PathBuf,
+    max_len: u64,
+    // Opened on the first write, so nothing is made until something is logged
+    file: Option<File>,
+    len: u64,
+}
+
+impl RotatingFile {
+    fn new(path: PathBuf, max_len: u64) -> Self {
+        Self {
+            path,
+            max_len,
+            file: None,
+            len: 0,
+        }
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        PathBuf::from(format!("{}.{}", self.path.to_string_lossy(), n))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        //! name.log becomes name.log.1, .1 becomes .2 & so on; the oldest past LOG_ROTATIONS is dropped
+        self.file = None;
+        std::fs::remove_file(self.rotated(LOG_ROTATIONS)).ok();
+        for n in (1..LOG_ROTATIONS).rev() {
+            std::fs::rename(self.rotated(n), self.rotated(n + 1)).ok();
+        }
+        std::fs::rename(&self.path, self.rotated(1))
+    }
+
+    fn file(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.len = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().unwrap())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file()?;
+        if self.len > 0 && self.len + buf.len() as u64 > self.max_len {
+            self.rotate()?;
+        }
+        let written = self.file()?.write(buf)?;
+        self.len += written as u64;
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn log_level() -> Level {
+    let current = FILTER
+        .get()
+        .and_then(|handle| handle.clone_current())
+        .unwrap_or(level_filter(DEFAULT_LEVEL));
+    Level::ALL
+        .into_iter()
+        .find(|level| level_filter(*level) == current)
+        .unwrap_or(DEFAULT_LEVEL)
+}
+
+pub fn set_log_level(level: Level) {
+    if let Some(handle) = FILTER.get() {
+        handle.reload(level_filter(level)).ok();
+        tracing::info!(level = level.name(), "Log level changed");
+    }
+}
+
+pub fn trace_loglet(loglet: &Loglet) {
+    //! For Loglets that go to the window by hand (errors); stderr & the log file get them from here
+    let (source, msg) = (loglet.source(), loglet.msg());
+    match loglet.level() {
+        Level::Error => tracing::error!(in_window = true, source, "{}", msg),
+        Level::Warn => tracing::warn!(in_window = true, source, "{}", msg),
+        Level::Info => tracing::info!(in_window = true, source, "{}", msg),
+        Level::Debug => tracing::debug!(in_window = true, source, "{}", msg),
+    }
+}
+
+// Turns events into Loglets for the in-app log window
+struct WindowLayer {
+    sender: WakingSender<Loglet>,
+}
+
+// The "message" of an event, with every other field after it (key=value)
+#[derive(Default)]
+struct Message {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl Visit for Message {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            name => self.fields.push(format!("{}={}", name, value)),
+        }
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            name => self.fields.push(format!("{}={:?}", name, value)),
+        }
+    }
+}
+
+fn source_of(target: &str) -> String {
+    //! bork_craft::pages::login -> pages/login; the same as a Loglet made by hand
+    target.trim_start_matches("bork_craft::").replace("::", "/")
+}
+
+impl<S> Layer<S> for WindowLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        // Events from the libraries (ureq, eframe...) are only for the files
+        if !metadata.target().starts_with("bork_craft") {
+            return;
+        }
+        // Already in the window (trace_loglet)
+        if metadata.fields().field("in_window").is_some() {
+            return;
+        }
+        let level = match *metadata.level() {
+            tracing::Level::ERROR => Level::Error,
+            tracing::Level::WARN => Level::Warn,
+            tracing::Level::INFO => Level::Info,
+            _ => Level::Debug,
+        };
+
+        let mut message = Message::default();
+        event.record(&mut message);
+        let mut msg = message.message;
+        if !message.fields.is_empty() {
+            msg = format!("{} ({})", msg, message.fields.join(", "));
+        }
+        // Which task/call it happened in: "task > call: message"
+        if let Some(scope) = ctx.event_scope(event) {
+            let spans: Vec<&str> = scope.from_root().map(|span| span.name()).collect();
+            msg = format!("{}: {}", spans.join(" > "), msg);
+        }
+
+        let source = source_of(metadata.module_path().unwrap_or(metadata.target()));
+        // The window is gone once the app is closing
+        self.sender
+            .send(Loglet::with_source(level, &source, &msg))
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_rotations() {
+        let dir = std::env::temp_dir().join(format!("borkcraft-rotating-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let mut file = RotatingFile::new(dir.join("borkcraft.log"), 20);
+        for n in 0..20 {
+            // The fmt layer writes a whole event at once
+            file.write_all(format!("line number {:02}\n", n).as_bytes())
+                .unwrap();
+        }
+        file.flush().unwrap();
+
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "borkcraft.log",
+                "borkcraft.log.1",
+                "borkcraft.log.2",
+                "borkcraft.log.3"
+            ]
+        );
+        let newest = std::fs::read_to_string(dir.join("borkcraft.log")).unwrap();
+        assert_eq!(newest, "line number 19\n");
+        let previous = std::fs::read_to_string(dir.join("borkcraft.log.1")).unwrap();
+        assert_eq!(previous, "line number 18\n");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,528 @@
+use crate::{
+    url_tools::{aws_public_dns, text_server_port},
+    windows::client_windows::GenericWindow,
+    BorkError,
+};
+use eframe::egui::{ComboBox, Context, Grid, TextEdit, Ui};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+// Where profiles are kept between sessions
+pub const PROFILES_FILE: &str = "borkcraft_profiles.json";
+// Every world gets a folder in here for its exports & caches: worlds/<profile>/<world>
+pub const WORLDS_DIR: &str = "worlds";
+
+// Base urls of the servers a profile talks to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Endpoints {
+    #[serde(rename = "Text")]
+    pub text: String,
+    #[serde(rename = "Image")]
+    pub image: String,
+    // The server that hands out every portal in bunches
+    #[serde(rename = "Portals")]
+    pub portals: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            text: format!("http://{}:{}", aws_public_dns(), text_server_port()),
+            image: "http://localhost:1234".to_string(),
+            portals: "http://localhost:3001".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct World {
+    #[serde(rename = "Name")]
+    pub name: String,
+    // Put in front of every route ("/survival/login"); empty when the server only has the one world
+    #[serde(rename = "Route_Prefix", default)]
+    pub route_prefix: String,
+}
+
+impl World {
+    pub fn new(name: &str, route_prefix: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            route_prefix: route_prefix.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Profile {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Endpoints")]
+    pub endpoints: Endpoints,
+    #[serde(rename = "Username", default)]
+    pub username: String,
+    // Only written to the file when remember_password is on; otherwise it is typed in at login
+    #[serde(rename = "Password", default, skip_serializing_if = "String::is_empty")]
+    pub password: String,
+    // The file is plain text, so saving the password has to be asked for
+    #[serde(rename = "Remember_Password", default)]
+    pub remember_password: bool,
+    #[serde(rename = "Worlds")]
+    pub worlds: Vec<World>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            endpoints: Endpoints::default(),
+            username: String::default(),
+            password: String::default(),
+            remember_password: false,
+            worlds: vec![World::new("world", "")],
+        }
+    }
+}
+
+// What every request, cache & export uses right now
+struct ActiveWorld {
+    endpoints: Endpoints,
+    route_prefix: String,
+    dir: PathBuf,
+}
+
+// Requests are made from other threads, so they read the active world from here
+static ACTIVE: RwLock<Option<ActiveWorld>> = RwLock::new(None);
+
+pub fn file_name(name: &str) -> String {
+    //! Names can be anything, folder names can not
+    name.chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect()
+}
+
+pub fn activate(profile: &Profile, world: &World) {
+    //! Every request, cache & export after this goes to (world)
+    let active = ActiveWorld {
+        endpoints: profile.endpoints.clone(),
+        route_prefix: world.route_prefix.clone(),
+        dir: Path::new(WORLDS_DIR)
+            .join(file_name(&profile.name))
+            .join(file_name(&world.name)),
+    };
+    *ACTIVE.write().unwrap() = Some(active);
+}
+
+pub fn base_url(pick: impl Fn(&Endpoints) -> &String) -> String {
+    //! The (pick)ed endpoint of the active world, with its route prefix
+    match ACTIVE.read().unwrap().as_ref() {
+        Some(active) => format!("{}{}", pick(&active.endpoints), active.route_prefix),
+        None => pick(&Endpoints::default()).clone(),
+    }
+}
+
+pub fn world_path(file: &str) -> String {
+    //! (file) inside of the active world's folder (the folder is made if needed)
+    match ACTIVE.read().unwrap().as_ref() {
+        Some(active) => {
+            std::fs::create_dir_all(&active.dir).ok();
+            active.dir.join(file).to_string_lossy().to_string()
+        }
+        None => file.to_string(),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProfilesFile {
+    #[serde(rename = "Profiles")]
+    profiles: Vec<Profile>,
+    #[serde(rename = "Active_Profile")]
+    active_profile: String,
+    #[serde(rename = "Active_World")]
+    active_world: String,
+}
+
+pub struct Profiles {
+    profiles: Vec<Profile>,
+    // (profile, world) indexes
+    active: (usize, usize),
+    // The profile open in the "Profiles" window
+    editing: usize,
+    // Why the profiles file could not be used; shown once the "Error Messages" window is up
+    load_error: Option<BorkError>,
+    pub display: GenericWindow,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self::open(PROFILES_FILE)
+    }
+}
+
+impl Profiles {
+    fn local() -> Self {
+        Self {
+            profiles: vec![Profile::new("Local")],
+            active: (0, 0),
+            editing: 0,
+            load_error: None,
+            display: GenericWindow::new("Profiles"),
+        }
+    }
+
+    fn open(path: &str) -> Self {
+        //! A missing file just means there is only the local profile; a broken one falls back to it too, with an error
+        if !Path::new(path).exists() {
+            return Self::local();
+        }
+        let mut profiles = Self::local();
+        let result = Self::load(path).and_then(|file| {
+            profiles.profiles = file.profiles;
+            profiles.check()?;
+            profiles.select(&file.active_profile, &file.active_world);
+            Ok(())
+        });
+        if let Err(err) = result {
+            profiles = Self::local();
+            profiles.load_error = Some(BorkError::validation(&format!(
+                "|{}| can not be used, only the Local profile is loaded: {}",
+                path, err
+            )));
+        }
+        profiles
+    }
+
+    fn load(path: &str) -> Result<ProfilesFile, BorkError> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    pub fn take_load_error(&mut self) -> Option<BorkError> {
+        self.load_error.take()
+    }
+
+    fn to_file(&self) -> ProfilesFile {
+        //! Passwords are left out, unless the profile asked to remember its password
+        let profiles = self
+            .profiles
+            .iter()
+            .cloned()
+            .map(|mut profile| {
+                if !profile.remember_password {
+                    profile.password = String::default();
+                }
+                profile
+            })
+            .collect();
+        ProfilesFile {
+            profiles,
+            active_profile: self.active_profile_ref().name.clone(),
+            active_world: self.active_world_ref().name.clone(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), BorkError> {
+        std::fs::write(PROFILES_FILE, serde_json::to_vec_pretty(&self.to_file())?)?;
+
+        Ok(())
+    }
+
+    fn select(&mut self, profile: &str, world: &str) {
+        //! Falls back to the first profile/world when the names are not found
+        let p = self
+            .profiles
+            .iter()
+            .position(|p| p.name == profile)
+            .unwrap_or_default();
+        let w = self.profiles[p]
+            .worlds
+            .iter()
+            .position(|w| w.name == world)
+            .unwrap_or_default();
+        self.active = (p, w);
+    }
+
+    pub fn active_profile_ref(&self) -> &Profile {
+        &self.profiles[self.active.0]
+    }
+    pub fn active_world_ref(&self) -> &World {
+        &self.active_profile_ref().worlds[self.active.1]
+    }
+    pub fn active_label(&self) -> String {
+        format!(
+            "{} / {}",
+            self.active_profile_ref().name,
+            self.active_world_ref().name
+        )
+    }
+
+    pub fn activate_current(&self) {
+        activate(self.active_profile_ref(), self.active_world_ref());
+    }
+
+    fn check(&self) -> Result<(), BorkError> {
+        //! Names are used for folders & to find the active world again, so they must be unique
+        if self.profiles.is_empty() {
+            return Err(BorkError::validation(
+                "There has to be at least one profile",
+            ));
+        }
+        for (index, profile) in self.profiles.iter().enumerate() {
+            if profile.worlds.is_empty() {
+                return Err(BorkError::validation(&format!(
+                    "|{}| needs at least one world",
+                    profile.name
+                )));
+            }
+            if self.profiles[..index]
+                .iter()
+                .any(|p| p.name == profile.name)
+            {
+                return Err(BorkError::validation(&format!(
+                    "There is more than one profile named |{}|",
+                    profile.name
+                )));
+            }
+            for (w, world) in profile.worlds.iter().enumerate() {
+                if profile.worlds[..w]
+                    .iter()
+                    .any(|other| other.name == world.name)
+                {
+                    return Err(BorkError::validation(&format!(
+                        "|{}| has more than one world named |{}|",
+                        profile.name, world.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn world_switcher(profiles: &mut Profiles, ui: &mut Ui) -> bool {
+    //! The world picker in the top panel; true when the user picked another world
+    let mut picked = None;
+    ComboBox::from_label("World")
+        .selected_text(profiles.active_label())
+        .show_ui(ui, |ui| {
+            for (p, profile) in profiles.profiles.iter().enumerate() {
+                for (w, world) in profile.worlds.iter().enumerate() {
+                    let label = format!("{} / {}", profile.name, world.name);
+                    if ui
+                        .selectable_label(profiles.active == (p, w), label)
+                        .clicked()
+                    {
+                        picked = Some((p, w));
+                    }
+                }
+            }
+        });
+    if ui.button("Profiles").clicked() {
+        profiles.display.is_window_open = !profiles.display.is_window_open;
+    }
+
+    match picked {
+        Some(active) if active != profiles.active => {
+            profiles.active = active;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn profile_form(profile: &mut Profile, active_world: Option<usize>, ui: &mut Ui) -> Option<usize> {
+    //! Returns the index of a removed world. (active_world) can not be removed, the app is using it
+    Grid::new("profile form").show(ui, |ui| {
+        ui.label("Name:");
+        ui.text_edit_singleline(&mut profile.name);
+        ui.end_row();
+        ui.label("Text Server:");
+        ui.text_edit_singleline(&mut profile.endpoints.text);
+        ui.end_row();
+        ui.label("Image Server:");
+        ui.text_edit_singleline(&mut profile.endpoints.image);
+        ui.end_row();
+        ui.label("Portal List Server:");
+        ui.text_edit_singleline(&mut profile.endpoints.portals);
+        ui.end_row();
+        ui.label("Username:");
+        ui.text_edit_singleline(&mut profile.username);
+        ui.end_row();
+        ui.label("Password:");
+        ui.add(TextEdit::singleline(&mut profile.password).password(true));
+        ui.end_row();
+        ui.label("");
+        ui.checkbox(&mut profile.remember_password, "Save the password");
+        ui.end_row();
+    });
+    if profile.remember_password {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!(
+                "The password is saved as plain text in |{}|; anybody who can read that file can log in as you",
+                PROFILES_FILE
+            ),
+        );
+    }
+
+    ui.strong("Worlds");
+    let mut remove = None;
+    Grid::new("profile worlds").striped(true).show(ui, |ui| {
+        ui.strong("Name");
+        ui.strong("Route Prefix");
+        ui.end_row();
+        for (index, world) in profile.worlds.iter_mut().enumerate() {
+            ui.text_edit_singleline(&mut world.name);
+            ui.text_edit_singleline(&mut world.route_prefix);
+            if Some(index) != active_world && ui.button("Remove").clicked() {
+                remove = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(index) = remove {
+        profile.worlds.remove(index);
+    }
+    if ui.button("Add World").clicked() {
+        let name = format!("world {}", profile.worlds.len() + 1);
+        profile.worlds.push(World::new(&name, ""));
+    }
+
+    remove
+}
+
+pub fn profiles_window(profiles: &mut Profiles, ctx: &Context) -> Result<bool, BorkError> {
+    //! Shows the "Profiles" window (if its open). Ok(true) when the active world has to be reloaded
+    let mut display = std::mem::take(&mut profiles.display);
+
+    let mut result = Ok(false);
+    display.show_with(ctx, |ui| {
+        let names: Vec<String> = profiles.profiles.iter().map(|p| p.name.clone()).collect();
+        profiles.editing = profiles.editing.min(names.len() - 1);
+        ui.horizontal(|ui| {
+            ComboBox::from_label("Profile")
+                .selected_text(&names[profiles.editing])
+                .show_ui(ui, |ui| {
+                    for (index, name) in names.iter().enumerate() {
+                        ui.selectable_value(&mut profiles.editing, index, name);
+                    }
+                });
+            if ui.button("Add Profile").clicked() {
+                let name = format!("Profile {}", names.len() + 1);
+                profiles.profiles.push(Profile::new(&name));
+                profiles.editing = profiles.profiles.len() - 1;
+            }
+            // The active profile stays, switch to another world first
+            if profiles.editing != profiles.active.0 && ui.button("Remove Profile").clicked() {
+                profiles.profiles.remove(profiles.editing);
+                if profiles.active.0 > profiles.editing {
+                    profiles.active.0 -= 1;
+                }
+                profiles.editing = profiles.active.0;
+            }
+        });
+        ui.separator();
+
+        let (editing, (active_profile, active_world)) = (profiles.editing, profiles.active);
+        let active_world = (editing == active_profile).then_some(active_world);
+        if let Some(removed) = profile_form(&mut profiles.profiles[editing], active_world, ui) {
+            // Worlds after the removed one move up
+            if active_world.is_some_and(|active| active > removed) {
+                profiles.active.1 -= 1;
+            }
+        }
+        ui.separator();
+
+        if ui.button("Save Profiles").clicked() {
+            // Changes to the active profile (new endpoints, a renamed world) only count after a reload
+            result = profiles
+                .check()
+                .and_then(|_| profiles.save())
+                .map(|_| active_world.is_some());
+        }
+    });
+
+    profiles.display = display;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwords_are_only_saved_when_asked_for() {
+        let mut forgetful = Profile::new("Forgetful");
+        forgetful.password = "hunter2".to_string();
+        let mut remembers = Profile::new("Remembers");
+        remembers.password = "hunter3".to_string();
+        remembers.remember_password = true;
+        let profiles = Profiles {
+            profiles: vec![forgetful, remembers],
+            active: (0, 0),
+            editing: 0,
+            load_error: None,
+            display: GenericWindow::default(),
+        };
+
+        let json = serde_json::to_string(&profiles.to_file()).unwrap();
+        assert!(!json.contains("hunter2"));
+        assert!(json.contains("hunter3"));
+
+        // The typed in password is still there for this session
+        assert_eq!(profiles.profiles[0].password, "hunter2");
+    }
+
+    fn open_json(name: &str, json: &str) -> Profiles {
+        let path =
+            std::env::temp_dir().join(format!("borkcraft-{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, json).unwrap();
+        let profiles = Profiles::open(path.to_str().unwrap());
+        std::fs::remove_file(&path).ok();
+        profiles
+    }
+
+    #[test]
+    fn profiles_file_is_checked_on_load() {
+        let endpoints = r#""Endpoints":{"Text":"t","Image":"i","Portals":"p"}"#;
+        let worlds = r#""Worlds":[{"Name":"a","Route_Prefix":""},{"Name":"b","Route_Prefix":"b"}]"#;
+        let good = format!(
+            r#"{{"Profiles":[{{"Name":"Team",{},{}}}],"Active_Profile":"Team","Active_World":"b"}}"#,
+            endpoints, worlds
+        );
+        let mut profiles = open_json("profiles-good", &good);
+        assert!(profiles.take_load_error().is_none());
+        assert_eq!(profiles.active_label(), "Team / b");
+
+        // No worlds would panic as soon as the active world is looked up
+        let no_worlds = format!(
+            r#"{{"Profiles":[{{"Name":"Team",{},"Worlds":[]}}],"Active_Profile":"Team","Active_World":""}}"#,
+            endpoints
+        );
+        let mut profiles = open_json("profiles-no-worlds", &no_worlds);
+        assert!(profiles.take_load_error().is_some());
+        assert_eq!(profiles.active_label(), "Local / world");
+
+        let mut profiles = open_json("profiles-broken", "{not json");
+        assert!(profiles.take_load_error().is_some());
+        assert_eq!(profiles.active_label(), "Local / world");
+
+        let mut profiles = Profiles::open("no such profiles file.json");
+        assert!(profiles.take_load_error().is_none());
+    }
+
+    #[test]
+    fn old_files_without_the_opt_in_load() {
+        let json = r#"{"Name":"Old","Endpoints":{"Text":"t","Image":"i","Portals":"p"},"Password":"pw","Worlds":[]}"#;
+        let profile: Profile = serde_json::from_str(json).unwrap();
+        assert_eq!(profile.password, "pw");
+        assert!(!profile.remember_password);
+    }
+}
@@ -1,23 +1,31 @@
-use std::sync::mpsc::Receiver;
-
 use crate::{
-    err_tools::ErrorX,
-    time_of_day,
+    connection::{call, is_available, Service},
+    thread_tools::{Request, RequestState},
     url_tools::{Routes, Urls},
-    windows::client_windows::{GenericWindow, Loglet},
-    MagicError,
+    windows::client_windows::{GenericWindow, Level, Loglet},
+    BorkError,
 };
+use eframe::egui::Context;
 use serde_derive::{Deserialize, Serialize};
-use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+// How often the server is asked how much session time is left
+const SESSION_POLL: Duration = Duration::from_secs(3);
 
 #[derive(Default)]
 pub struct SessionInfo {
     pub key: String,
     pub session_time: SessionTime,
     pub is_logged_in: bool,
+    // Who logged in; goes into the audit log with every save
+    pub username: String,
     pub access_rights: Vec<String>,
     pub display: GenericWindow,
-    pub receiver: Option<Receiver<(SessionTime, Loglet)>>,
+    poll: Request<SessionTime, BorkError>,
+    last_poll: Option<Instant>,
+    // Set by a failed poll; only the first failure in a row is reported
+    failing: bool,
 }
 
 impl SessionInfo {
@@ -26,36 +34,26 @@ impl SessionInfo {
         instance
     }
 
-    pub fn new(receiver: Option<Receiver<(SessionTime, Loglet)>>) -> Self {
-        Self {
-            key: String::default(),
-            session_time: SessionTime::default(),
-            is_logged_in: bool::default(),
-            access_rights: Vec::default(),
-            display: GenericWindow::default(),
-            receiver,
-        }
-    }
-
     pub fn session_time_to_session_info(
         session_time: SessionTime,
-    ) -> Result<SessionInfo, MagicError> {
+    ) -> Result<SessionInfo, BorkError> {
         Ok(SessionInfo {
             key: session_time.key.clone(),
             session_time,
             is_logged_in: true,
             access_rights: Vec::default(),
             display: GenericWindow::default(),
-            receiver: None,
+            ..Self::default()
         })
     }
 
     pub fn consume(&mut self, si: SessionInfo) {
         //! Takes in a Session Information and properly appends all necessary information
-        //! without overriding the already present maybe Option<Receiver<>>
+        //! without overriding the session poll that is already running
         self.key = si.key;
         self.session_time = si.session_time;
         self.is_logged_in = si.is_logged_in;
+        self.username = si.username;
         self.access_rights = si.access_rights;
         self.display = si.display;
     }
@@ -76,25 +74,48 @@ impl SessionInfo {
         }
     }
 
-    pub fn try_update(&mut self) -> Result<(), MagicError> {
-        //! Check for updates from: (Session Time Updater Thread)
-        let receiver = self.receiver.as_ref().ok_or(ErrorX::new_box(""))?;
-        //for (st, loglet) in receiver.try_recv() {
-        while let Ok((st, loglet)) = receiver.try_recv() {
-            self.is_logged_in = Self::is_session_over(&st.time);
-            self.session_time = st;
-            self.display.log.push(loglet);
-        }
-        Ok(())
-    }
-    pub fn block_update(&mut self) {
-        if let Some(receiver) = &self.receiver {
-            let (st, loglet) = receiver.recv().unwrap();
-            // update user login status
-            self.is_logged_in = Self::is_session_over(&st.time);
-            self.session_time = st;
-            self.display.log.push(loglet);
+    pub fn try_update(&mut self, runtime: &Runtime, ctx: &Context) -> Result<(), BorkError> {
+        //! Take in the last session poll, then start the next one once SESSION_POLL has passed
+        self.poll.poll();
+        let result: Result<(), BorkError> = match self.poll.state_ref() {
+            RequestState::Success(_) => {
+                let st = self.poll.take_ready().unwrap_or_default();
+                let loglet = Loglet::new(Level::Info, &st.time.formatted());
+
+                // The key to use on the next poll
+                self.key = st.key.clone();
+                self.is_logged_in = Self::is_session_over(&st.time);
+                self.session_time = st;
+                self.display.log.push(loglet);
+                self.failing = false;
+                Ok(())
+            }
+            RequestState::Error(err) => {
+                let err = err.clone();
+                self.poll.reset();
+                match std::mem::replace(&mut self.failing, true) {
+                    true => Ok(()),
+                    false => Err(err),
+                }
+            }
+            _ => Ok(()),
+        };
+
+        let is_due = self
+            .last_poll
+            .is_none_or(|last_poll| last_poll.elapsed() >= SESSION_POLL);
+        // Polling stops while the text server is down, its circuit breaker lets one through after a cooldown
+        if self.poll.is_idle() && is_due && is_available(Service::Text) {
+            let key = self.key.clone();
+            self.poll.spawn(runtime, ctx, "Session Poll", move |_| {
+                call(Service::Text, true, || current_session_time(&key))
+            });
+            self.last_poll = Some(Instant::now());
         }
+        // Nothing else wakes the ui when the next poll is due
+        ctx.request_repaint_after(SESSION_POLL);
+
+        result
     }
 }
 
@@ -129,23 +150,12 @@ fn fetch_session_time(key: &String) -> Result<ureq::Response, ureq::Error> {
     ureq::post(&Urls::default(Routes::SessionTimeLeft)).send_json(Key { key })
 }
 
-pub fn current_session_time(
-    sender: &Sender<(SessionTime, Loglet)>,
-    validation_key: String,
-) -> Result<String, MagicError> {
+pub fn current_session_time(validation_key: &String) -> Result<SessionTime, BorkError> {
     // Get the sesssion time from the server
-    let response = fetch_session_time(&validation_key)?;
+    let response = fetch_session_time(validation_key)?;
 
-    // Convert response to time object
+    // Convert response to time object; it also has the key to use on the next poll
     let session_time: SessionTime = response.into_json()?;
 
-    // Create information to be sent by (Sender)
-    let loglet = Loglet::new("Update", &session_time.time.formatted(), &time_of_day());
-    let validation_key = session_time.key.clone();
-
-    // Send
-    sender.send((session_time, loglet)).unwrap();
-
-    // Return Key to be used on next iteration of calling scope's loop
-    Ok(validation_key)
+    Ok(session_time)
 }
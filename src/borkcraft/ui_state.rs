@@ -0,0 +1,43 @@
+use crate::windows::{
+    client_windows::{GenericWindow, Level},
+    settings::Theme,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// What eframe keeps between restarts (BorkCraft::save); panel sizes & table widths are in egui's own memory
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct UiState {
+    // The base page ("Login", "Nether Portals")
+    #[serde(rename = "Page")]
+    pub page: String,
+    // Keyed by window name
+    #[serde(rename = "Open_Windows")]
+    pub open_windows: HashMap<String, bool>,
+    // The last selected pair's id, keyed by "profile / world"
+    #[serde(rename = "Selected_Pairs")]
+    pub selected_pairs: HashMap<String, i32>,
+    #[serde(rename = "Theme")]
+    pub theme: Theme,
+    #[serde(rename = "Log_Level")]
+    pub log_level: Option<Level>,
+}
+
+impl UiState {
+    pub fn remember_windows(&mut self, windows: Vec<&mut GenericWindow>) {
+        windows.into_iter().for_each(|window| {
+            self.open_windows
+                .insert(window.get_name(), window.is_window_open);
+        });
+    }
+
+    pub fn restore_windows(&self, windows: Vec<&mut GenericWindow>) {
+        //! Windows that were never seen keep their default
+        windows.into_iter().for_each(|window| {
+            if let Some(is_open) = self.open_windows.get(&window.name) {
+                window.is_window_open = *is_open;
+            }
+        });
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{profiles::world_path, BorkError};
+use serde_derive::{Deserialize, Serialize};
+use std::path::Path;
+
+// Where custom dimensions are kept between sessions (one file per world)
+pub const DIMENSIONS_FILE: &str = "borkcraft_dimensions.json";
+
+// Any dimension a portal (or gateway) can be in; Realm covers the two sides every portal has
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Dimension {
+    #[serde(rename = "Id")]
+    pub id: String,
+    #[serde(rename = "Name")]
+    pub name: String,
+    // How many overworld blocks one block of this dimension covers (same as a dimension_type's coordinate_scale)
+    #[serde(rename = "Scale")]
+    pub scale: f64,
+    #[serde(rename = "Min_Y")]
+    pub min_y: i32,
+    #[serde(rename = "Max_Y")]
+    pub max_y: i32,
+}
+
+impl Dimension {
+    pub fn new(id: &str, name: &str, scale: f64, min_y: i32, max_y: i32) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            scale,
+            min_y,
+            max_y,
+        }
+    }
+
+    pub fn overworld() -> Self {
+        Self::new("minecraft:overworld", "Overworld", 1.0, -64, 319)
+    }
+    pub fn nether() -> Self {
+        Self::new("minecraft:the_nether", "Nether", 8.0, 0, 255)
+    }
+    pub fn the_end() -> Self {
+        Self::new("minecraft:the_end", "The End", 1.0, 0, 255)
+    }
+
+    pub fn build_limits(&self) -> (i32, i32) {
+        //! Lowest and highest y-coordinate a block can be placed at (min, max)
+        (self.min_y, self.max_y)
+    }
+
+    pub fn path(&self) -> &str {
+        //! "the_nether" from "minecraft:the_nether"
+        self.id.split_once(':').map_or(&self.id, |(_, path)| path)
+    }
+
+    pub fn convert_cords(&self, to: &Dimension, cords: (i32, i32, i32)) -> (i32, i32, i32) {
+        //! Where (cords) in this dimension line up in (to); y is kept inside the build limits
+        let (x, y, z) = cords;
+        let convert = |cord: i32| (cord as f64 * self.scale / to.scale).floor() as i32;
+        (convert(x), y.clamp(to.min_y, to.max_y), convert(z))
+    }
+}
+
+// Every dimension the client knows about: the built in ones first, then the custom ones
+#[derive(Clone)]
+pub struct Dimensions {
+    custom: Vec<Dimension>,
+}
+
+impl Default for Dimensions {
+    fn default() -> Self {
+        // A missing or broken file just means there are no custom dimensions yet
+        Self {
+            custom: Self::load(&world_path(DIMENSIONS_FILE)).unwrap_or_default(),
+        }
+    }
+}
+
+impl Dimensions {
+    fn load(path: &str) -> Result<Vec<Dimension>, BorkError> {
+        if !Path::new(path).exists() {
+            return Ok(Vec::new());
+        }
+        let custom = serde_json::from_slice(&std::fs::read(path)?)?;
+
+        Ok(custom)
+    }
+
+    pub fn save(&self) -> Result<(), BorkError> {
+        std::fs::write(
+            world_path(DIMENSIONS_FILE),
+            serde_json::to_vec_pretty(&self.custom)?,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn primary() -> [Dimension; 2] {
+        //! The two sides every portal has
+        [Dimension::overworld(), Dimension::nether()]
+    }
+
+    pub fn extra(&self) -> Vec<Dimension> {
+        //! Dimensions that are not one of the two sides of a portal
+        let mut extra = vec![Dimension::the_end()];
+        extra.extend(self.custom.iter().cloned());
+        extra
+    }
+
+    pub fn all(&self) -> Vec<Dimension> {
+        let mut all = Self::primary().to_vec();
+        all.extend(self.extra());
+        all
+    }
+
+    pub fn get(&self, id: &str) -> Option<Dimension> {
+        self.all().into_iter().find(|dimension| dimension.id == id)
+    }
+
+    pub fn get_or_unknown(&self, id: &str) -> Dimension {
+        //! A side can outlive its custom dimension; it then gets overworld limits & scale under its id
+        self.get(id)
+            .unwrap_or_else(|| Dimension::new(id, id, 1.0, -64, 319))
+    }
+
+    pub fn find(&self, text: &str) -> Option<Dimension> {
+        //! Match a dimension by its id, its path ("the_nether") or its name
+        let text = text.trim().to_lowercase();
+        self.all().into_iter().find(|dimension| {
+            dimension.id == text
+                || dimension.path() == text
+                || dimension.name.to_lowercase() == text
+        })
+    }
+
+    pub fn add(&mut self, dimension: Dimension) -> Result<(), BorkError> {
+        if self.get(&dimension.id).is_some() {
+            return Err(BorkError::validation(&format!(
+                "|{}| already exists",
+                dimension.id
+            )));
+        }
+        self.custom.push(dimension);
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        //! Only custom dimensions can be removed
+        self.custom.retain(|dimension| dimension.id != id);
+    }
+
+    pub fn custom_ref(&self) -> &Vec<Dimension> {
+        &self.custom
+    }
+}
@@ -1,4 +1,4 @@
-use crate::thread_tools::SPromise;
+use crate::{thread_tools::Request, BorkError};
 use egui_extras::RetainedImage;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
@@ -35,8 +35,22 @@ impl Clone for ImageDetails {
 }
 
 pub type ImageDetailsList = HashMap<String, ImageDetails>;
-type F = Box<dyn std::future::Future<Output = ()> + Unpin>;
-pub type ImagerList = BTreeMap<String, SPromise<Imager, F>>;
+pub type ImagerList = BTreeMap<String, ImageSlot>;
+
+// One image of a portal; its details are known before the image itself is downloaded
+pub struct ImageSlot {
+    pub details: ImageDetails,
+    pub request: Request<Imager, BorkError>,
+}
+
+impl ImageSlot {
+    pub fn new(details: ImageDetails) -> Self {
+        Self {
+            details,
+            request: Request::default(),
+        }
+    }
+}
 //////
 
 #[derive(Default)]
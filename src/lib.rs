@@ -1,23 +1,24 @@
 mod windows;
 
 pub mod borkcraft;
+pub mod dimension;
 pub mod images;
+pub mod nbt;
 pub mod pages;
 
 pub use borkcraft::*;
 use chrono::{Timelike, Utc};
-use std::collections::HashMap;
-use std::sync::mpsc::Sender;
+use thread_tools::WakingSender;
 use windows::error_messages::ErrorMessage;
 
 use crate::windows::client_windows::Loglet;
+use dimension::Dimension;
 
 // Custom Types (For convenience)
-type MagicError = Box<dyn std::error::Error>;
-// type MagicSendError = Box<dyn std::error::Error + Send>;
+use err_tools::BorkError;
 
 // lifetime sillyness
-//fn subfn<'a, 'b, T>(t: &'a Result<T, MagicError>, f: impl FnOnce(&'b MagicError))
+//fn subfn<'a, 'b, T>(t: &'a Result<T, BorkError>, f: impl FnOnce(&'b BorkError))
 //where
 //'a: 'b,
 //{
@@ -27,6 +28,7 @@ type MagicError = Box<dyn std::error::Error>;
 //}
 
 // Enums
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Realm {
     Overworld,
     Nether,
@@ -39,6 +41,33 @@ impl Realm {
             Self::Nether => nether,
         }
     }
+    pub fn name(&self) -> &'static str {
+        self.matcher("overworld", "nether")
+    }
+    pub fn dimension(&self) -> Dimension {
+        //! The dimension this side of a portal is in
+        self.matcher(Dimension::overworld(), Dimension::nether())
+    }
+    pub fn from_dimension(dimension: &Dimension) -> Option<Realm> {
+        //! Only the overworld & nether are a Realm; every other dimension is an extra side
+        Self::from_id(&dimension.id)
+    }
+    pub fn from_id(dimension_id: &str) -> Option<Realm> {
+        [Realm::Overworld, Realm::Nether]
+            .into_iter()
+            .find(|realm| realm.dimension().id == dimension_id)
+    }
+    pub fn build_limits(&self) -> (i32, i32) {
+        //! Lowest and highest y-coordinate a block can be placed at (min, max)
+        self.dimension().build_limits()
+    }
+    pub fn other(&self) -> Realm {
+        self.matcher(Realm::Nether, Realm::Overworld)
+    }
+    pub fn convert_cords(&self, to: &Realm, cords: (i32, i32, i32)) -> (i32, i32, i32) {
+        //! Where (cords) in this realm line up in the realm (to); y is kept inside the build limits
+        self.dimension().convert_cords(&to.dimension(), cords)
+    }
 }
 
 // Traits
@@ -60,26 +89,28 @@ pub fn option(mut f: impl FnMut() -> Option<()>) -> Option<()> {
 pub trait HandleError<T> {
     fn consume_error(self, err_msg: &mut ErrorMessage);
 
-    fn otherwise(self, f: impl FnOnce(&MagicError)) -> Self;
+    fn otherwise(self, f: impl FnOnce(&BorkError)) -> Self;
 
-    fn send_error(self, err_msg_sender: Sender<Loglet>) -> Self;
+    fn send_error(self, err_msg_sender: WakingSender<Loglet>) -> Self;
 }
 
-impl<T> HandleError<T> for Result<T, MagicError> {
+impl<T> HandleError<T> for Result<T, BorkError> {
+    #[track_caller]
     fn consume_error(self, err_msg: &mut ErrorMessage) {
         if let Err(err) = self {
-            err_msg.push_err(&err.to_string())
+            err_msg.push_error(err)
         }
     }
-    fn send_error(self, err_msg_sender: Sender<Loglet>) -> Self {
+    #[track_caller]
+    fn send_error(self, err_msg_sender: WakingSender<Loglet>) -> Self {
         if let Err(err) = &self {
-            err_msg_sender
-                .send(Loglet::err_s(&err.to_string()))
-                .unwrap()
+            let loglet = Loglet::err(err.clone());
+            logging::trace_loglet(&loglet);
+            err_msg_sender.send(loglet).unwrap()
         }
         return self;
     }
-    fn otherwise(self, f: impl FnOnce(&MagicError)) -> Self {
+    fn otherwise(self, f: impl FnOnce(&BorkError)) -> Self {
         if let Err(err) = self.as_ref() {
             f(err);
         }
@@ -88,31 +119,15 @@ impl<T> HandleError<T> for Result<T, MagicError> {
 }
 
 pub trait StatusCheck {
-    fn status_check(self) -> Result<ureq::Response, ErrorX>;
+    fn status_check(self) -> Result<ureq::Response, BorkError>;
 }
 
-use crate::err_tools::ErrorX;
-fn reason(resp: ureq::Response) -> String {
-    println!("passed");
-    let msg: HashMap<String, String> = serde_json::from_reader(resp.into_reader()).unwrap();
-    format!("Reason: -> |{}|\n", msg["error"])
-}
 impl StatusCheck for ureq::Response {
-    fn status_check(self) -> Result<ureq::Response, ErrorX> {
-        let status = self.status();
-        let pattern = || format!("status code: -> |{}|", status);
-        match status {
-            202 => Ok(self),
-            403 => Err(ErrorX::new(&format!(
-                "Request Denied... {}\n{}",
-                pattern(),
-                reason(self)
-            ))),
-            _ => Err(ErrorX::new(&format!(
-                "Request was not aproved: {}\nReason: -> |{}|",
-                pattern(),
-                reason(self)
-            ))),
+    fn status_check(self) -> Result<ureq::Response, BorkError> {
+        //! Any 2xx is a success; everything else carries the server's reason (when it gave one)
+        match self.status() {
+            200..=299 => Ok(self),
+            _ => Err(BorkError::from_response(self)),
         }
     }
 }
@@ -130,14 +145,14 @@ fn get_tokio_runtime() -> tokio::runtime::Runtime {
 fn _try_access<T>(
     try_me: &std::sync::Arc<std::sync::Mutex<T>>,
     f: impl FnOnce(std::sync::MutexGuard<T>),
-) -> Result<(), MagicError> {
+) -> Result<(), BorkError> {
     match try_me.try_lock() {
         Ok(access) => {
             f(access);
             Ok(())
             // Do magic
         }
-        Err(_) => Err(crate::err_tools::ErrorX::new_box(
+        Err(_) => Err(crate::BorkError::validation(
             "try_access was used currently, try again later...",
         )),
     }
@@ -146,14 +161,14 @@ fn _try_access<T>(
 fn _try_access_experimental<T>(
     try_me: &std::sync::Arc<std::sync::Mutex<T>>,
     f: impl FnOnce(std::sync::MutexGuard<T>),
-) -> Result<std::sync::Arc<std::sync::Mutex<T>>, MagicError> {
+) -> Result<std::sync::Arc<std::sync::Mutex<T>>, BorkError> {
     match try_me.try_lock() {
         Ok(access) => {
             f(access);
             return Ok(std::sync::Arc::clone(try_me));
             // Do magic
         }
-        Err(_) => Err(crate::err_tools::ErrorX::new_box(
+        Err(_) => Err(crate::BorkError::validation(
             "try_access was used currently, try again later...",
         )),
     }
@@ -166,10 +181,59 @@ fn time_of_day() -> String {
 }
 
 pub mod thread_tools {
+    use crate::BorkError;
+    use eframe::egui::Context;
     use std::{
         future::Future,
-        sync::mpsc::{channel, Receiver, Sender},
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            mpsc::{channel, Receiver, SendError, Sender},
+            Arc, Mutex, OnceLock,
+        },
+        time::Instant,
     };
+    use tokio::runtime::Runtime;
+    use tracing::Span;
+
+    // The ui's Context, so that other threads can wake it up. Set once when the app starts
+    static REPAINT: OnceLock<Context> = OnceLock::new();
+
+    pub fn set_repaint(ctx: &Context) {
+        REPAINT.set(ctx.clone()).ok();
+    }
+
+    pub fn wake() {
+        //! Ask for a new frame; the ui only repaints on input or when something has arrived
+        if let Some(ctx) = REPAINT.get() {
+            ctx.request_repaint();
+        }
+    }
+
+    // A Sender that wakes the ui up, so whatever it sent is shown right away
+    pub struct WakingSender<T> {
+        sender: Sender<T>,
+    }
+
+    impl<T> Clone for WakingSender<T> {
+        fn clone(&self) -> Self {
+            Self {
+                sender: self.sender.clone(),
+            }
+        }
+    }
+
+    impl<T> WakingSender<T> {
+        pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+            let result = self.sender.send(value);
+            wake();
+            result
+        }
+    }
+
+    pub fn waking_channel<T>() -> (WakingSender<T>, Receiver<T>) {
+        let (sender, receiver) = channel();
+        (WakingSender { sender }, receiver)
+    }
 
     pub struct Downloader<T: Default> {
         inner: T,
@@ -177,8 +241,8 @@ pub mod thread_tools {
     }
 
     impl<T: Default> Downloader<T> {
-        pub fn new() -> (Downloader<T>, Sender<T>) {
-            let (sender, receiver) = channel();
+        pub fn new() -> (Downloader<T>, WakingSender<T>) {
+            let (sender, receiver) = waking_channel();
             let downloader = Self {
                 inner: T::default(),
                 receiver,
@@ -208,7 +272,7 @@ pub mod thread_tools {
         uploader: Uploader<T>,
         uploader_receiver: Receiver<T>,
         downloader: Downloader<T>,
-        downloader_sender: Sender<T>,
+        downloader_sender: WakingSender<T>,
     }
     impl<T: Default> Communicator<T> {
         pub fn new() -> Communicator<T> {
@@ -225,7 +289,7 @@ pub mod thread_tools {
             communicator
         }
         // Senders
-        pub fn downloader_sender_clone(&self) -> Sender<T> {
+        pub fn downloader_sender_clone(&self) -> WakingSender<T> {
             self.downloader_sender.clone()
         }
 
@@ -300,19 +364,6 @@ pub mod thread_tools {
         pub fn sender_ref(&self) -> &Option<poll_promise::Sender<T>> {
             &self.sender
         }
-        pub fn take_sender(&mut self) -> Option<poll_promise::Sender<T>> {
-            // This stupid freaking lib will just throw a panic if the promise is dropped...
-            // Never use this again!
-            // Just check if the promise exists first LOL THEN check/take the sender
-            //self.some_promise.as_ref()?;
-            if let Some(_) = self.some_promise {
-                println!("There is a promise here bois!")
-            }
-            if let Some(sender) = self.sender.take() {
-                return Some(sender);
-            }
-            None
-        }
         pub fn add_value(&mut self, value: T) {
             self.value = value
         }
@@ -326,6 +377,16 @@ pub mod thread_tools {
         pub fn ref_value(&self) -> &T {
             &self.value
         }
+        pub fn take_ready(&mut self) -> Option<T> {
+            //! Take the value out of a finished promise; an unfinished promise is left alone
+            match self.some_promise.take()?.try_take() {
+                Ok(value) => Some(value),
+                Err(promise) => {
+                    self.some_promise = Some(promise);
+                    None
+                }
+            }
+        }
 
         // Test Values
         pub fn quick_value(&self) -> &T {
@@ -348,6 +409,331 @@ pub mod thread_tools {
     }
     // Store the sender, and receiver somewhere in the struct?
     // pass the sender @ struct creation to the (F: Future)
+
+    // Where a Request is at
+    pub enum RequestState<T, E> {
+        Idle,
+        Pending,
+        // How much is done: 0.0 to 1.0
+        Progress(f32),
+        Success(T),
+        Error(E),
+    }
+
+    enum Update<T, E> {
+        Progress(f32),
+        Done(Result<T, E>),
+        // Cancelled from somewhere else (the "Tasks" window)
+        Cancelled,
+    }
+
+    // One job running in the background, as the "Tasks" window lists it
+    #[derive(Clone)]
+    pub struct TaskInfo {
+        pub id: u64,
+        pub name: String,
+        pub started: Instant,
+        // None until the job says how far it is
+        pub progress: Option<f32>,
+        cancelled: Arc<AtomicBool>,
+    }
+
+    impl TaskInfo {
+        pub fn cancel(&self) {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+        pub fn is_cancelled(&self) -> bool {
+            self.cancelled.load(Ordering::Relaxed)
+        }
+    }
+
+    // Jobs run on other threads, so they (un)list themselves in here
+    static TASKS: Mutex<Vec<TaskInfo>> = Mutex::new(Vec::new());
+    static NEXT_TASK: AtomicU64 = AtomicU64::new(0);
+
+    pub fn tasks() -> Vec<TaskInfo> {
+        //! Every job that is still running, oldest first
+        TASKS.lock().unwrap().clone()
+    }
+
+    pub fn cancel_all_tasks() {
+        TASKS.lock().unwrap().iter().for_each(|task| task.cancel());
+    }
+
+    // Keeps a job listed in the task registry until it is dropped
+    pub struct TaskGuard {
+        id: u64,
+        cancelled: Arc<AtomicBool>,
+        // Every event the job makes is logged under this
+        span: Span,
+    }
+
+    impl TaskGuard {
+        pub fn track(name: &str) -> Self {
+            //! List a job that was spawned by hand; keep this alive for as long as the job runs
+            Self::with_flag(name, Arc::new(AtomicBool::new(false)))
+        }
+        fn with_flag(name: &str, cancelled: Arc<AtomicBool>) -> Self {
+            let id = NEXT_TASK.fetch_add(1, Ordering::Relaxed);
+            TASKS.lock().unwrap().push(TaskInfo {
+                id,
+                name: name.to_string(),
+                started: Instant::now(),
+                progress: None,
+                cancelled: Arc::clone(&cancelled),
+            });
+            wake();
+            let span = tracing::info_span!("task", id, name);
+            tracing::debug!(parent: &span, "Started");
+            Self {
+                id,
+                cancelled,
+                span,
+            }
+        }
+        pub fn span(&self) -> &Span {
+            &self.span
+        }
+        pub fn progress(&self, done: f32) {
+            let mut tasks = TASKS.lock().unwrap();
+            if let Some(task) = tasks.iter_mut().find(|task| task.id == self.id) {
+                task.progress = Some(done.clamp(0.0, 1.0));
+            }
+            wake();
+        }
+        pub fn is_cancelled(&self) -> bool {
+            self.cancelled.load(Ordering::Relaxed)
+        }
+    }
+
+    impl Drop for TaskGuard {
+        fn drop(&mut self) {
+            let mut tasks = TASKS.lock().unwrap();
+            if let Some(task) = tasks.iter().find(|task| task.id == self.id) {
+                let elapsed_ms = task.started.elapsed().as_millis() as u64;
+                match self.is_cancelled() {
+                    true => tracing::info!(parent: &self.span, elapsed_ms, "Cancelled"),
+                    false => tracing::debug!(parent: &self.span, elapsed_ms, "Finished"),
+                }
+            }
+            tasks.retain(|task| task.id != self.id);
+            drop(tasks);
+            wake();
+        }
+    }
+
+    // The job's end of a Request
+    pub struct Responder<T, E> {
+        sender: Sender<Update<T, E>>,
+        task: TaskGuard,
+        ctx: Context,
+    }
+
+    impl<T, E> Responder<T, E> {
+        pub fn progress(&self, done: f32) {
+            self.task.progress(done);
+            self.sender
+                .send(Update::Progress(done.clamp(0.0, 1.0)))
+                .ok();
+            self.ctx.request_repaint();
+        }
+        pub fn is_cancelled(&self) -> bool {
+            //! Long jobs should check this between steps & stop early
+            self.task.is_cancelled()
+        }
+        pub fn check_cancelled(&self) -> Result<(), E>
+        where
+            E: From<BorkError>,
+        {
+            //! Err() once the job was cancelled, for `?` between steps. The Request never sees that Err()
+            match self.is_cancelled() {
+                true => Err(E::from(BorkError::cancelled())),
+                false => Ok(()),
+            }
+        }
+        fn send(&self, update: Update<T, E>) {
+            // Nobody listening (cancelled, or the Request was dropped) is fine
+            self.sender.send(update).ok();
+            self.ctx.request_repaint();
+        }
+    }
+
+    type Job<T, E> = Arc<dyn Fn(&Responder<T, E>) -> Result<T, E> + Send + Sync>;
+
+    // A job that runs on the tokio runtime & wakes the ui when it is done
+    pub struct Request<T, E> {
+        state: RequestState<T, E>,
+        receiver: Option<Receiver<Update<T, E>>>,
+        cancelled: Arc<AtomicBool>,
+        // Kept so the job can be retried
+        job: Option<Job<T, E>>,
+        // What the "Tasks" window calls it
+        name: String,
+        attempts: u32,
+    }
+
+    impl<T, E> Default for Request<T, E> {
+        fn default() -> Self {
+            Self {
+                state: RequestState::Idle,
+                receiver: None,
+                cancelled: Arc::new(AtomicBool::new(false)),
+                job: None,
+                name: String::default(),
+                attempts: 0,
+            }
+        }
+    }
+
+    impl<T: Send + 'static, E: From<BorkError> + Send + 'static> Request<T, E> {
+        pub fn spawn(
+            &mut self,
+            runtime: &Runtime,
+            ctx: &Context,
+            name: &str,
+            job: impl Fn(&Responder<T, E>) -> Result<T, E> + Send + Sync + 'static,
+        ) {
+            //! Start (job) & list it as (name) in the task registry; a job that is still running is cancelled first
+            self.job = Some(Arc::new(job));
+            self.name = name.to_string();
+            self.attempts = 0;
+            self.run(runtime, ctx);
+        }
+        pub fn retry(&mut self, runtime: &Runtime, ctx: &Context) -> bool {
+            //! Run the last job again. false == there is no job or it is still running
+            if self.job.is_none() || self.is_pending() {
+                return false;
+            }
+            self.run(runtime, ctx);
+            true
+        }
+        fn run(&mut self, runtime: &Runtime, ctx: &Context) {
+            self.cancel();
+            let job = match &self.job {
+                Some(job) => Arc::clone(job),
+                None => return,
+            };
+            let (sender, receiver) = channel();
+            self.cancelled = Arc::new(AtomicBool::new(false));
+            let responder = Responder {
+                sender,
+                task: TaskGuard::with_flag(&self.name, Arc::clone(&self.cancelled)),
+                ctx: ctx.clone(),
+            };
+            self.receiver = Some(receiver);
+            self.state = RequestState::Pending;
+            self.attempts += 1;
+
+            // Jobs block (ureq, waiting between retries), so they get a thread of their own
+            runtime.spawn_blocking(move || {
+                let _span = responder.task.span().clone().entered();
+                let result = job(&responder);
+                match responder.is_cancelled() {
+                    // Only heard when the Request itself did not cancel
+                    true => responder.send(Update::Cancelled),
+                    false => responder.send(Update::Done(result)),
+                }
+            });
+        }
+    }
+
+    impl<T, E: From<BorkError>> Request<T, E> {
+        pub fn cancel(&mut self) {
+            //! Tell the job to stop; anything it still sends is ignored
+            self.cancelled.store(true, Ordering::Relaxed);
+            self.receiver = None;
+            if self.is_pending() {
+                self.state = RequestState::Idle;
+            }
+        }
+        pub fn poll(&mut self) -> bool {
+            //! Take in whatever the job sent. true == it finished just now
+            let receiver = match self.receiver.take() {
+                Some(receiver) => receiver,
+                None => return false,
+            };
+            loop {
+                match receiver.try_recv() {
+                    Ok(Update::Progress(done)) => self.state = RequestState::Progress(done),
+                    Ok(Update::Done(result)) => {
+                        self.state = match result {
+                            Ok(value) => RequestState::Success(value),
+                            Err(err) => RequestState::Error(err),
+                        };
+                        return true;
+                    }
+                    Ok(Update::Cancelled) => {
+                        self.state = RequestState::Idle;
+                        return false;
+                    }
+                    Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                    // The job panicked before it could finish
+                    Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                        self.state = RequestState::Error(E::from(BorkError::Aborted(format!(
+                            "|{}| stopped before it finished",
+                            self.name
+                        ))));
+                        return true;
+                    }
+                }
+            }
+            self.receiver = Some(receiver);
+            false
+        }
+        pub fn reset(&mut self) {
+            self.cancel();
+            self.state = RequestState::Idle;
+        }
+
+        // Getters
+        pub fn state_ref(&self) -> &RequestState<T, E> {
+            &self.state
+        }
+        pub fn is_pending(&self) -> bool {
+            matches!(
+                self.state,
+                RequestState::Pending | RequestState::Progress(_)
+            )
+        }
+        pub fn is_idle(&self) -> bool {
+            matches!(self.state, RequestState::Idle)
+        }
+        pub fn ready(&self) -> Option<&T> {
+            match &self.state {
+                RequestState::Success(value) => Some(value),
+                _ => None,
+            }
+        }
+        pub fn error_ref(&self) -> Option<&E> {
+            match &self.state {
+                RequestState::Error(err) => Some(err),
+                _ => None,
+            }
+        }
+        pub fn take_ready(&mut self) -> Option<T> {
+            //! Take the value of a finished job; the Request goes back to Idle
+            match std::mem::replace(&mut self.state, RequestState::Idle) {
+                RequestState::Success(value) => Some(value),
+                state => {
+                    self.state = state;
+                    None
+                }
+            }
+        }
+        pub fn attempts(&self) -> u32 {
+            self.attempts
+        }
+    }
+
+    impl<T, E: From<BorkError> + Clone> Request<T, E> {
+        pub fn poll_error(&mut self) -> Result<(), E> {
+            //! poll(), but Err() once, when the job just failed; for windows that hand errors to "Error Messages"
+            match self.poll() {
+                true => self.error_ref().cloned().map_or(Ok(()), Err),
+                false => Ok(()),
+            }
+        }
+    }
 }
 // Modules
 mod increment {
@@ -396,55 +782,174 @@ pub mod string_tools {
 }
 
 pub mod err_tools {
-    #[derive(Debug)]
-    pub struct ErrorX {
-        details: String,
+    use std::collections::HashMap;
+
+    // Anything longer than this from a server is cut off, so an html error page does not fill the window
+    const MAX_REASON: usize = 300;
+
+    // Every way something can go wrong in BorkCraft
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum BorkError {
+        // The server could not be reached, or the connection broke
+        Network(String),
+        // The server answered, but not with a 2xx. (reason) is its error body, when it sent one
+        Http { status: u16, reason: Option<String> },
+        // Not logged in, the session ran out, or not allowed to do that
+        Auth(String),
+        // Something could not be read as what it should be (json, images, nbt...)
+        Decode(String),
+        // What was asked for does not make sense, or there is nothing to do
+        Validation(String),
+        // Reading or writing a local file failed
+        Io(String),
+        // A background job was cancelled, or died before it could finish
+        Aborted(String),
     }
 
-    impl ErrorX {
-        pub fn new(msg: &str) -> ErrorX {
-            ErrorX {
-                details: msg.to_string(),
+    impl BorkError {
+        pub fn validation(msg: &str) -> Self {
+            Self::Validation(msg.to_string())
+        }
+        pub fn decode(msg: &str) -> Self {
+            Self::Decode(msg.to_string())
+        }
+        pub fn cancelled() -> Self {
+            Self::Aborted("Cancelled".to_string())
+        }
+
+        pub fn from_response(response: ureq::Response) -> Self {
+            //! A response that was not a success; 401 & 403 are Auth, everything else is Http
+            let status = response.status();
+            let reason = Self::reason(response);
+            match status {
+                401 | 403 => Self::Auth(reason.unwrap_or_else(|| "Request Denied...".to_string())),
+                _ => Self::Http { status, reason },
             }
         }
-        pub fn new_box(msg: &str) -> Box<ErrorX> {
-            Box::new(ErrorX {
-                details: msg.to_string(),
-            })
+
+        fn reason(response: ureq::Response) -> Option<String> {
+            //! The server sends {"error": "..."}; anything else is kept as the plain text it is
+            let body = response.into_string().ok()?;
+            let reason = match serde_json::from_str::<HashMap<String, String>>(&body) {
+                Ok(json) => json.get("error")?.clone(),
+                Err(_) => body.trim().chars().take(MAX_REASON).collect(),
+            };
+            (!reason.is_empty()).then_some(reason)
+        }
+
+        pub fn kind(&self) -> &'static str {
+            match self {
+                Self::Network(_) => "Network",
+                Self::Http { .. } => "Http",
+                Self::Auth(_) => "Auth",
+                Self::Decode(_) => "Decode",
+                Self::Validation(_) => "Validation",
+                Self::Io(_) => "Io",
+                Self::Aborted(_) => "Aborted",
+            }
+        }
+
+        pub fn is_retriable(&self) -> bool {
+            //! Could trying the exact same thing again work?
+            match self {
+                Self::Network(_) => true,
+                // Timeouts, rate limits & the server having a bad time
+                Self::Http { status, .. } => matches!(status, 408 | 429 | 500..=599),
+                _ => false,
+            }
+        }
+
+        pub fn user_message(&self) -> String {
+            match self {
+                Self::Network(msg) => format!("Could not reach the server: {}", msg),
+                Self::Http {
+                    status,
+                    reason: Some(reason),
+                } => format!(
+                    "Request was not aproved: status code: -> |{}|\nReason: -> |{}|",
+                    status, reason
+                ),
+                Self::Http { status, .. } => {
+                    format!("Request was not aproved: status code: -> |{}|", status)
+                }
+                Self::Auth(msg) => format!("Request Denied... {}", msg),
+                Self::Decode(msg) => format!("Could not read it: {}", msg),
+                Self::Validation(msg) => msg.clone(),
+                Self::Io(msg) => format!("File trouble: {}", msg),
+                Self::Aborted(msg) => format!("Stopped: {}", msg),
+            }
         }
     }
 
-    impl std::fmt::Display for ErrorX {
+    impl std::fmt::Display for BorkError {
         fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-            write!(f, "{}", self.details)
+            write!(f, "{}", self.user_message())
+        }
+    }
+
+    impl std::error::Error for BorkError {}
+
+    impl From<ureq::Error> for BorkError {
+        fn from(err: ureq::Error) -> Self {
+            match err {
+                ureq::Error::Status(_, response) => Self::from_response(response),
+                ureq::Error::Transport(transport) => Self::Network(transport.to_string()),
+            }
         }
     }
 
-    impl std::error::Error for ErrorX {
-        fn description(&self) -> &str {
-            &self.details
+    impl From<std::io::Error> for BorkError {
+        fn from(err: std::io::Error) -> Self {
+            Self::Io(err.to_string())
+        }
+    }
+
+    impl From<serde_json::Error> for BorkError {
+        fn from(err: serde_json::Error) -> Self {
+            // A broken file on disk & a broken response look the same to serde
+            match err.is_io() {
+                true => Self::Io(err.to_string()),
+                false => Self::Decode(err.to_string()),
+            }
+        }
+    }
+
+    impl From<csv::Error> for BorkError {
+        fn from(err: csv::Error) -> Self {
+            match err.is_io_error() {
+                true => Self::Io(err.to_string()),
+                false => Self::Decode(err.to_string()),
+            }
+        }
+    }
+
+    impl<T> From<std::sync::mpsc::SendError<T>> for BorkError {
+        fn from(_: std::sync::mpsc::SendError<T>) -> Self {
+            // Whoever was listening is gone (the page or world was closed)
+            Self::Io("The receiving end of a channel was closed".to_string())
         }
     }
 }
 
 pub mod url_tools {
-    use super::err_tools::ErrorX;
+    use super::err_tools::BorkError;
+    use crate::profiles::base_url;
     use serde::Serialize;
 
     pub fn to_vec8(cereal: &impl Serialize) -> Vec<u8> {
         serde_json::to_vec(cereal).unwrap()
     }
 
-    pub fn status_check(response: &ureq::Response) -> Result<(), ErrorX> {
+    pub fn status_check(response: &ureq::Response) -> Result<(), BorkError> {
+        //! Like StatusCheck, for when the body is still needed (so there is no reason)
         let status = response.status();
-        let pattern = || format!("status code: -> |{}|", status);
         match status {
-            202 => Ok(()),
-            403 => Err(ErrorX::new(&format!("Request Denied... {}", pattern()))),
-            _ => Err(ErrorX::new(&format!(
-                "Request was not aproved: {}",
-                pattern()
-            ))),
+            200..=299 => Ok(()),
+            401 | 403 => Err(BorkError::Auth(format!("status code: -> |{}|", status))),
+            _ => Err(BorkError::Http {
+                status,
+                reason: None,
+            }),
         }
     }
 
@@ -463,6 +968,9 @@ pub mod url_tools {
         AccessRights,
         SessionTimeLeft,
         EstimatedAmountNetherPortals,
+        GetNetherPortalTextHistory,
+        // Every server has one; it may say which version it is
+        Health,
     }
     impl Routes {
         fn make(&self) -> String {
@@ -481,6 +989,8 @@ pub mod url_tools {
                 Routes::AccessRights => "/getaccessrights",
                 Routes::SessionTimeLeft => "/sessiontimeleft",
                 Routes::EstimatedAmountNetherPortals => "/netherportalsestimatedamount",
+                Routes::GetNetherPortalTextHistory => "/getnetherportaltexthistory",
+                Routes::Health => "/health",
             }
             .to_string()
         }
@@ -490,11 +1000,15 @@ pub mod url_tools {
     }
     impl Urls {
         pub fn default(route: Routes) -> String {
-            //! Provides a url with default port number and dns ipv4 thingy
-            Urls::new(aws_public_dns(), text_server_port()).url(route)
+            //! Provides a url to the text server of the active world
+            format!("{}{}", base_url(|e| &e.text), route.make())
         }
         pub fn default_i(route: Routes) -> String {
-            Urls::new("localhost".to_string(), "1234".to_string()).url(route)
+            format!("{}{}", base_url(|e| &e.image), route.make())
+        }
+        pub fn default_p(route: Routes) -> String {
+            //! The server that hands out portals in bunches
+            format!("{}{}", base_url(|e| &e.portals), route.make())
         }
         pub fn new(public_dns: String, port: String) -> Urls {
             Urls {
@@ -516,8 +1030,32 @@ pub mod url_tools {
 }
 
 pub mod eframe_tools {
-    use crate::string_tools::newliner;
-    use eframe::egui::{ScrollArea, Ui};
+    use crate::{
+        string_tools::newliner,
+        thread_tools::{Request, RequestState},
+    };
+    use eframe::egui::{ProgressBar, ScrollArea, Ui};
+    pub fn request_status<T, E>(ui: &mut Ui, request: &mut Request<T, E>)
+    where
+        E: From<crate::BorkError> + std::fmt::Display,
+    {
+        //! A spinner (or how far it is) & a "Cancel" button while (request) runs; its error once it failed
+        match request.state_ref() {
+            RequestState::Pending => {
+                ui.spinner();
+            }
+            RequestState::Progress(done) => {
+                ui.add(ProgressBar::new(*done).desired_width(100.0));
+            }
+            RequestState::Error(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+            }
+            RequestState::Idle | RequestState::Success(_) => {}
+        }
+        if request.is_pending() && ui.button("Cancel").clicked() {
+            request.cancel();
+        }
+    }
     pub fn scroll_and_vert(ui: &mut Ui, id: impl std::hash::Hash, f: impl FnOnce(&mut Ui)) {
         ScrollArea::vertical()
             .id_source(id)
@@ -560,6 +1098,13 @@ pub mod eframe_tools {
             self.selected_option.clone()
         }
 
+        pub fn select_option(&mut self, option: &str) -> Option<()> {
+            //! Only options the machine has can be selected
+            let option = self.options.iter().find(|o| *o == option)?;
+            self.selected_option = option.clone();
+            Some(())
+        }
+
         pub fn modal_machine(&mut self, id: i64, ui: &mut Ui) {
             ui.push_id(id, |ui| {
                 eframe::egui::ComboBox::from_label(&self.name)
@@ -633,4 +1178,202 @@ mod tests {
     //    println!("{:?}", vecker);
     //    panic!("forced panic")
     //}
+
+    use super::{
+        err_tools::BorkError,
+        thread_tools::{set_repaint, tasks, wake, waking_channel, Request, RequestState},
+        url_tools::status_check,
+        StatusCheck,
+    };
+    use eframe::egui::Context;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+    use tokio::runtime::Runtime;
+
+    fn wait_until(mut done: impl FnMut() -> bool) {
+        let started = Instant::now();
+        while !done() {
+            assert!(started.elapsed() < Duration::from_secs(5), "timed out");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn is_listed(name: &str) -> bool {
+        tasks().iter().any(|task| task.name == name)
+    }
+
+    #[test]
+    fn request_finishes_with_its_value() {
+        let (runtime, ctx) = (Runtime::new().unwrap(), Context::default());
+        let mut request: Request<i32, BorkError> = Request::default();
+        assert!(request.is_idle());
+
+        request.spawn(&runtime, &ctx, "test value", |_| Ok(5));
+        assert!(request.is_pending());
+        wait_until(|| request.poll());
+        assert_eq!(request.ready(), Some(&5));
+        assert_eq!(request.take_ready(), Some(5));
+        assert!(request.is_idle());
+
+        // The job is kept, so it can run again
+        assert!(request.retry(&runtime, &ctx));
+        wait_until(|| request.poll());
+        assert_eq!(request.attempts(), 2);
+    }
+
+    #[test]
+    fn request_reports_progress_and_can_be_cancelled() {
+        let (runtime, ctx) = (Runtime::new().unwrap(), Context::default());
+        let mut request: Request<(), BorkError> = Request::default();
+        request.spawn(&runtime, &ctx, "test cancel", |responder| {
+            responder.progress(0.5);
+            while !responder.is_cancelled() {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            responder.check_cancelled()
+        });
+
+        wait_until(|| {
+            request.poll();
+            matches!(request.state_ref(), RequestState::Progress(done) if *done == 0.5)
+        });
+        assert!(is_listed("test cancel"));
+
+        request.cancel();
+        assert!(request.is_idle());
+        assert!(!request.poll());
+        // The job saw the flag & left the task registry
+        wait_until(|| !is_listed("test cancel"));
+        assert!(request.is_idle());
+    }
+
+    #[test]
+    fn request_that_dies_is_aborted() {
+        let (runtime, ctx) = (Runtime::new().unwrap(), Context::default());
+        let mut request: Request<(), BorkError> = Request::default();
+        request.spawn(&runtime, &ctx, "test panic", |_| panic!("the job died"));
+
+        wait_until(|| request.poll());
+        assert_eq!(request.error_ref().map(|err| err.kind()), Some("Aborted"));
+        // Err() only once, when it just failed
+        assert!(request.poll_error().is_ok());
+    }
+
+    #[test]
+    fn waking_senders_repaint_the_ui() {
+        let ctx = Context::default();
+        let repaints = Arc::new(AtomicUsize::new(0));
+        let counter = repaints.clone();
+        ctx.set_request_repaint_callback(move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        set_repaint(&ctx);
+        // Other tests' jobs can wake it too, so only more repaints are checked for. egui asks for one a frame
+        let count = || repaints.load(Ordering::Relaxed);
+
+        let (sender, receiver) = waking_channel();
+        let _ = ctx.run(Default::default(), |_| {});
+        let before = count();
+        std::thread::spawn(move || sender.send(5).unwrap())
+            .join()
+            .unwrap();
+        assert!(count() > before);
+        assert_eq!(receiver.try_recv(), Ok(5));
+
+        let _ = ctx.run(Default::default(), |_| {});
+        let before = count();
+        wake();
+        assert!(count() > before);
+    }
+
+    fn response(status: u16, body: &str) -> ureq::Response {
+        ureq::Response::new(status, "test", body).unwrap()
+    }
+
+    #[test]
+    fn responses_become_typed_errors() {
+        assert!(response(200, "").status_check().is_ok());
+        assert!(response(202, "").status_check().is_ok());
+        assert!(response(204, "").status_check().is_ok());
+
+        // The server's {"error": ...} body is the reason
+        let err = response(500, r#"{"error": "db is down"}"#)
+            .status_check()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BorkError::Http {
+                status: 500,
+                reason: Some("db is down".to_string())
+            }
+        );
+        assert!(err.user_message().contains("db is down"));
+
+        // Anything else is kept as text, cut short; nothing means no reason
+        let page = format!("<html>{}</html>", "x".repeat(1000));
+        match response(502, &page).status_check().unwrap_err() {
+            BorkError::Http {
+                status: 502,
+                reason: Some(reason),
+            } => assert_eq!(reason.chars().count(), 300),
+            err => panic!("{:?}", err),
+        }
+        assert_eq!(
+            response(404, "").status_check().unwrap_err(),
+            BorkError::Http {
+                status: 404,
+                reason: None
+            }
+        );
+
+        assert_eq!(
+            response(401, r#"{"error": "session over"}"#)
+                .status_check()
+                .unwrap_err(),
+            BorkError::Auth("session over".to_string())
+        );
+        assert_eq!(response(403, "").status_check().unwrap_err().kind(), "Auth");
+        // status_check keeps the body, so it has no reason
+        assert_eq!(status_check(&response(401, "")).unwrap_err().kind(), "Auth");
+        assert!(status_check(&response(201, "")).is_ok());
+    }
+
+    #[test]
+    fn errors_know_if_a_retry_could_work() {
+        let http = |status| BorkError::Http {
+            status,
+            reason: None,
+        };
+        assert!(BorkError::Network("reset".to_string()).is_retriable());
+        for status in [408, 429, 500, 503] {
+            assert!(http(status).is_retriable(), "{}", status);
+        }
+        for status in [400, 404, 409] {
+            assert!(!http(status).is_retriable(), "{}", status);
+        }
+        assert!(!BorkError::Auth(String::new()).is_retriable());
+        assert!(!BorkError::validation("no").is_retriable());
+        assert!(!BorkError::cancelled().is_retriable());
+    }
+
+    #[test]
+    fn errors_convert_to_their_kind() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert_eq!(BorkError::from(io).kind(), "Io");
+        let json = serde_json::from_str::<i32>("nope").unwrap_err();
+        assert_eq!(BorkError::from(json).kind(), "Decode");
+        let (sender, receiver) = std::sync::mpsc::channel::<i32>();
+        drop(receiver);
+        assert_eq!(BorkError::from(sender.send(1).unwrap_err()).kind(), "Io");
+        let transport = ureq::get("http://127.0.0.1:1/nothing").call().unwrap_err();
+        assert_eq!(BorkError::from(transport).kind(), "Network");
+
+        assert_eq!(BorkError::validation("bad").to_string(), "bad");
+        assert_eq!(BorkError::cancelled().to_string(), "Stopped: Cancelled");
+    }
 }
@@ -5,7 +5,7 @@ fn main() {
     eframe::run_native(
         "BorkCraft",
         option,
-        Box::new(|_cc| Box::new(BorkCraft::default())),
+        Box::new(|cc| Box::new(BorkCraft::new(cc))),
     )
     .unwrap();
 }
@@ -0,0 +1,299 @@
+use crate::BorkError;
+use std::collections::HashMap;
+
+// Minecraft's Named Binary Tag format; just enough to read level.dat & chunks
+#[derive(Debug, Clone)]
+pub enum Tag {
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        //! A child of a Compound
+        match self {
+            Tag::Compound(children) => children.get(name),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<Tag>> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_longs(&self) -> Option<&Vec<i64>> {
+        match self {
+            Tag::LongArray(longs) => Some(longs),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        //! Any whole number tag
+        match self {
+            Tag::Byte(n) => Some(*n as i64),
+            Tag::Short(n) => Some(*n as i64),
+            Tag::Int(n) => Some(*n as i64),
+            Tag::Long(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, amount: usize) -> Result<&'a [u8], BorkError> {
+        let end = self.position + amount;
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or_else(|| BorkError::decode("NBT data ended too early"))?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BorkError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn length(&mut self) -> Result<usize, BorkError> {
+        // Negative lengths mean empty
+        Ok(i32::from_be_bytes(self.array()?).max(0) as usize)
+    }
+
+    fn string(&mut self) -> Result<String, BorkError> {
+        let length = u16::from_be_bytes(self.array()?) as usize;
+        // Java's "modified UTF-8" only differs for characters no block name uses
+        Ok(String::from_utf8_lossy(self.take(length)?).to_string())
+    }
+
+    fn payload(&mut self, kind: u8) -> Result<Tag, BorkError> {
+        let tag = match kind {
+            0 => Tag::End,
+            1 => Tag::Byte(i8::from_be_bytes(self.array()?)),
+            2 => Tag::Short(i16::from_be_bytes(self.array()?)),
+            3 => Tag::Int(i32::from_be_bytes(self.array()?)),
+            4 => Tag::Long(i64::from_be_bytes(self.array()?)),
+            5 => Tag::Float(f32::from_be_bytes(self.array()?)),
+            6 => Tag::Double(f64::from_be_bytes(self.array()?)),
+            7 => {
+                let length = self.length()?;
+                Tag::ByteArray(self.take(length)?.iter().map(|b| *b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item_kind = self.array::<1>()?[0];
+                let length = self.length()?;
+                let mut list = Vec::with_capacity(length.min(4096));
+                for _ in 0..length {
+                    list.push(self.payload(item_kind)?);
+                }
+                Tag::List(list)
+            }
+            10 => {
+                let mut children = HashMap::new();
+                loop {
+                    let child_kind = self.array::<1>()?[0];
+                    if child_kind == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    children.insert(name, self.payload(child_kind)?);
+                }
+                Tag::Compound(children)
+            }
+            11 => {
+                let length = self.length()?;
+                let mut ints = Vec::with_capacity(length.min(4096));
+                for _ in 0..length {
+                    ints.push(i32::from_be_bytes(self.array()?));
+                }
+                Tag::IntArray(ints)
+            }
+            12 => {
+                let length = self.length()?;
+                let mut longs = Vec::with_capacity(length.min(4096));
+                for _ in 0..length {
+                    longs.push(i64::from_be_bytes(self.array()?));
+                }
+                Tag::LongArray(longs)
+            }
+            _ => {
+                return Err(BorkError::decode(&format!(
+                    "Unknown NBT tag type: |{}|",
+                    kind
+                )))
+            }
+        };
+
+        Ok(tag)
+    }
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Tag, BorkError> {
+    //! Parse uncompressed NBT. The root tag's name is thrown away
+    let mut reader = Reader { bytes, position: 0 };
+    let kind = reader.array::<1>()?[0];
+    reader.string()?;
+    reader.payload(kind)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn kind(tag: &Tag) -> u8 {
+        match tag {
+            Tag::End => 0,
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    fn write_string(bytes: &mut Vec<u8>, string: &str) {
+        bytes.extend((string.len() as u16).to_be_bytes());
+        bytes.extend(string.as_bytes());
+    }
+
+    fn write_payload(bytes: &mut Vec<u8>, tag: &Tag) {
+        match tag {
+            Tag::End => {}
+            Tag::Byte(n) => bytes.extend(n.to_be_bytes()),
+            Tag::Short(n) => bytes.extend(n.to_be_bytes()),
+            Tag::Int(n) => bytes.extend(n.to_be_bytes()),
+            Tag::Long(n) => bytes.extend(n.to_be_bytes()),
+            Tag::Float(n) => bytes.extend(n.to_be_bytes()),
+            Tag::Double(n) => bytes.extend(n.to_be_bytes()),
+            Tag::ByteArray(array) => {
+                bytes.extend((array.len() as i32).to_be_bytes());
+                bytes.extend(array.iter().map(|b| *b as u8));
+            }
+            Tag::String(string) => write_string(bytes, string),
+            Tag::List(list) => {
+                bytes.push(list.first().map(kind).unwrap_or(0));
+                bytes.extend((list.len() as i32).to_be_bytes());
+                list.iter().for_each(|item| write_payload(bytes, item));
+            }
+            Tag::Compound(children) => {
+                for (name, child) in children {
+                    bytes.push(kind(child));
+                    write_string(bytes, name);
+                    write_payload(bytes, child);
+                }
+                bytes.push(0);
+            }
+            Tag::IntArray(ints) => {
+                bytes.extend((ints.len() as i32).to_be_bytes());
+                ints.iter().for_each(|n| bytes.extend(n.to_be_bytes()));
+            }
+            Tag::LongArray(longs) => {
+                bytes.extend((longs.len() as i32).to_be_bytes());
+                longs.iter().for_each(|n| bytes.extend(n.to_be_bytes()));
+            }
+        }
+    }
+
+    pub(crate) fn write(tag: &Tag) -> Vec<u8> {
+        //! The other way around from parse, with an empty root name; for building test data
+        let mut bytes = vec![kind(tag)];
+        write_string(&mut bytes, "");
+        write_payload(&mut bytes, tag);
+        bytes
+    }
+
+    pub(crate) fn compound(children: Vec<(&str, Tag)>) -> Tag {
+        Tag::Compound(
+            children
+                .into_iter()
+                .map(|(name, tag)| (name.to_string(), tag))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parses_every_kind() {
+        let tag = compound(vec![
+            ("byte", Tag::Byte(-3)),
+            ("short", Tag::Short(300)),
+            ("int", Tag::Int(-70_000)),
+            ("long", Tag::Long(1 << 40)),
+            ("double", Tag::Double(0.5)),
+            ("bytes", Tag::ByteArray(vec![1, -1])),
+            ("name", Tag::String("Nether Hub".to_string())),
+            ("list", Tag::List(vec![Tag::Int(1), Tag::Int(2)])),
+            ("ints", Tag::IntArray(vec![7, 8, 9])),
+            ("longs", Tag::LongArray(vec![-1, 2])),
+            ("inner", compound(vec![("y", Tag::Int(64))])),
+        ]);
+        let parsed = parse(&write(&tag)).unwrap();
+
+        let get = |name: &str| parsed.get(name).unwrap();
+        assert_eq!(get("byte").as_i64(), Some(-3));
+        assert_eq!(get("short").as_i64(), Some(300));
+        assert_eq!(get("int").as_i64(), Some(-70_000));
+        assert_eq!(get("long").as_i64(), Some(1 << 40));
+        assert!(matches!(get("double"), Tag::Double(n) if *n == 0.5));
+        assert!(matches!(get("bytes"), Tag::ByteArray(b) if b == &vec![1, -1]));
+        assert_eq!(get("name").as_str(), Some("Nether Hub"));
+        let list: Vec<i64> = get("list")
+            .as_list()
+            .unwrap()
+            .iter()
+            .filter_map(|n| n.as_i64())
+            .collect();
+        assert_eq!(list, [1, 2]);
+        assert!(matches!(get("ints"), Tag::IntArray(i) if i == &vec![7, 8, 9]));
+        assert_eq!(get("longs").as_longs(), Some(&vec![-1, 2]));
+        assert_eq!(get("inner").get("y").and_then(|y| y.as_i64()), Some(64));
+    }
+
+    #[test]
+    fn short_data_is_an_error() {
+        let bytes = write(&compound(vec![("name", Tag::String("hub".to_string()))]));
+        let err = parse(&bytes[..bytes.len() - 3]).unwrap_err();
+        assert!(err.to_string().contains("ended too early"));
+    }
+
+    #[test]
+    fn unknown_tag_is_an_error() {
+        let err = parse(&[10, 0, 0, 13, 0, 1, b'x']).unwrap_err();
+        assert!(err.to_string().contains("|13|"));
+    }
+}
@@ -1,21 +1,16 @@
 use crate::{
     eframe_tools::text_edit,
-    err_tools::ErrorX,
     sessions::SessionInfo,
-    time_of_day,
     url_tools::{Routes, Urls},
-    windows::{
-        client_windows::{GenericWindow, Loglet},
-        error_messages::ErrorMessage,
-    },
-    MagicError,
+    windows::error_messages::ErrorMessage,
+    BorkError,
 };
 
 use serde_derive::Serialize;
 use serde_json::to_vec;
 
 use eframe::egui::Ui;
-use std::{collections::HashMap, sync::mpsc::Sender};
+use std::collections::HashMap;
 
 type AccessRights = Vec<String>;
 
@@ -24,35 +19,12 @@ pub struct LoginForm {
     pub username: String,
     pub password: String,
     pub session_key: String,
-    #[serde(skip_serializing)]
-    pub sender: Option<Sender<String>>,
 }
 
 impl LoginForm {
     pub fn default() -> Self {
         Default::default()
     }
-
-    pub fn send(&self, key: String) -> Option<()> {
-        //! Send key to through Sender<>
-        //! Returns None if there is no Sender
-        //!
-        //! This uses an infinite loop so it is possible it will block the thread
-        //! IF session thread is stuck on a network request
-        let sender = self.sender.as_ref()?;
-        loop {
-            if let Ok(_) = sender.send(key.clone()) {
-                break;
-            }
-        }
-        Some(())
-    }
-
-    pub fn try_send(&self, key: String) -> Result<(), MagicError> {
-        let sender = self.sender.as_ref().ok_or(ErrorX::new_box("No Sender"))?;
-        sender.send(key)?;
-        Ok(())
-    }
 }
 
 fn show_login_form(ui: &mut Ui, login_form: &mut LoginForm) {
@@ -65,14 +37,15 @@ fn show_login_form(ui: &mut Ui, login_form: &mut LoginForm) {
     ui.end_row();
 }
 
-fn handle_login(login_form: &LoginForm) -> Result<SessionInfo, MagicError> {
+#[tracing::instrument(skip_all, fields(username = %login_form.username))]
+fn handle_login(login_form: &LoginForm) -> Result<SessionInfo, BorkError> {
     // Send LoginForm to Server
     let response =
         ureq::post(&Urls::default(Routes::Login)).send_bytes(&to_vec(&login_form).unwrap())?;
 
     // Convert Response & Assign data
     let session_time = response.into_json()?;
-    let sess_info = SessionInfo::session_time_to_session_info(session_time)?;
+    let mut sess_info = SessionInfo::session_time_to_session_info(session_time)?;
 
     // get access rights
     let response = get_access_rights(&login_form.username, Urls::default(Routes::AccessRights))?;
@@ -80,15 +53,10 @@ fn handle_login(login_form: &LoginForm) -> Result<SessionInfo, MagicError> {
     let access_rights: AccessRights = convert_access_rights_resp(response)?;
 
     // Combine the response data
-    let sess_info = SessionInfo {
-        access_rights,
-        ..sess_info
-    };
-
-    // Send the new key to the looping session thread; This is an endless loop interally... Reconsider in the future
-    login_form.send(sess_info.key.clone()).unwrap();
-    println!("SENT!");
+    sess_info.access_rights = access_rights;
+    sess_info.username = login_form.username.clone();
 
+    // The session poll picks the new key up from SessionInfo
     Ok(sess_info)
 }
 
@@ -99,12 +67,14 @@ fn get_access_rights(username: &String, url: String) -> Result<ureq::Response, u
     result
 }
 
-fn convert_access_rights_resp(response: ureq::Response) -> Result<Vec<String>, MagicError> {
+fn convert_access_rights_resp(response: ureq::Response) -> Result<Vec<String>, BorkError> {
     //! The db server sends its response wrapped in a useless hashmap.
     //!
     //! This function converts the response and removes the usefull data/structure.
     let mut hasher: HashMap<String, Vec<String>> = serde_json::from_str(&response.into_string()?)?;
-    Ok(hasher.remove("access_rights").unwrap())
+    hasher
+        .remove("access_rights")
+        .ok_or_else(|| BorkError::decode("The response has no access_rights"))
 }
 
 pub fn login_page(
@@ -122,12 +92,10 @@ pub fn login_page(
     if ui.button("Login").clicked() {
         match handle_login(login_form) {
             Ok(si) => {
+                tracing::info!(username = %login_form.username, "Logged in");
                 session_info.consume(si);
             }
-            Err(error) => GenericWindow::push_loglet(
-                &mut err_msg.display,
-                Loglet::new("Error", &error.to_string(), &time_of_day()),
-            ),
+            Err(error) => err_msg.push_error(error),
         }
     }
 }
@@ -1,6 +1,19 @@
+pub mod audit;
+pub mod backup;
 pub mod display;
 pub mod display_images;
 pub mod download_images;
 pub mod download_npt;
+pub mod history;
+pub mod lint;
+pub mod log_import;
+pub mod map;
+pub mod nearest;
 pub mod page;
+pub mod paste_location;
+pub mod portal_csv;
 pub mod portals;
+pub mod sides;
+pub mod tags;
+pub mod waypoints;
+pub mod world_save;
@@ -0,0 +1,304 @@
+use crate::{
+    eframe_tools::request_status,
+    profiles::world_path,
+    thread_tools::{waking_channel, Request, WakingSender},
+    time_of_day,
+    url_tools::{Routes, Urls},
+    windows::client_windows::GenericWindow,
+    BorkError,
+};
+use eframe::egui::{Context, ScrollArea, Ui};
+use serde_derive::{Deserialize, Serialize};
+use std::{path::Path, sync::mpsc::Receiver};
+use tokio::runtime::Runtime;
+
+// Where the audit log is kept between sessions (one file per world)
+pub const AUDIT_FILE: &str = "borkcraft_audit.json";
+// The oldest entries are dropped past this
+const MAX_AUDIT_ENTRIES: usize = 1000;
+
+// Server side history
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RevisionChange {
+    #[serde(rename = "Realm")]
+    pub realm: String,
+    #[serde(rename = "Field")]
+    pub field: String,
+    #[serde(rename = "Old")]
+    pub old: String,
+    #[serde(rename = "New")]
+    pub new: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Revision {
+    #[serde(rename = "Id")]
+    pub id: i32,
+    #[serde(rename = "Username")]
+    pub username: String,
+    #[serde(rename = "Changed_At")]
+    pub changed_at: String,
+    #[serde(rename = "Changes")]
+    pub changes: Vec<RevisionChange>,
+}
+
+fn download_revisions(id: i32) -> Result<Vec<Revision>, BorkError> {
+    //! Every revision the server has stored for the NetherPortalText(id)
+    let url = Urls::default(Routes::GetNetherPortalTextHistory);
+    let response = ureq::get(&format!("{}?id={}", url, id)).call()?;
+    let revisions = serde_json::from_reader(response.into_reader())?;
+
+    Ok(revisions)
+}
+
+pub struct RevisionHistory {
+    id: Option<i32>,
+    revisions: Request<Vec<Revision>, BorkError>,
+    pub display: GenericWindow,
+}
+
+impl Default for RevisionHistory {
+    fn default() -> Self {
+        Self {
+            id: None,
+            revisions: Request::default(),
+            display: GenericWindow::new("Portal History"),
+        }
+    }
+}
+
+impl RevisionHistory {
+    pub fn fetch(&mut self, id: i32, runtime: &Runtime, ctx: &Context) {
+        //! Download the revisions of (id) in the background
+        self.id = Some(id);
+        let name = format!("History of |{}|", id);
+        self.revisions
+            .spawn(runtime, ctx, &name, move |_| download_revisions(id));
+    }
+
+    fn show(&mut self, ui: &mut Ui) -> Result<(), BorkError> {
+        let result = self.revisions.poll_error();
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                ui.label("Nothing fetched yet");
+                return result;
+            }
+        };
+        request_status(ui, &mut self.revisions);
+        let revisions = match self.revisions.ready() {
+            Some(revisions) => revisions,
+            None => return result,
+        };
+
+        ui.strong(format!("Id: {} -- {} revision(s)", id, revisions.len()));
+        revisions.iter().for_each(|revision| {
+            ui.separator();
+            ui.label(format!(
+                "{} by |{}|",
+                revision.changed_at, revision.username
+            ));
+            revision.changes.iter().for_each(|change| {
+                ui.label(format!(
+                    "    {} {}: |{}| -> |{}|",
+                    change.realm, change.field, change.old, change.new
+                ));
+            });
+        });
+
+        result
+    }
+}
+
+// Client side history; every save this client made and what the server said about it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditEntry {
+    pub time: String,
+    // Who was logged in when it was saved
+    #[serde(default)]
+    pub username: String,
+    pub id: i32,
+    pub name: String,
+    pub changes: Vec<String>,
+    pub response: String,
+}
+
+impl AuditEntry {
+    pub fn new(username: String, id: i32, name: String, changes: Vec<String>) -> Self {
+        Self {
+            time: time_of_day(),
+            username,
+            id,
+            name,
+            changes,
+            response: String::default(),
+        }
+    }
+
+    pub fn respond(mut self, result: &Result<(), BorkError>) -> Self {
+        //! Fill in what the server said
+        self.time = time_of_day();
+        self.response = match result {
+            Ok(()) => "Accepted".to_string(),
+            Err(err) => err.to_string(),
+        };
+        self
+    }
+}
+
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+    receiver: Receiver<AuditEntry>,
+    sender: WakingSender<AuditEntry>,
+    // Where the entries are kept (AUDIT_FILE in the world's folder)
+    path: String,
+    // Who is logged in right now
+    username: String,
+    export_path: String,
+    pub display: GenericWindow,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::open(world_path(AUDIT_FILE))
+    }
+}
+
+impl AuditLog {
+    fn open(path: String) -> Self {
+        //! A missing or broken file just means nothing was saved from this world yet
+        let (sender, receiver) = waking_channel();
+        Self {
+            entries: Self::load(&path).unwrap_or_default(),
+            receiver,
+            sender,
+            path,
+            username: String::default(),
+            export_path: world_path("borkcraft_audit_log.json"),
+            display: GenericWindow::new("Audit Log"),
+        }
+    }
+
+    fn load(path: &str) -> Result<Vec<AuditEntry>, BorkError> {
+        if !Path::new(path).exists() {
+            return Ok(Vec::new());
+        }
+        let entries = serde_json::from_slice(&std::fs::read(path)?)?;
+
+        Ok(entries)
+    }
+
+    pub fn try_update(&mut self) -> Result<(), BorkError> {
+        //! Receive audit entries from async saves; the file is written again when there are new ones
+        let before = self.entries.len();
+        self.entries.extend(self.receiver.try_iter());
+        if self.entries.len() == before {
+            return Ok(());
+        }
+        let over = self.entries.len().saturating_sub(MAX_AUDIT_ENTRIES);
+        self.entries.drain(..over);
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&self.entries)?)?;
+
+        Ok(())
+    }
+
+    pub fn set_username(&mut self, username: &str) {
+        if self.username != username {
+            self.username = username.to_string();
+        }
+    }
+
+    pub fn username_ref(&self) -> &String {
+        &self.username
+    }
+
+    pub fn sender_clone(&self) -> WakingSender<AuditEntry> {
+        self.sender.clone()
+    }
+
+    pub fn export(&self) -> Result<(), BorkError> {
+        //! Write every entry as json to (export_path)
+        let json = serde_json::to_vec_pretty(&self.entries)?;
+        std::fs::write(&self.export_path, json)?;
+
+        Ok(())
+    }
+
+    fn show(&mut self, ui: &mut Ui) -> Result<(), BorkError> {
+        let mut result = Ok(());
+        ui.horizontal(|ui| {
+            ui.label("Export to:");
+            ui.text_edit_singleline(&mut self.export_path);
+            if ui.button("Export").clicked() {
+                result = self.export();
+            }
+        });
+        ui.separator();
+
+        ScrollArea::vertical().show(ui, |ui| {
+            self.entries.iter().rev().for_each(|entry| {
+                ui.strong(format!(
+                    "{} by |{}| -- Id: {} |{}|",
+                    entry.time, entry.username, entry.id, entry.name
+                ));
+                entry.changes.iter().for_each(|change| {
+                    ui.label(format!("    {}", change));
+                });
+                ui.label(format!("Server: {}", entry.response));
+                ui.separator();
+            });
+        });
+
+        result
+    }
+}
+
+pub fn audit_windows(
+    revision_history: &mut RevisionHistory,
+    audit_log: &mut AuditLog,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! Shows the "Portal History" & "Audit Log" windows (if they are open)
+    let mut history_result = Ok(());
+    let mut display = std::mem::take(&mut revision_history.display);
+    display.show_with(ctx, |ui| {
+        ScrollArea::vertical().show(ui, |ui| history_result = revision_history.show(ui));
+    });
+    revision_history.display = display;
+
+    let mut result = Ok(());
+    let mut display = std::mem::take(&mut audit_log.display);
+    display.show_with(ctx, |ui| result = audit_log.show(ui));
+    audit_log.display = display;
+
+    history_result.and(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_kept_between_sessions() {
+        let dir = std::env::temp_dir().join(format!("borkcraft-audit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(AUDIT_FILE).to_string_lossy().to_string();
+
+        let mut audit_log = AuditLog::open(path.clone());
+        audit_log.set_username("Steve");
+        let changes = vec!["Overworld Owner: || -> |Steve|".to_string()];
+        let entry = AuditEntry::new(audit_log.username_ref().clone(), 7, "Home".into(), changes);
+        audit_log
+            .sender_clone()
+            .send(entry.respond(&Ok(())))
+            .unwrap();
+        audit_log.try_update().unwrap();
+
+        let reopened = AuditLog::open(path);
+        assert_eq!(reopened.entries.len(), 1);
+        assert_eq!(reopened.entries[0].username, "Steve");
+        assert_eq!(reopened.entries[0].response, "Accepted");
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,709 @@
+use super::{
+    download_images::{download_image_bytes, get_image_details_list},
+    download_npt::fetch_all_nether_portals,
+    page::save_nether_portal,
+    portal_csv::{add_nether_portal, show_submit_status},
+    portals::{NetherPortalText, NetherPortals, PortalText},
+};
+use crate::{
+    connection::{call_unless, Service},
+    eframe_tools::{request_status, ModalMachine},
+    images::ImageDetails,
+    profiles::world_path,
+    thread_tools::{Request, Responder},
+    url_tools::{Routes, Urls},
+    windows::client_windows::GenericWindow,
+    BorkError, Realm, StatusCheck,
+};
+use eframe::egui::{Context, ScrollArea, Ui};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::File,
+    io::Read,
+};
+use tokio::runtime::Runtime;
+
+// Paths inside of the archive
+const PORTALS_FILE: &str = "portals.json";
+const IMAGES_FILE: &str = "images.json";
+const IMAGES_DIR: &str = "images/";
+
+// Everything the server knows about, as one archive
+#[derive(Default)]
+pub struct Backup {
+    pub npts: Vec<NetherPortalText>,
+    pub images: Vec<(ImageDetails, Vec<u8>)>,
+}
+
+impl Backup {
+    pub fn collect(responder: &Responder<(), BorkError>) -> Result<Self, BorkError> {
+        //! Download every portal on the server, with every image (and its ImageDetails) of both sides
+        //!
+        //! Not just the loaded portals, a backup has to have everything. Stops between portals once cancelled
+        let is_cancelled = || responder.is_cancelled();
+        let npts = fetch_all_nether_portals(&is_cancelled)?;
+        let mut images = Vec::new();
+        for (index, npt) in npts.iter().enumerate() {
+            for realm in [Realm::Overworld, Realm::Nether] {
+                let true_name = npt.realm_ref(&realm).true_name_ref().clone();
+                let list = call_unless(Service::Text, true, is_cancelled, || {
+                    get_image_details_list(true_name.clone())
+                })?;
+                for (_, image_details) in list {
+                    let bytes = call_unless(Service::Image, true, is_cancelled, || {
+                        download_image_bytes(&image_details.name)
+                    })?;
+                    images.push((image_details, bytes));
+                }
+            }
+            responder.progress((index + 1) as f32 / npts.len() as f32);
+        }
+
+        Ok(Self { npts, images })
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), BorkError> {
+        //! Write a .tar.gz with the portals & image details as json and every image as a file
+        let encoder = GzEncoder::new(File::create(path)?, Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+
+        let details: Vec<&ImageDetails> = self.images.iter().map(|(details, _)| details).collect();
+        let files = [
+            (
+                PORTALS_FILE.to_string(),
+                serde_json::to_vec_pretty(&self.npts)?,
+            ),
+            (
+                IMAGES_FILE.to_string(),
+                serde_json::to_vec_pretty(&details)?,
+            ),
+        ];
+        let images = self
+            .images
+            .iter()
+            .map(|(details, bytes)| (format!("{}{}", IMAGES_DIR, details.name), bytes.clone()));
+
+        for (name, bytes) in files.into_iter().chain(images) {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append_data(&mut header, name, bytes.as_slice())?;
+        }
+        archive.into_inner()?.finish()?;
+
+        Ok(())
+    }
+
+    pub fn read(path: &str) -> Result<Self, BorkError> {
+        //! Read an archive made by Backup::write
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+        let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().to_string();
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            files.insert(name, bytes);
+        }
+
+        let missing =
+            |name: &str| BorkError::decode(&format!("|{}| is missing from the backup", name));
+        let npts = serde_json::from_slice(
+            files
+                .get(PORTALS_FILE)
+                .ok_or_else(|| missing(PORTALS_FILE))?,
+        )?;
+        let details: Vec<ImageDetails> =
+            serde_json::from_slice(files.get(IMAGES_FILE).ok_or_else(|| missing(IMAGES_FILE))?)?;
+
+        let mut images = Vec::new();
+        for image_details in details {
+            let name = format!("{}{}", IMAGES_DIR, image_details.name);
+            let bytes = files.remove(&name).ok_or_else(|| missing(&name))?;
+            images.push((image_details, bytes));
+        }
+
+        Ok(Self { npts, images })
+    }
+}
+
+fn save_image_details(image_details: &ImageDetails) -> Result<ureq::Response, BorkError> {
+    let url = &Urls::default(Routes::SaveImageText);
+    let response = ureq::post(url).send_json(image_details)?;
+
+    Ok(response)
+}
+
+fn save_image_request(name: &str) -> ureq::Request {
+    //! ureq encodes the value, so "a b&c#1.png" is not cut short at the & or #
+    ureq::post(&Urls::default_i(Routes::SaveImage)).query("name", name)
+}
+
+fn save_image(name: &str, bytes: &[u8]) -> Result<ureq::Response, BorkError> {
+    let response = save_image_request(name).send_bytes(bytes)?;
+
+    Ok(response)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RestoreMode {
+    // Only add what the server does not have
+    Merge,
+    // Also replace what the server has with what is in the backup
+    Overwrite,
+}
+
+impl RestoreMode {
+    fn name(&self) -> &'static str {
+        match self {
+            RestoreMode::Merge => "Merge",
+            RestoreMode::Overwrite => "Overwrite",
+        }
+    }
+}
+
+// What a restore would do; made by a dry run and carried out by the "Restore" button
+#[derive(Default)]
+pub struct RestorePlan {
+    pub add: Vec<NetherPortalText>,
+    pub update: Vec<(NetherPortalText, Vec<String>)>,
+    pub skip: Vec<String>,
+    pub upload: Vec<(ImageDetails, Vec<u8>)>,
+    pub skip_images: Vec<String>,
+}
+
+fn side_changes(
+    server: &BTreeMap<String, PortalText>,
+    backup: &BTreeMap<String, PortalText>,
+) -> Vec<String> {
+    //! The differences between the End/custom dimension sides, keyed by dimension id, a side missing on either end included
+    let ids: BTreeSet<&String> = server.keys().chain(backup.keys()).collect();
+    let mut changes = Vec::new();
+    for id in ids {
+        match (server.get(id), backup.get(id)) {
+            (Some(old), Some(new)) => changes.extend(old.diff(new).into_iter().map(|change| {
+                format!(
+                    "{} {}: |{}| -> |{}|",
+                    id,
+                    change.field.name(),
+                    change.old,
+                    change.new
+                )
+            })),
+            (None, Some(new)) => changes.push(format!("{}: added |{}|", id, new.true_name_ref())),
+            (Some(old), None) => changes.push(format!("{}: removed |{}|", id, old.true_name_ref())),
+            (None, None) => {}
+        }
+    }
+    changes
+}
+
+impl RestorePlan {
+    pub fn build(
+        backup: Backup,
+        existing: &[NetherPortalText],
+        server_images: &HashSet<String>,
+        mode: RestoreMode,
+    ) -> Self {
+        //! Compare (backup) with the portals & image names on the server. Portals are matched by their overworld true_name
+        let existing: HashMap<String, &NetherPortalText> = existing
+            .iter()
+            .map(|npt| (npt.ow_true_name(), npt))
+            .collect();
+        let mut plan = Self::default();
+
+        for npt in backup.npts {
+            let server_npt = match existing.get(&npt.ow_true_name()) {
+                Some(server_npt) => server_npt,
+                None => {
+                    plan.add.push(npt);
+                    continue;
+                }
+            };
+
+            let mut changes: Vec<String> = [Realm::Overworld, Realm::Nether]
+                .iter()
+                .flat_map(|realm| {
+                    server_npt
+                        .realm_ref(realm)
+                        .diff(npt.realm_ref(realm))
+                        .into_iter()
+                        .map(move |change| {
+                            format!(
+                                "{} {}: |{}| -> |{}|",
+                                realm.name(),
+                                change.field.name(),
+                                change.old,
+                                change.new
+                            )
+                        })
+                })
+                .collect();
+            changes.extend(side_changes(server_npt.sides_ref(), npt.sides_ref()));
+
+            match (mode, changes.is_empty()) {
+                (RestoreMode::Overwrite, false) => {
+                    // The server's id, so the update lands on the right row
+                    let npt = NetherPortalText::build_from(
+                        server_npt.get_id(),
+                        npt.realm_ref(&Realm::Overworld).clone(),
+                        npt.realm_ref(&Realm::Nether).clone(),
+                    )
+                    .with_username(npt.username_ref().clone())
+                    .with_sides(npt.sides_ref().clone());
+                    plan.update.push((npt, changes));
+                }
+                (_, true) => plan
+                    .skip
+                    .push(format!("|{}| is the same", npt.ow_true_name())),
+                (RestoreMode::Merge, false) => plan.skip.push(format!(
+                    "|{}| already exists ({} difference(s))",
+                    npt.ow_true_name(),
+                    changes.len()
+                )),
+            }
+        }
+
+        for (details, bytes) in backup.images {
+            match mode == RestoreMode::Merge && server_images.contains(&details.name) {
+                true => plan.skip_images.push(details.name),
+                false => plan.upload.push((details, bytes)),
+            }
+        }
+
+        plan
+    }
+
+    pub fn dry_run(
+        backup: Backup,
+        mode: RestoreMode,
+        responder: &Responder<RestorePlan, BorkError>,
+    ) -> Result<Self, BorkError> {
+        //! Fetch what the server has right now (not what is loaded, that can be stale) & build the plan against it
+        let is_cancelled = || responder.is_cancelled();
+        let existing = fetch_all_nether_portals(&is_cancelled)?;
+        responder.progress(0.5);
+
+        let mut server_images = HashSet::new();
+        let true_names: HashSet<String> = backup
+            .images
+            .iter()
+            .map(|(details, _)| details.true_name.clone())
+            .collect();
+        for true_name in true_names {
+            let list = call_unless(Service::Text, true, is_cancelled, || {
+                get_image_details_list(true_name.clone())
+            })?;
+            server_images.extend(list.into_keys());
+        }
+
+        Ok(Self::build(backup, &existing, &server_images, mode))
+    }
+
+    pub fn apply(&self, responder: &Responder<(), BorkError>) -> Result<(), BorkError> {
+        //! Send everything in the plan to the server. Every failure is logged; Err() is the first one
+        let is_cancelled = || responder.is_cancelled();
+        let mut failure = None;
+        let mut fail = |name: String, result: Result<(), BorkError>| {
+            if let Err(err) = result {
+                tracing::warn!("Could not restore |{}|: {}", name, err);
+                failure.get_or_insert(err);
+            }
+        };
+
+        // Portals first; images point at a portal's true_name
+        for npt in self.add.iter() {
+            responder.check_cancelled()?;
+            let name = npt.ow_true_name();
+            // Adding is not idempotent, a retry could add it twice
+            let result = call_unless(Service::Text, false, is_cancelled, || {
+                add_nether_portal(npt.clone())?.status_check()?;
+                Ok(())
+            });
+            fail(name, result);
+        }
+        for (npt, _) in self.update.iter() {
+            responder.check_cancelled()?;
+            let name = npt.ow_true_name();
+            let result = call_unless(Service::Text, true, is_cancelled, || {
+                save_nether_portal(npt.clone())?.status_check()?;
+                Ok(())
+            });
+            fail(name, result);
+        }
+        for (details, bytes) in self.upload.iter() {
+            responder.check_cancelled()?;
+            // Both are stored by the image's name, so sending them again changes nothing
+            let result = call_unless(Service::Text, true, is_cancelled, || {
+                save_image_details(details)?.status_check()?;
+                Ok(())
+            })
+            .and_then(|_| {
+                call_unless(Service::Image, true, is_cancelled, || {
+                    save_image(&details.name, bytes)?.status_check()?;
+                    Ok(())
+                })
+            });
+            fail(details.name.clone(), result);
+        }
+
+        match failure {
+            None => Ok(()),
+            Some(err) => Err(err),
+        }
+    }
+
+    fn show(&self, ui: &mut Ui) {
+        ui.label(format!(
+            "{} portal(s) to add, {} to overwrite, {} skipped; {} image(s) to upload, {} skipped",
+            self.add.len(),
+            self.update.len(),
+            self.skip.len(),
+            self.upload.len(),
+            self.skip_images.len()
+        ));
+
+        ScrollArea::vertical()
+            .id_source("restore plan")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                self.add.iter().for_each(|npt| {
+                    ui.label(format!("Add |{}|", npt.ow_true_name()));
+                });
+                self.update.iter().for_each(|(npt, changes)| {
+                    ui.label(format!("Overwrite |{}|", npt.ow_true_name()));
+                    changes.iter().for_each(|change| {
+                        ui.label(format!("    {}", change));
+                    });
+                });
+                self.skip.iter().for_each(|skip| {
+                    ui.weak(format!("Skip {}", skip));
+                });
+                self.upload.iter().for_each(|(details, _)| {
+                    ui.label(format!(
+                        "Upload image |{}| for |{}|",
+                        details.name, details.true_name
+                    ));
+                });
+                self.skip_images.iter().for_each(|name| {
+                    ui.weak(format!("Skip image |{}|, the server has it", name));
+                });
+            });
+    }
+}
+
+pub struct BackupRestore {
+    backup_path: String,
+    restore_path: String,
+    mode: ModalMachine,
+    backup_request: Request<(), BorkError>,
+    plan: Request<RestorePlan, BorkError>,
+    restore_request: Request<(), BorkError>,
+    pub display: GenericWindow,
+}
+
+impl Default for BackupRestore {
+    fn default() -> Self {
+        let options = vec![
+            RestoreMode::Merge.name().to_string(),
+            RestoreMode::Overwrite.name().to_string(),
+        ];
+        Self {
+            backup_path: world_path("borkcraft_backup.tar.gz"),
+            restore_path: world_path("borkcraft_backup.tar.gz"),
+            mode: ModalMachine::new(options[0].clone(), options, "Mode".to_string()),
+            backup_request: Request::default(),
+            plan: Request::default(),
+            restore_request: Request::default(),
+            display: GenericWindow::new("Backup/Restore"),
+        }
+    }
+}
+
+impl BackupRestore {
+    fn selected_mode(&self) -> RestoreMode {
+        match self.mode.get_selected_option() == RestoreMode::Overwrite.name() {
+            true => RestoreMode::Overwrite,
+            false => RestoreMode::Merge,
+        }
+    }
+
+    pub fn backup(&mut self, runtime: &Runtime, ctx: &Context) {
+        //! Download every portal & image and write the archive in the background
+        let path = self.backup_path.clone();
+
+        self.backup_request
+            .spawn(runtime, ctx, "Backup", move |responder| {
+                let backup = Backup::collect(responder)?;
+                responder.check_cancelled()?;
+                backup.write(&path)
+            });
+    }
+
+    pub fn dry_run(&mut self, runtime: &Runtime, ctx: &Context) {
+        //! Read the archive & work out what a restore would do, without changing anything
+        self.restore_request.reset();
+        let path = self.restore_path.clone();
+        let mode = self.selected_mode();
+
+        self.plan
+            .spawn(runtime, ctx, "Restore Dry Run", move |responder| {
+                RestorePlan::dry_run(Backup::read(&path)?, mode, responder)
+            });
+    }
+
+    pub fn restore(&mut self, runtime: &Runtime, ctx: &Context) -> Result<(), BorkError> {
+        //! Carry out the plan made by the last dry run
+        let plan = self
+            .plan
+            .take_ready()
+            .ok_or_else(|| BorkError::validation("Do a dry run first"))?;
+
+        self.restore_request
+            .spawn(runtime, ctx, "Restore", move |responder| {
+                plan.apply(responder)
+            });
+
+        Ok(())
+    }
+
+    fn show(&mut self, runtime: &Runtime, ui: &mut Ui) -> Result<(), BorkError> {
+        let mut result = self.plan.poll_error();
+        self.backup_request.poll();
+
+        // Backup
+        ui.strong("Backup");
+        ui.horizontal(|ui| {
+            ui.label("Save to:");
+            ui.text_edit_singleline(&mut self.backup_path);
+            if ui.button("Backup").clicked() {
+                self.backup(runtime, ui.ctx());
+            }
+            request_status(ui, &mut self.backup_request);
+            if self.backup_request.ready().is_some() {
+                ui.label("Backup written");
+            }
+        });
+        ui.separator();
+
+        // Restore
+        ui.strong("Restore");
+        ui.label("The dry run compares the backup with what is on the server right now");
+        ui.horizontal(|ui| {
+            ui.label("Load from:");
+            ui.text_edit_singleline(&mut self.restore_path);
+        });
+        ui.horizontal(|ui| {
+            self.mode.modal_machine(0, ui);
+            if ui.button("Dry Run").clicked() {
+                self.dry_run(runtime, ui.ctx());
+            }
+            request_status(ui, &mut self.plan);
+        });
+
+        let ready = match self.plan.ready() {
+            Some(plan) => {
+                plan.show(ui);
+                true
+            }
+            None => false,
+        };
+        ui.horizontal(|ui| {
+            if ready && ui.button("Restore").clicked() {
+                result = self.restore(runtime, ui.ctx());
+            }
+            show_submit_status(&mut self.restore_request, ui);
+        });
+
+        result
+    }
+}
+
+pub fn backup_window(
+    nether_portals: &mut NetherPortals,
+    runtime: &Runtime,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! Shows the "Backup/Restore" window (if its open)
+    let mut backup_restore = std::mem::take(nether_portals.backup_restore_mut());
+    let mut display = std::mem::take(&mut backup_restore.display);
+
+    let mut result = Ok(());
+    display.show_with(ctx, |ui| {
+        result = backup_restore.show(runtime, ui);
+    });
+
+    backup_restore.display = display;
+    *nether_portals.backup_restore_mut() = backup_restore;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn npt(id: i32, name: &str, cords: (i32, i32, i32)) -> NetherPortalText {
+        let nether = (cords.0 / 8, cords.1, cords.2 / 8);
+        NetherPortalText::build_from(
+            id,
+            PortalText::build(name.to_string(), cords),
+            PortalText::build(format!("{} nether", name), nether),
+        )
+    }
+
+    fn details(name: &str, true_name: &str) -> ImageDetails {
+        ImageDetails {
+            id: 0,
+            name: name.to_string(),
+            true_name: true_name.to_string(),
+            username: "steve".to_string(),
+            local_image: None,
+        }
+    }
+
+    fn backup() -> Backup {
+        Backup {
+            npts: vec![
+                npt(1, "Hub", (0, 70, 0)),
+                npt(2, "Farm", (800, 64, 800)),
+                npt(3, "Base", (-160, 80, 320)),
+            ],
+            images: vec![
+                (details("hub.png", "Hub"), vec![1, 2, 3]),
+                (details("farm.png", "Farm"), vec![4, 5]),
+            ],
+        }
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("borkcraft-backup-{}.tar.gz", std::process::id()));
+        let path = path.to_str().unwrap();
+        backup().write(path).unwrap();
+        let read = Backup::read(path).unwrap();
+        std::fs::remove_file(path).ok();
+
+        assert_eq!(
+            serde_json::to_value(&read.npts).unwrap(),
+            serde_json::to_value(&backup().npts).unwrap()
+        );
+        let images: Vec<(String, String, Vec<u8>)> = read
+            .images
+            .into_iter()
+            .map(|(details, bytes)| (details.name, details.true_name, bytes))
+            .collect();
+        assert_eq!(
+            images,
+            [
+                ("hub.png".to_string(), "Hub".to_string(), vec![1, 2, 3]),
+                ("farm.png".to_string(), "Farm".to_string(), vec![4, 5]),
+            ]
+        );
+    }
+
+    #[test]
+    fn broken_archive_is_an_error() {
+        let path =
+            std::env::temp_dir().join(format!("borkcraft-broken-{}.tar.gz", std::process::id()));
+        std::fs::write(&path, b"not an archive").unwrap();
+        assert!(Backup::read(path.to_str().unwrap()).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    fn plan(mode: RestoreMode) -> RestorePlan {
+        //! The server has Hub as it is, Farm moved & no Base; it already has hub.png
+        let server = vec![npt(10, "Hub", (0, 70, 0)), npt(11, "Farm", (900, 64, 800))];
+        let server_images = HashSet::from(["hub.png".to_string()]);
+        RestorePlan::build(backup(), &server, &server_images, mode)
+    }
+
+    fn names(npts: &[NetherPortalText]) -> Vec<String> {
+        npts.iter().map(|npt| npt.ow_true_name()).collect()
+    }
+
+    #[test]
+    fn merge_only_adds_what_is_missing() {
+        let plan = plan(RestoreMode::Merge);
+        assert_eq!(names(&plan.add), ["Base"]);
+        assert!(plan.update.is_empty());
+        assert_eq!(plan.skip.len(), 2);
+        assert!(plan
+            .skip
+            .iter()
+            .any(|skip| skip.contains("|Hub| is the same")));
+        assert!(plan
+            .skip
+            .iter()
+            .any(|skip| skip.contains("|Farm| already exists")));
+        let uploads: Vec<&str> = plan.upload.iter().map(|(d, _)| d.name.as_str()).collect();
+        assert_eq!(uploads, ["farm.png"]);
+        assert_eq!(plan.skip_images, ["hub.png"]);
+    }
+
+    #[test]
+    fn overwrite_replaces_what_differs() {
+        let plan = plan(RestoreMode::Overwrite);
+        assert_eq!(names(&plan.add), ["Base"]);
+        // Skipped only because nothing is different
+        assert_eq!(plan.skip.len(), 1);
+        assert!(plan.skip[0].contains("|Hub| is the same"));
+
+        let (farm, changes) = &plan.update[0];
+        assert_eq!(plan.update.len(), 1);
+        // The server's id, so the update lands on the right row
+        assert_eq!(farm.get_id(), 11);
+        assert_eq!(farm.realm_ref(&Realm::Overworld).cords().0, 800);
+        assert!(changes
+            .iter()
+            .any(|change| change.contains("|900| -> |800|")));
+        assert_eq!(plan.upload.len(), 2);
+        assert!(plan.skip_images.is_empty());
+    }
+
+    #[test]
+    fn overwrite_carries_the_other_sides() {
+        let end = |name: &str, x: i32| {
+            BTreeMap::from([(
+                "the_end".to_string(),
+                PortalText::build(name.to_string(), (x, 60, 0)),
+            )])
+        };
+        let aether = BTreeMap::from([(
+            "aether".to_string(),
+            PortalText::build("Sky".to_string(), (5, 100, 5)),
+        )]);
+        let backup = Backup {
+            npts: vec![npt(1, "Hub", (0, 70, 0)).with_sides(end("Hub end", 100))],
+            images: Vec::new(),
+        };
+        // Same overworld & nether, so only the sides differ
+        let mut server_sides = end("Hub end", 40);
+        server_sides.extend(aether);
+        let server = vec![npt(10, "Hub", (0, 70, 0)).with_sides(server_sides)];
+
+        let plan = RestorePlan::build(backup, &server, &HashSet::new(), RestoreMode::Overwrite);
+        let (hub, changes) = &plan.update[0];
+        assert_eq!(hub.sides_ref(), &end("Hub end", 100));
+        assert_eq!(changes.len(), 2);
+        assert!(changes
+            .iter()
+            .any(|change| change.starts_with("the_end") && change.contains("|40| -> |100|")));
+        assert!(changes.contains(&"aether: removed |Sky|".to_string()));
+
+        let added = side_changes(&BTreeMap::new(), &end("Hub end", 100));
+        assert_eq!(added, ["the_end: added |Hub end|"]);
+    }
+
+    #[test]
+    fn image_names_are_encoded() {
+        let url = save_image_request("Luke SpawnPoint&Co #2.png")
+            .url()
+            .to_string();
+        let (_, query) = url.split_once('?').unwrap();
+        assert_eq!(query, "name=Luke+SpawnPoint%26Co+%232.png");
+    }
+}
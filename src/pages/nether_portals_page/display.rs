@@ -1,9 +1,15 @@
-use eframe::egui::Ui;
+use eframe::egui::{DragValue, RichText, TextEdit, Ui};
 use egui_extras::{Column, TableBuilder};
-use std::collections::BTreeMap;
 
-use super::portals::NetherPortals;
-use crate::increment::Inc;
+use super::{
+    history::Edit,
+    nearest::nearest_to_selected,
+    paste_location::paste_location,
+    portals::{FieldValue, NetherPortals, PortalField, PortalText, WORLD_BORDER},
+    sides::{add_side_picker, reset_sides},
+    tags::{tag_chips, tag_picker},
+};
+use crate::{dimension::Dimension, Realm};
 
 fn quick_table(ui: &mut Ui, columns: usize, reset: bool) -> TableBuilder {
     //! Just for settings up a simple table
@@ -50,11 +56,7 @@ fn quick_table(ui: &mut Ui, columns: usize, reset: bool) -> TableBuilder {
     table
 }
 
-fn portal_text_displayer(
-    displayable_pt: &BTreeMap<String, String>,
-    table: TableBuilder,
-    name: &str,
-) {
+fn portal_text_displayer(displayable_pt: &PortalText, table: TableBuilder, name: &str) {
     // Use that table bby!
     table
         .header(20.0, |mut header| {
@@ -66,25 +68,52 @@ fn portal_text_displayer(
             });
         })
         .body(|mut body| {
-            // Iter through the BTree & Display each key/element of Nether Portal Text info
-            displayable_pt.iter().for_each(|(key, value)| {
+            // Iter through each field & Display each part of Nether Portal Text info
+            PortalField::ALL.iter().for_each(|field| {
                 body.row(30.0, |mut row| {
                     row.col(|ui| {
-                        ui.label(format!("key: {}", key));
+                        ui.label(field.name());
                     });
                     row.col(|ui| {
-                        ui.label(format!("value: {}", value));
+                        ui.label(displayable_pt.field_string(*field));
                     });
                 });
             });
         })
 }
 
+fn field_label(ui: &mut Ui, field: &PortalField, is_dirty: bool) {
+    //! Edited fields get highlighted so you can see what will be saved
+    let text = RichText::new(field.name());
+    match is_dirty {
+        true => ui.label(text.strong().color(ui.visuals().warn_fg_color)),
+        false => ui.label(text),
+    };
+}
+
+fn field_editor(ui: &mut Ui, edit: &mut PortalText, field: &PortalField) {
+    if let Some(cord) = edit.cord_mut(*field) {
+        ui.add(DragValue::new(cord).clamp_range(-WORLD_BORDER..=WORLD_BORDER));
+        return;
+    }
+    if let Some(text) = edit.text_mut(*field) {
+        match field {
+            PortalField::Notes => ui.add(TextEdit::multiline(text).desired_rows(3)),
+            _ => ui.add(TextEdit::singleline(text)),
+        };
+    }
+}
+
 fn portal_text_displayer_mut(
-    displayable_pt: &mut BTreeMap<String, String>,
+    edit: &mut PortalText,
+    original: &PortalText,
+    dimension: &Dimension,
     table: TableBuilder,
     name: &str,
-) {
+) -> Vec<(PortalField, FieldValue, FieldValue)> {
+    //! Returns every (field, old value, new value) the user changed this frame
+    let problems = edit.validate(dimension);
+    let mut changed = Vec::new();
     table
         .header(20.0, |mut header| {
             header.col(|ui| {
@@ -93,19 +122,39 @@ fn portal_text_displayer_mut(
             header.col(|ui| {
                 ui.strong("Details");
             });
+            header.col(|ui| {
+                ui.strong("Problems");
+            });
         })
         .body(|mut body| {
-            displayable_pt.iter_mut().for_each(|(key, value)| {
-                body.row(30.0, |mut row| {
+            PortalField::ALL.iter().for_each(|field| {
+                let height = match field {
+                    PortalField::Notes => 70.0,
+                    _ => 30.0,
+                };
+                let is_dirty = edit.field_string(*field) != original.field_string(*field);
+                body.row(height, |mut row| {
+                    row.col(|ui| {
+                        field_label(ui, field, is_dirty);
+                    });
                     row.col(|ui| {
-                        ui.label(format!("key: {}", key));
+                        let before = edit.field_value(*field);
+                        field_editor(ui, edit, field);
+                        let after = edit.field_value(*field);
+                        if before != after {
+                            changed.push((*field, before, after));
+                        }
                     });
                     row.col(|ui| {
-                        ui.add(egui::TextEdit::singleline(value));
+                        if let Some(problem) = problems.get(field) {
+                            ui.colored_label(ui.visuals().error_fg_color, problem);
+                        }
                     });
                 });
             })
         });
+
+    changed
 }
 
 fn bool_button(ui: &mut Ui, name: &str, mut reset: bool) -> bool {
@@ -127,97 +176,186 @@ fn mutate_button(ui: &mut Ui, state: bool) -> bool {
     }
 }
 
-fn reset_all_btree(nether_portals: &mut NetherPortals, ui: &mut Ui) {
+fn reset_all_edits(nether_portals: &mut NetherPortals, ui: &mut Ui) {
     if ui.button("Reset All Text").clicked() {
-        nether_portals
-            .overworld_mut()
-            .iter_mut()
-            .for_each(|(_, nether_portal)| nether_portal.set_as_btree());
+        let ids: Vec<i32> = nether_portals.pairs_ref().keys().copied().collect();
+        for id in ids {
+            reset_edit(nether_portals, &Realm::Overworld, id);
+            reset_edit(nether_portals, &Realm::Nether, id);
+        }
+    }
+}
+
+fn reset_edit(nether_portals: &mut NetherPortals, realm: &Realm, id: i32) {
+    if let Some(nether_portal) = nether_portals.portal_mut(realm, id) {
+        nether_portal.reset_edit();
     }
 }
 
-fn reset_this_btree(nether_portals: &mut NetherPortals, key: &String, ui: &mut Ui) {
+fn reset_this_edit(nether_portals: &mut NetherPortals, id: i32, ui: &mut Ui) {
+    // Reset either side on its own, or both at once
+    if ui.button("Reset Overworld").clicked() {
+        reset_edit(nether_portals, &Realm::Overworld, id);
+    }
+    if ui.button("Reset Nether").clicked() {
+        reset_edit(nether_portals, &Realm::Nether, id);
+    }
     if ui.button("Reset This").clicked() {
-        nether_portals
-            .overworld_mut()
-            .get_mut(key)
-            .and_then(|nether_portal| {
-                nether_portal.set_as_btree();
-                None::<&NetherPortals>
-            });
+        reset_edit(nether_portals, &Realm::Overworld, id);
+        reset_edit(nether_portals, &Realm::Nether, id);
     }
 }
 
-pub fn displayer(nether_portals: &mut NetherPortals, unique: &mut Inc, ui: &mut Ui) -> Option<()> {
+fn realm_displayer(
+    nether_portals: &mut NetherPortals,
+    realm: &Realm,
+    id: i32,
+    mutate: bool,
+    reset: bool,
+    ui: &mut Ui,
+) {
+    // Access The current edit form
+    let display_portal = match nether_portals.portal_mut(realm, id) {
+        Some(display_portal) => display_portal,
+        None => return,
+    };
+
+    // Display Content
+    let name = realm.name();
+    match mutate {
+        true => {
+            // Create a table (with an extra column for problems)
+            let table = quick_table(ui, 2, reset);
+            let original = display_portal.pt_ref().clone();
+            let true_name = original.true_name_ref().clone();
+            let edit = display_portal.edit_mut();
+            let mut changed =
+                portal_text_displayer_mut(edit, &original, &realm.dimension(), table, name);
+
+            // Quick buttons for the defined tags
+            let mut edit = std::mem::take(edit);
+            if let Some((old, new)) = tag_picker(nether_portals.tags_ref(), &mut edit, ui) {
+                changed.push((PortalField::Tags, old, new));
+            }
+            tag_chips(nether_portals.tags_ref(), &edit, ui);
+            if let Some(display_portal) = nether_portals.portal_mut(realm, id) {
+                *display_portal.edit_mut() = edit;
+            }
+
+            // Remember every change so it can be undone
+            for (field, old, new) in changed {
+                let edit = Edit::new(realm.dimension(), id, true_name.clone(), field, old, new);
+                nether_portals.history_mut().record(edit);
+            }
+        }
+        false => {
+            let table = quick_table(ui, 1, reset);
+            let edit = display_portal.edit_ref().clone();
+            portal_text_displayer(&edit, table, name);
+            tag_chips(nether_portals.tags_ref(), &edit, ui);
+        }
+    }
+}
+
+fn sides_displayer(
+    nether_portals: &mut NetherPortals,
+    id: i32,
+    mutate: bool,
+    reset: bool,
+    ui: &mut Ui,
+) {
+    //! Every side of the pair that is not in the overworld or nether
+    //!
+    //! "Reset Sides" throws the edits away
+    let dimension_ids: Vec<String> = nether_portals
+        .sides_ref(id)
+        .map(|sides| sides.keys().cloned().collect())
+        .unwrap_or_default();
+
+    for dimension_id in dimension_ids {
+        let dimension = nether_portals.dimension(&dimension_id);
+        let side = match nether_portals.sides_mut(id).get_mut(&dimension_id) {
+            Some(side) => side,
+            None => continue,
+        };
+
+        let changed = ui.push_id(&dimension_id, |ui| match mutate {
+            true => {
+                let table = quick_table(ui, 2, reset);
+                let original = side.saved_ref().cloned().unwrap_or_default();
+                portal_text_displayer_mut(
+                    side.edit_mut(),
+                    &original,
+                    &dimension,
+                    table,
+                    &dimension.name,
+                )
+            }
+            false => {
+                let table = quick_table(ui, 1, reset);
+                portal_text_displayer(side.edit_ref(), table, &dimension.name);
+                Vec::new()
+            }
+        });
+
+        // Remember every change so it can be undone, the same as the overworld & nether
+        let true_name = side.edit_ref().true_name_ref().clone();
+        for (field, old, new) in changed.inner {
+            let edit = Edit::new(dimension.clone(), id, true_name.clone(), field, old, new);
+            nether_portals.history_mut().record(edit);
+        }
+    }
+
+    if mutate {
+        ui.horizontal(|ui| {
+            add_side_picker(nether_portals, id, ui);
+            if ui.button("Reset Sides").clicked() {
+                reset_sides(nether_portals, id);
+            }
+        });
+    }
+}
+
+pub fn displayer(nether_portals: &mut NetherPortals, ui: &mut Ui) -> Option<()> {
     // If there is no information, leave. There is nothing to display
-    if nether_portals.is_overworld_empty() {
+    if nether_portals.is_empty() {
         return None;
     }
 
-    // Get the current key chosen. if no key, nothing to show. So leave
-    let ow_key = nether_portals.get_ow_pos()?;
-    let neth_key = nether_portals.get_neth_pos()?;
+    // Get the current pair chosen. if no pair, nothing to show. So leave
+    let id = nether_portals.selected()?;
 
     let mut reset = bool::default();
     let mut mutate = nether_portals.get_mutate();
     ui.horizontal(|ui| {
-        // buttons to control which direction to seek PT information
-        //move_back_or_forth_buttons(nether_portals, ui);
-
         // Allows a Reset of the portal_text_displayer view
         reset = reset_button(ui);
 
-        // Mutate allows for mutations inside PortalTextBTree
+        // Mutate allows for mutations inside the edit forms
         mutate = nether_portals.set_mutate(mutate_button(ui, mutate));
-
+    });
+    ui.horizontal(|ui| {
         // Reset changes allowed by mutate
-        reset_all_btree(nether_portals, ui);
-
-        // Reset changes allow by mutate to THIS KEY only
-        reset_this_btree(nether_portals, &ow_key, ui);
+        reset_all_edits(nether_portals, ui);
 
-        // Save All Changes allowed by mutate
-        //save_all_changes();
-
-        // Save changes allowed by mutate to THIS KEY only
-        //save_this();
+        // Reset changes allow by mutate to THIS PAIR only
+        reset_this_edit(nether_portals, id, ui);
     });
 
-    // Access The current PortalTextBTree
-    nether_portals
-        .overworld_mut()
-        .get_mut(&ow_key)
-        .and_then(|display_portal| {
-            // Make sure everything is Unique to avoid clashes
-            ui.push_id(unique.up_str(), |ui| {
-                // Create a table
-                let table = quick_table(ui, 1, reset);
-                // Display Content
-                let name = "overworld";
-                match mutate {
-                    true => portal_text_displayer_mut(&mut display_portal.btree_mut(), table, name),
-                    false => portal_text_displayer(display_portal.btree_ref(), table, name),
-                }
-                //portal_text_displayer(display_portal.btree_ref(), table);
-            });
-            None::<&NetherPortals>
-        });
-
-    nether_portals
-        .nether_mut()
-        .get_mut(&neth_key)
-        .and_then(|display_portal| {
-            ui.push_id(unique.up_str(), |ui| {
-                let table = quick_table(ui, 1, reset);
-                let name = "nether";
-                match mutate {
-                    true => portal_text_displayer(&mut &display_portal.btree_mut(), table, name),
-                    false => portal_text_displayer(display_portal.btree_ref(), table, name),
-                }
-            });
+    // Fill cords from F3+C, a waypoint share or "x y z"
+    if mutate {
+        paste_location(nether_portals, id, ui);
+    }
 
-            None::<&NetherPortals>
-        });
+    // Unique, but the same on every launch; egui keeps the column widths under these ids
+    ui.push_id(Realm::Overworld.name(), |ui| {
+        realm_displayer(nether_portals, &Realm::Overworld, id, mutate, reset, ui);
+    });
+    ui.push_id(Realm::Nether.name(), |ui| {
+        realm_displayer(nether_portals, &Realm::Nether, id, mutate, reset, ui);
+    });
+    sides_displayer(nether_portals, id, mutate, reset, ui);
+    nearest_to_selected(nether_portals, id, ui);
 
     Some(())
 }
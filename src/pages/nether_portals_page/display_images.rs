@@ -13,14 +13,14 @@ pub fn change_image_by_user_input(nps: &mut NetherPortals, realm: &Realm) -> Opt
     // If there is an event continue, otherwise return
     nps.image_modal_mut(realm).use_event()?;
 
-    // Get the key to the current NetherPortal
-    let pos = &nps.realm_pos(realm)?;
+    // Get the id of the current pair
+    let id = nps.selected()?;
 
     // Get the new image position from ModalMachine
     let new_pos = nps.image_modal_mut(realm).get_selected_option();
 
     // Get the current NetherPortal
-    let np = nps.realm_mut(realm).get_mut(pos)?;
+    let np = nps.portal_mut(realm, id)?;
 
     // Set the position of the newly (user) selected image
     np.img_pos_set(new_pos);
@@ -29,8 +29,8 @@ pub fn change_image_by_user_input(nps: &mut NetherPortals, realm: &Realm) -> Opt
 }
 
 pub fn image_from_np<'a>(nps: &'a mut NetherPortals, realm: &Realm) -> Option<&'a RetainedImage> {
-    let pos = &nps.realm_pos(realm)?;
-    let np = nps.realm_mut(realm).get_mut(pos)?;
+    let id = nps.selected()?;
+    let np = nps.portal_mut(realm, id)?;
     let retained_image = some_retained_image(np)?;
 
     Some(retained_image)
@@ -1,22 +1,24 @@
 use super::portals::{NetherPortal, NetherPortals};
+use crate::connection::{call_unless, is_available, Service};
 use crate::eframe_tools::ModalMachine;
-use crate::images::{ImageDetails, ImageDetailsList, Imager, ImagerList};
-use crate::thread_tools::SPromise;
+use crate::images::{ImageDetails, ImageDetailsList, ImageSlot, Imager, ImagerList};
+use crate::thread_tools::WakingSender;
 use crate::url_tools::{Routes, Urls};
 use crate::windows::client_windows::Loglet;
+use crate::BorkError;
 use crate::HandleError;
-use crate::MagicError;
+use crate::Realm;
+use eframe::egui::Context;
 use egui_extras::RetainedImage;
-use std::sync::mpsc::Sender;
 use tokio::runtime::Runtime;
 
-fn to_image_details(resp: ureq::Response) -> Result<ImageDetailsList, MagicError> {
+fn to_image_details(resp: ureq::Response) -> Result<ImageDetailsList, BorkError> {
     let image_details = serde_json::from_reader(resp.into_reader())?;
 
     Ok(image_details)
 }
 
-fn image_details_list(true_name: &String) -> Result<ureq::Response, MagicError> {
+fn image_details_list(true_name: &String) -> Result<ureq::Response, BorkError> {
     //! Downloads meta details about the image from a api/server
     let url: String = Urls::default(Routes::GetNetherPortalImageNames);
     let result = ureq::get(&format!("{}?true_name={}", url, true_name)).call()?;
@@ -24,7 +26,7 @@ fn image_details_list(true_name: &String) -> Result<ureq::Response, MagicError>
     Ok(result)
 }
 
-fn download_image(true_name: &String) -> Result<ureq::Response, MagicError> {
+fn download_image(true_name: &String) -> Result<ureq::Response, BorkError> {
     //! Download image specified by the name from the server
     let url = Urls::default_i(Routes::GetNetherPortalImage);
     let response = ureq::get(&format!("{}?name={}", url, true_name)).call()?;
@@ -32,16 +34,27 @@ fn download_image(true_name: &String) -> Result<ureq::Response, MagicError> {
     Ok(response)
 }
 
-fn to_retained_image(response: ureq::Response) -> Result<egui_extras::RetainedImage, MagicError> {
+pub fn download_image_bytes(name: &String) -> Result<Vec<u8>, BorkError> {
+    //! The raw bytes of an image, as it is stored on the server
+    let mut bytes = Vec::new();
+    download_image(name)?
+        .into_reader()
+        .read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+fn to_retained_image(response: ureq::Response) -> Result<egui_extras::RetainedImage, BorkError> {
     //! Convert response to struct RetainedImage
     let url = "Your Mom.";
     let mut bytes = Vec::new();
     response.into_reader().read_to_end(&mut bytes)?;
-    let retained_image = egui_extras::image::RetainedImage::from_image_bytes(url, &bytes)?;
+    let retained_image = egui_extras::image::RetainedImage::from_image_bytes(url, &bytes)
+        .map_err(|err| BorkError::decode(&err))?;
     Ok(retained_image)
 }
 
-fn get_imager(image_details: ImageDetails) -> Result<Imager, MagicError> {
+fn get_imager(image_details: ImageDetails) -> Result<Imager, BorkError> {
     //! Calls a bunch of functions to finally produce a struct Imager
 
     let name = image_details.name.clone();
@@ -59,13 +72,8 @@ pub fn some_retained_image(np: &mut NetherPortal) -> Option<&RetainedImage> {
     // Get the position/key
     let key = np.image_pos_ref();
 
-    // dot into BTree of (struct Imager) -> into a specific (struct SPromise) -> check if its done downloading -> get a reference to the image
-    let retained_image = np
-        .images_ref()
-        .get(key)?
-        .spromise_ref()?
-        .ready()?
-        .image_ref()?;
+    // dot into BTree of (struct ImageSlot) -> into its Request -> check if its done downloading -> get a reference to the image
+    let retained_image = np.images_ref().get(key)?.request.ready()?.image_ref()?;
 
     // return the contained image as a reference
     Some(retained_image)
@@ -74,28 +82,31 @@ pub fn some_retained_image(np: &mut NetherPortal) -> Option<&RetainedImage> {
 pub fn should_we_reload_nether_images(
     nps: &mut NetherPortals,
 ) -> Option<(&mut NetherPortal, String)> {
-    let position = nps.get_neth_pos()?;
+    let id = nps.selected()?;
     // put (nps.=> to images) in the function signature, that way i dont have to rewrite it twice
-    let np = nps.nether_mut().get_mut(&position)?;
+    let np = nps.portal_mut(&Realm::Nether, id)?;
     let images = np.images_mut();
     if images.len() != 0 {
         // No load/reload necessary
         return None;
     }
 
-    Some((np, position))
+    // The image server still knows the portal by its true_name
+    let true_name = np.pt_ref().true_name_ref().clone();
+    Some((np, true_name))
 }
 
 pub fn should_we_reload_ow_images(nps: &mut NetherPortals) -> Option<(&mut NetherPortal, String)> {
-    let position = nps.get_ow_pos()?;
-    let np = nps.overworld_mut().get_mut(&position)?;
+    let id = nps.selected()?;
+    let np = nps.portal_mut(&Realm::Overworld, id)?;
     let images = np.images_mut();
 
     if images.len() != 0 {
         return None;
     }
 
-    Some((np, position))
+    let true_name = np.pt_ref().true_name_ref().clone();
+    Some((np, true_name))
 }
 
 fn merge_image_details_to_nether_portals(
@@ -104,58 +115,48 @@ fn merge_image_details_to_nether_portals(
 ) {
     image_details_list.drain().for_each(|(_, image_details)| {
         let name = image_details.name.clone();
-        let imager = Imager::new(name.clone(), Some(image_details), None);
-        imager_list.insert(name, SPromise::create_promise(imager));
+        imager_list.insert(name, ImageSlot::new(image_details));
     });
 }
 
-fn execute_futures(np: &mut NetherPortal, runtime: &Runtime, err_msg_sender: Sender<Loglet>) {
-    let mut list_of_futures = Vec::new();
-
-    //np.images_mut().iter_mut().for_each(|(_, spromise_imager)| {
-    for (_, spromise_imager) in np.images_mut().iter_mut() {
-        || -> Option<()> {
-            // Create Senders for async/thread communication
-            let imager_sender = spromise_imager.take_sender()?;
-            let em_sender = err_msg_sender.clone();
-
-            // Clone the struct ImageDetails from inside struct Imager; It has to be 'Send'-able, so plain refs dont work
-            let image_details = spromise_imager
-                .ref_value()
-                .image_details_ref()
-                .unwrap()
-                .clone();
-
-            // Create a closure to be performed asyncronously/threaded and spawn a tokio thread
-            let fut = move || match get_imager(image_details.clone()) {
-                Ok(imager) => {
-                    println!("Image Sent!");
-                    imager_sender.send(imager);
-                }
-                Err(err) => {
-                    em_sender.send(Loglet::err(err)).unwrap();
-                    // the sender has to be used otherwise it will through a panic if its dropped before use...
-                    imager_sender.send(Imager::new(
-                        image_details.name.clone(),
-                        Some(image_details),
-                        None,
-                    ))
-                }
-            };
-
-            list_of_futures.push(fut);
-            Some(())
-        }();
-    }
-    //});
-
-    // Then spawn a thread for the futures. Let ToKIoOOoOO #ThePRIMEagen do its magic
-    for task in list_of_futures.into_iter() {
-        runtime.spawn(async move { task() });
+fn execute_futures(
+    np: &mut NetherPortal,
+    runtime: &Runtime,
+    ctx: &Context,
+    err_msg_sender: WakingSender<Loglet>,
+) {
+    // Every image that has not been asked for yet gets its own Request. Let ToKIoOOoOO #ThePRIMEagen do its magic
+    for (_, slot) in np.images_mut().iter_mut() {
+        // Ones that failed because the server was having trouble get another go
+        let failed = slot
+            .request
+            .error_ref()
+            .is_some_and(|err| err.is_retriable());
+        if failed && is_available(Service::Image) {
+            slot.request.retry(runtime, ctx);
+            continue;
+        }
+        if !slot.request.is_idle() {
+            continue;
+        }
+        let image_details = slot.details.clone();
+        let em_sender = err_msg_sender.clone();
+        let name = format!("Image |{}|", image_details.name);
+        // Cancelled once another pair is selected (poll_requests); no more tries after that
+        slot.request.spawn(runtime, ctx, &name, move |responder| {
+            let is_cancelled = || responder.is_cancelled();
+            let result = call_unless(Service::Image, true, is_cancelled, || {
+                get_imager(image_details.clone())
+            });
+            match responder.is_cancelled() {
+                true => result,
+                false => result.send_error(em_sender.clone()),
+            }
+        });
     }
 }
 
-fn get_image_details_list(true_name: String) -> Result<ImageDetailsList, MagicError> {
+pub fn get_image_details_list(true_name: String) -> Result<ImageDetailsList, BorkError> {
     // Calls a bunch of functions to finally produce a HashMap of struct ImageDetails, Keyed with the name of the image
     let response = image_details_list(&true_name)?;
     let image_details_list = to_image_details(response)?;
@@ -166,8 +167,14 @@ pub fn load_images(
     np: &mut NetherPortal,
     position: String,
     runtime: &Runtime,
-    err_msg_sender: Sender<Loglet>,
+    ctx: &Context,
+    err_msg_sender: WakingSender<Loglet>,
 ) -> Option<()> {
+    // This runs on the ui thread, so a server that is down is not even asked
+    if !is_available(Service::Image) {
+        return None;
+    }
+
     // Get image names from the server; They are to be used to download the exact image needed
     let image_details_list = get_image_details_list(position.clone())
         .send_error(err_msg_sender.clone())
@@ -177,17 +184,34 @@ pub fn load_images(
     merge_image_details_to_nether_portals(np.images_mut(), image_details_list);
 
     // Use all those SHOVED values to Execute image calls as a async/thread
-    execute_futures(np, runtime, err_msg_sender);
+    execute_futures(np, runtime, ctx, err_msg_sender);
+
+    Some(())
+}
+
+pub fn resume_images(
+    nps: &mut NetherPortals,
+    runtime: &Runtime,
+    ctx: &Context,
+    err_msg_sender: WakingSender<Loglet>,
+) -> Option<()> {
+    //! Start the selected pair's downloads again; they were cancelled when another pair got selected
+    let id = nps.selected()?;
+    for realm in [Realm::Overworld, Realm::Nether] {
+        if let Some(np) = nps.portal_mut(&realm, id) {
+            execute_futures(np, runtime, ctx, err_msg_sender.clone());
+        }
+    }
 
     Some(())
 }
 
 pub fn reload_image_mm(nps: &mut NetherPortals, realm: &crate::Realm, id: String) -> Option<()> {
-    // Get the chosen nether portal key
-    let position = nps.realm_pos(realm)?;
+    // Get the chosen pair
+    let id = nps.selected()?;
 
     // Chosen realm is just a convienence variable
-    let chosen_realm = nps.realm_ref(realm).get(&position)?;
+    let chosen_realm = nps.portal_ref(realm, id)?;
 
     // Take all the image keys and convert to an arrary
     let options: Vec<String> = chosen_realm.images_ref().keys().cloned().collect();
@@ -1,42 +1,46 @@
 use super::portals::NetherPortalText;
 use crate::{
-    err_tools::ErrorX,
-    time_of_day,
+    connection::{call, call_unless, Service},
+    logging::trace_loglet,
+    thread_tools::{tasks, TaskGuard, WakingSender},
     url_tools::{Routes, Urls},
     windows::client_windows::Loglet,
-    MagicError,
+    BorkError,
 };
 
-use std::{
-    error::Error,
-    {collections::HashMap, sync::mpsc::Sender},
-};
+use std::collections::HashMap;
 use tokio::runtime::Runtime;
 
-use std::future::Future;
 type NetherPortalTextBunch = HashMap<String, NetherPortalText>;
 
+// Portals asked for at once when everything is fetched for a backup
+const BACKUP_LIMIT: i32 = 50;
+// What the download is listed as in the "Tasks" window
+const DOWNLOAD_TASK: &str = "Download Portals";
+
 fn batch_fetch_nether_portal_text(offset: i32, limit: i32) -> Result<ureq::Response, ureq::Error> {
-    // TODO Change this...?
-    let route = &format!("http://localhost:3001/getnetherportalstextinformation");
-    //let route = Urls::default(Routes::GetNetherPortalBunch);
+    let route = Urls::default_p(Routes::GetNetherPortalBunch);
     let url = &format!("{}?orderby={}&limit={}", route, offset, limit);
-    println!("url: {}", url);
+    tracing::debug!(url, "Fetching a bunch of portals");
 
     ureq::get(url).call()
 }
 
-fn estimate_nether_portals_count() -> Result<i32, MagicError> {
+fn estimate_nether_portals_count() -> Result<i32, BorkError> {
     // Takes a relative count from the database through the Rest API
 
     // Fetch Request
-    let response = ureq::get(&Urls::default(Routes::EstimatedAmountNetherPortals)).call()?;
+    let url = Urls::default(Routes::EstimatedAmountNetherPortals);
+    tracing::debug!(url, "Fetching the estimate");
+    let response = ureq::get(&url).call()?;
 
     // Convert to Map<Key: String, Value: i32>
     let estimate: HashMap<String, i32> = serde_json::from_reader(response.into_reader())?;
 
     // Convert To i32
-    let estimate = *estimate.get("count").unwrap();
+    let estimate = *estimate
+        .get("count")
+        .ok_or_else(|| BorkError::decode("The estimate has no count"))?;
 
     // Return
     Ok(estimate)
@@ -44,14 +48,18 @@ fn estimate_nether_portals_count() -> Result<i32, MagicError> {
 
 fn response_to_nether_portal_bunch(
     response: ureq::Response,
-) -> Result<NetherPortalTextBunch, MagicError> {
+) -> Result<NetherPortalTextBunch, BorkError> {
     let reader = response.into_reader();
     let bunch = serde_json::from_reader(reader)?;
     Ok(bunch)
 }
 
-async fn sync_fetch_all_nether_portals(limit: i32) -> Result<NetherPortalTextBunch, MagicError> {
-    let offset = -1; // Starting point for scrapping the db table
+fn sync_fetch_all_nether_portals(
+    limit: i32,
+    is_cancelled: &dyn Fn() -> bool,
+) -> Result<NetherPortalTextBunch, BorkError> {
+    //! Stops between bunches (and retries) once (is_cancelled)
+    let mut offset = -1; // Starting point for scrapping the db table
 
     // The One-True Overlord of Nether Portal Bunchs: Portahlus Bunchaccous!
     let mut master_bunch = NetherPortalTextBunch::new();
@@ -59,7 +67,9 @@ async fn sync_fetch_all_nether_portals(limit: i32) -> Result<NetherPortalTextBun
     // Loop until you have all bunchs from rest api
     loop {
         // Get a bunch of nether portals from Rest API as response
-        let response = batch_fetch_nether_portal_text(offset, limit)?;
+        let response = call_unless(Service::Portals, true, is_cancelled, || {
+            Ok(batch_fetch_nether_portal_text(offset, limit)?)
+        })?;
 
         // Convert Response
         let bunch: NetherPortalTextBunch = response_to_nether_portal_bunch(response)?;
@@ -70,6 +80,18 @@ async fn sync_fetch_all_nether_portals(limit: i32) -> Result<NetherPortalTextBun
             break;
         }
 
+        // The next bunch starts after the highest id of this one; stop if that does not move
+        let next = bunch
+            .values()
+            .map(|npt| npt.get_id())
+            .max()
+            .unwrap_or(offset);
+        if next <= offset {
+            master_bunch.extend(bunch);
+            break;
+        }
+        offset = next;
+
         // Append to Master Bunch
         master_bunch.extend(bunch);
     }
@@ -78,10 +100,23 @@ async fn sync_fetch_all_nether_portals(limit: i32) -> Result<NetherPortalTextBun
     Ok(master_bunch)
 }
 
-async fn send_nether_portal_texts(
-    nether_portal_sender: Sender<NetherPortalText>,
+pub fn fetch_all_nether_portals(
+    is_cancelled: &dyn Fn() -> bool,
+) -> Result<Vec<NetherPortalText>, BorkError> {
+    //! Every portal on the server, however many there are & whether or not they are loaded
+    let mut npts: Vec<NetherPortalText> =
+        sync_fetch_all_nether_portals(BACKUP_LIMIT, is_cancelled)?
+            .into_values()
+            .collect();
+    npts.sort_by_key(|npt| npt.get_id());
+
+    Ok(npts)
+}
+
+fn send_nether_portal_texts(
+    nether_portal_sender: WakingSender<NetherPortalText>,
     mut npt_bunchs: NetherPortalTextBunch,
-) -> Result<(), MagicError> {
+) -> Result<(), BorkError> {
     // send each npt with a channel sender to the main thread's NetherPortals(data struct)
     for (_, nether_portal_text) in npt_bunchs.drain() {
         nether_portal_sender.send(nether_portal_text)?
@@ -90,55 +125,74 @@ async fn send_nether_portal_texts(
     Ok(())
 }
 
-use std::pin::Pin;
 type FetchFunction =
-    Pin<Box<dyn Future<Output = Result<HashMap<String, NetherPortalText>, Box<dyn Error>>> + Send>>;
+    Box<dyn FnOnce(&dyn Fn() -> bool) -> Result<NetherPortalTextBunch, BorkError> + Send>;
 
-fn get_fetcher_fn(estimate: i32, limit: i32) -> Result<FetchFunction, MagicError> {
+fn get_fetcher_fn(estimate: i32, limit: i32) -> Result<FetchFunction, BorkError> {
     // If the estimate of rows is larger than 100 use multi_threaded fn; TODO
     if estimate > 100 {
-        return Err(ErrorX::new_box(
+        return Err(BorkError::validation(
             "Create multi-threaded async func, estimate to large...",
         ));
     }
     // If the estimate is less than 100: use sync fetcher
     if estimate < 100 && estimate > 0 {
-        return Ok(Box::pin(sync_fetch_all_nether_portals(limit)));
+        return Ok(Box::new(move |is_cancelled| {
+            sync_fetch_all_nether_portals(limit, is_cancelled)
+        }));
     }
 
     // No one uses this app lol, thats why there arent any rows
-    Err(ErrorX::new_box("There are no rows in the database"))
+    Err(BorkError::validation("There are no rows in the database"))
 }
 
-pub fn download_nether_portals(
-    nether_portal_sender: Sender<NetherPortalText>,
-    err_msg_sender: Sender<Loglet>,
-    runtime: &Runtime,
-) {
-    runtime.spawn(async move {
-        let result = async move {
-            // Get estimate of how many rows server has
-            let estimate = estimate_nether_portals_count()?;
+fn download_all(
+    task: &TaskGuard,
+    nether_portal_sender: WakingSender<NetherPortalText>,
+) -> Result<(), BorkError> {
+    // Get estimate of how many rows server has
+    let estimate = call(Service::Text, true, estimate_nether_portals_count)?;
+    task.progress(0.1);
 
-            // Choose an appropriate function based on estimate
-            let npt_fetcher = get_fetcher_fn(estimate, 5)?;
+    // Choose an appropriate function based on estimate
+    let npt_fetcher = get_fetcher_fn(estimate, 5)?;
 
-            // Get all Nether_Portal_Text_Bunchs (Ik its 'bunches')
-            let npt_bunchs = npt_fetcher.await?;
+    // Get all Nether_Portal_Text_Bunchs (Ik its 'bunches')
+    let npt_bunchs = npt_fetcher(&|| task.is_cancelled())?;
+    task.progress(0.9);
 
-            // Send them all down the provided channel
-            send_nether_portal_texts(nether_portal_sender, npt_bunchs).await?;
+    // Cancelled portals are never sent, so nothing half loaded shows up
+    if task.is_cancelled() {
+        return Ok(());
+    }
 
-            // Return
-            Ok::<(), MagicError>(())
-        }
-        .await;
+    // Send them all down the provided channel
+    tracing::info!(count = npt_bunchs.len(), "Downloaded portals");
+    send_nether_portal_texts(nether_portal_sender, npt_bunchs)
+}
 
-        // Send any error down the channel
-        if let Err(error) = result {
-            err_msg_sender
-                .send(Loglet::new("Error", &error.to_string(), &time_of_day()))
-                .unwrap();
+pub fn is_downloading() -> bool {
+    //! Whether the portals are still being downloaded; once it is false, every one of them has been sent
+    tasks().iter().any(|task| task.name == DOWNLOAD_TASK)
+}
+
+pub fn download_nether_portals(
+    nether_portal_sender: WakingSender<NetherPortalText>,
+    err_msg_sender: WakingSender<Loglet>,
+    runtime: &Runtime,
+) {
+    // Every fetch blocks (ureq, waiting between retries)
+    runtime.spawn_blocking(move || {
+        // Listed in the "Tasks" window until this block ends
+        let task = TaskGuard::track(DOWNLOAD_TASK);
+        let _span = task.span().clone().entered();
+
+        // Send any error down the channel; a cancelled download is not one
+        let result = download_all(&task, nether_portal_sender);
+        if let (Err(error), false) = (result, task.is_cancelled()) {
+            let loglet = Loglet::err(error);
+            trace_loglet(&loglet);
+            err_msg_sender.send(loglet).unwrap();
         }
     });
 }
@@ -0,0 +1,279 @@
+use eframe::egui::{Context, Key, Modifiers, ScrollArea, Ui};
+
+use super::portals::{FieldValue, PortalField};
+use crate::{dimension::Dimension, time_of_day, windows::client_windows::GenericWindow};
+use std::time::{Duration, Instant};
+
+// Changes to the same field closer together than this are one edit (typing, dragging)
+pub const MERGE_WINDOW: Duration = Duration::from_millis(1500);
+
+// A single field-level edit made in one of the edit forms
+#[derive(Clone, Debug)]
+pub struct Edit {
+    // The dimension of the side that was edited
+    pub dimension: Dimension,
+    // The pair the edit was made to
+    pub id: i32,
+    // The true_name when the edit was made; only for showing
+    pub name: String,
+    pub field: PortalField,
+    pub old: FieldValue,
+    pub new: FieldValue,
+    pub time: String,
+    // When it was last changed; only for merging
+    at: Instant,
+    pub saved: bool,
+}
+
+impl Edit {
+    pub fn new(
+        dimension: Dimension,
+        id: i32,
+        name: String,
+        field: PortalField,
+        old: FieldValue,
+        new: FieldValue,
+    ) -> Self {
+        Self {
+            dimension,
+            id,
+            name,
+            field,
+            old,
+            new,
+            time: time_of_day(),
+            at: Instant::now(),
+            saved: false,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        //! The edit that undoes this edit
+        Self::new(
+            self.dimension.clone(),
+            self.id,
+            self.name.clone(),
+            self.field,
+            self.new.clone(),
+            self.old.clone(),
+        )
+    }
+
+    fn same_target(&self, other: &Edit) -> bool {
+        self.dimension.id == other.dimension.id && self.id == other.id && self.field == other.field
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} |{}| {}: |{}| -> |{}|",
+            self.dimension.name,
+            self.name,
+            self.field.name(),
+            self.old,
+            self.new
+        )
+    }
+}
+
+// Things the user asked the history to do
+pub enum HistoryAction {
+    Undo,
+    Redo,
+    Revert(usize),
+}
+
+#[derive(Default)]
+pub struct EditHistory {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    pub display: GenericWindow,
+}
+
+impl EditHistory {
+    pub fn record(&mut self, edit: Edit) {
+        //! Push a new edit. A new edit makes the redo stack meaningless so it is cleared
+        //!
+        //! Typing or dragging changes a field every frame, so edits to the same field are merged together.
+        //! A pause longer than MERGE_WINDOW starts a new edit, so an undo does not throw away minutes of work
+        self.redo.clear();
+        if let Some(last) = self.undo.last_mut() {
+            let recent = edit.at.duration_since(last.at) <= MERGE_WINDOW;
+            if !last.saved && recent && last.same_target(&edit) {
+                last.new = edit.new;
+                last.time = edit.time;
+                last.at = edit.at;
+                if last.old == last.new {
+                    self.undo.pop();
+                }
+                return;
+            }
+        }
+        self.undo.push(edit);
+    }
+
+    pub fn record_saved(&mut self, mut edit: Edit) {
+        //! Push an edit that the server already knows about (Never merged)
+        edit.saved = true;
+        self.undo.push(edit);
+    }
+
+    pub fn mark_saved(&mut self, dimension_id: &str, id: i32) {
+        //! Every edit to the (dimension_id) side of pair (id) has been sent to the server
+        self.undo
+            .iter_mut()
+            .filter(|edit| edit.dimension.id == dimension_id && edit.id == id)
+            .for_each(|edit| edit.saved = true);
+    }
+
+    pub fn take_undo(&mut self) -> Option<Edit> {
+        let edit = self.undo.pop()?;
+        self.redo.push(edit.clone());
+        Some(edit)
+    }
+
+    pub fn take_redo(&mut self) -> Option<Edit> {
+        let edit = self.redo.pop()?;
+        self.undo.push(edit.clone());
+        Some(edit)
+    }
+
+    // Getters
+    pub fn undo_ref(&self) -> &Vec<Edit> {
+        &self.undo
+    }
+    pub fn redo_ref(&self) -> &Vec<Edit> {
+        &self.redo
+    }
+}
+
+pub fn history_shortcuts(ctx: &Context) -> Option<HistoryAction> {
+    //! Ctrl+Z & Ctrl+Y (or Ctrl+Shift+Z)
+    //!
+    //! Text boxes have their own undo, so leave the keys alone while one is being typed in
+    if ctx.wants_keyboard_input() {
+        return None;
+    }
+
+    ctx.input_mut(|input| {
+        let shift_command = Modifiers::COMMAND | Modifiers::SHIFT;
+        if input.consume_key(shift_command, Key::Z) {
+            return Some(HistoryAction::Redo);
+        }
+        if input.consume_key(Modifiers::COMMAND, Key::Z) {
+            return Some(HistoryAction::Undo);
+        }
+        if input.consume_key(Modifiers::COMMAND, Key::Y) {
+            return Some(HistoryAction::Redo);
+        }
+        None
+    })
+}
+
+fn show_edits(history: &EditHistory, action: &mut Option<HistoryAction>, ui: &mut Ui) {
+    // Most recent first, undone edits (redo-able) are on top and greyed out
+    history.redo_ref().iter().for_each(|edit| {
+        ui.weak(format!("(undone) {} -- {}", edit.describe(), edit.time));
+    });
+
+    history
+        .undo_ref()
+        .iter()
+        .enumerate()
+        .rev()
+        .for_each(|(index, edit)| {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}) {} -- {}", index, edit.describe(), edit.time));
+                match edit.saved {
+                    true => {
+                        ui.strong("saved");
+                        if ui.button("Revert").clicked() {
+                            *action = Some(HistoryAction::Revert(index));
+                        }
+                    }
+                    false => {
+                        ui.weak("not saved");
+                    }
+                }
+            });
+        });
+}
+
+pub fn history_window(history: &mut EditHistory, ctx: &Context) -> Option<HistoryAction> {
+    //! Shows every edit, Undo/Redo buttons and a Revert button for edits that were saved
+    let mut action = None;
+    history.display.namae("Edit History");
+
+    // Only the window itself needs (history.display) so take it out for a moment
+    let mut display = std::mem::take(&mut history.display);
+    display.show_with(ctx, |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("Undo (Ctrl+Z)").clicked() {
+                action = Some(HistoryAction::Undo);
+            }
+            if ui.button("Redo (Ctrl+Y)").clicked() {
+                action = Some(HistoryAction::Redo);
+            }
+        });
+        ui.separator();
+        ScrollArea::vertical().show(ui, |ui| show_edits(history, &mut action, ui));
+    });
+    history.display = display;
+
+    action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(old: &str, new: &str) -> Edit {
+        let (old, new) = (FieldValue::Text(old.into()), FieldValue::Text(new.into()));
+        Edit::new(
+            Dimension::overworld(),
+            1,
+            "Home".into(),
+            PortalField::Owner,
+            old,
+            new,
+        )
+    }
+
+    #[test]
+    fn typing_is_one_edit() {
+        let mut history = EditHistory::default();
+        history.record(typed("", "S"));
+        history.record(typed("S", "St"));
+        history.record(typed("St", "Steve"));
+
+        assert_eq!(history.undo_ref().len(), 1);
+        assert_eq!(history.undo_ref()[0].old, FieldValue::Text("".into()));
+        assert_eq!(history.undo_ref()[0].new, FieldValue::Text("Steve".into()));
+    }
+
+    #[test]
+    fn a_pause_starts_a_new_edit() {
+        let mut history = EditHistory::default();
+        history.record(typed("", "Steve"));
+        // As if it was typed a while ago
+        let last = history.undo.last_mut().unwrap();
+        last.at = match last.at.checked_sub(MERGE_WINDOW * 2) {
+            Some(at) => at,
+            None => return,
+        };
+        history.record(typed("Steve", "Alex"));
+
+        assert_eq!(history.undo_ref().len(), 2);
+        history.take_undo();
+        assert_eq!(history.undo_ref()[0].new, FieldValue::Text("Steve".into()));
+    }
+
+    #[test]
+    fn saved_edits_are_not_merged() {
+        let mut history = EditHistory::default();
+        history.record(typed("", "Steve"));
+        history.mark_saved(&Dimension::overworld().id, 1);
+        history.record(typed("Steve", "Alex"));
+
+        assert_eq!(history.undo_ref().len(), 2);
+    }
+}
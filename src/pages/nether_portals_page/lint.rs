@@ -0,0 +1,487 @@
+use eframe::egui::{Context, Grid, RichText, ScrollArea, Ui};
+
+use super::portals::{NameIndex, NetherPortals};
+use crate::{dimension::Dimension, windows::client_windows::GenericWindow, Realm};
+use std::collections::BTreeMap;
+
+// How far (in blocks of the side's dimension) a side can be from where the coordinate scale puts it.
+// The game looks for a portal to link to within 16 blocks in the nether
+pub const RATIO_TOLERANCE: i32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintKind {
+    Duplicate,
+    InvalidField,
+    MissingPartner,
+    BadRatio,
+    EmptyOwner,
+    OrphanedImage,
+}
+
+impl LintKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintKind::Duplicate => "Duplicate",
+            LintKind::InvalidField => "Invalid Field",
+            LintKind::MissingPartner => "Missing Partner",
+            LintKind::BadRatio => "Bad Ratio",
+            LintKind::EmptyOwner => "Empty Owner",
+            LintKind::OrphanedImage => "Orphaned Image",
+        }
+    }
+}
+
+// One thing wrong with one loaded portal
+#[derive(Clone, Debug)]
+pub struct Lint {
+    pub kind: LintKind,
+    // The dimension of the side with the problem
+    pub dimension: Dimension,
+    // The pair the portal is a side of
+    pub id: i32,
+    pub name: String,
+    pub message: String,
+}
+
+impl Lint {
+    fn new(kind: LintKind, dimension: &Dimension, id: i32, name: &str, message: String) -> Self {
+        Self {
+            kind,
+            dimension: dimension.clone(),
+            id,
+            name: name.to_string(),
+            message,
+        }
+    }
+}
+
+fn name_indices(nether_portals: &NetherPortals) -> BTreeMap<String, NameIndex> {
+    //! dimension id -> true_name -> ids, for the sides in every dimension
+    let mut indices: BTreeMap<String, NameIndex> = BTreeMap::new();
+    for id in nether_portals.pairs_ref().keys() {
+        for (dimension, pt) in nether_portals.side_texts(*id) {
+            indices
+                .entry(dimension.id)
+                .or_default()
+                .entry(pt.true_name_ref().clone())
+                .or_default()
+                .push(*id);
+        }
+    }
+    indices
+}
+
+fn side_lints(nether_portals: &NetherPortals, lints: &mut Vec<Lint>) {
+    //! Everything that can be checked by looking at one side on its own
+    for id in nether_portals.pairs_ref().keys() {
+        for (dimension, pt) in nether_portals.side_texts(*id) {
+            let key = pt.true_name_ref();
+            // A blank overworld/nether side is a pair with only one portal; its fields mean nothing
+            if let Some(realm) = Realm::from_dimension(&dimension) {
+                if key.trim().is_empty() {
+                    lints.push(missing_partner(nether_portals, &realm, *id));
+                    continue;
+                }
+            }
+            for (field, problem) in pt.validate(&dimension) {
+                let message = format!("{}: {}", field.name(), problem);
+                lints.push(Lint::new(
+                    LintKind::InvalidField,
+                    &dimension,
+                    *id,
+                    key,
+                    message,
+                ));
+            }
+            if pt.owner_ref().trim().is_empty() {
+                let message = "Nobody owns this portal".to_string();
+                lints.push(Lint::new(
+                    LintKind::EmptyOwner,
+                    &dimension,
+                    *id,
+                    key,
+                    message,
+                ));
+            }
+        }
+    }
+}
+
+fn missing_partner(nether_portals: &NetherPortals, realm: &Realm, id: i32) -> Lint {
+    //! The lint for pair (id) having a blank (realm) side, named after the side it does have
+    let partner = nether_portals
+        .side_text(id, &realm.other().dimension().id)
+        .map(|pt| pt.true_name_ref().clone())
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or(format!("|{}|", id));
+    let message = format!("No {} side", realm.name());
+    Lint::new(
+        LintKind::MissingPartner,
+        &realm.dimension(),
+        id,
+        &partner,
+        message,
+    )
+}
+
+fn image_lints(nether_portals: &NetherPortals, lints: &mut Vec<Lint>) {
+    //! Images that name a portal which is not loaded; only the overworld & nether sides have images
+    for realm in [Realm::Overworld, Realm::Nether] {
+        let (dimension, true_names) = (realm.dimension(), nether_portals.names_ref(&realm));
+        for (id, pair) in nether_portals.pairs_ref() {
+            let np = pair.realm_ref(&realm);
+            let key = np.pt_ref().true_name_ref();
+            for (name, slot) in np.images_ref() {
+                let details = &slot.details;
+                if !true_names.contains_key(&details.true_name) {
+                    let message = format!(
+                        "Image |{}| belongs to |{}|, which is not loaded",
+                        name, details.true_name
+                    );
+                    lints.push(Lint::new(
+                        LintKind::OrphanedImage,
+                        &dimension,
+                        *id,
+                        key,
+                        message,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn ratio_lints(nether_portals: &NetherPortals, lints: &mut Vec<Lint>) {
+    //! Sides in a scaled dimension (the nether, modded ones) should be where the scale puts the overworld side
+    let overworld = Dimension::overworld();
+    for id in nether_portals.pairs_ref().keys() {
+        let ow_cords = match nether_portals.side_text(*id, &overworld.id) {
+            Some(pt) => pt.cords(),
+            None => continue,
+        };
+        // The end & other unscaled dimensions do not link by position
+        let scaled = nether_portals
+            .side_texts(*id)
+            .into_iter()
+            .filter(|(dimension, _)| dimension.scale != overworld.scale);
+        for (dimension, pt) in scaled {
+            let cords = pt.cords();
+            let expected = overworld.convert_cords(&dimension, ow_cords);
+            let off = (expected.0 - cords.0)
+                .abs()
+                .max((expected.2 - cords.2).abs());
+            if off > RATIO_TOLERANCE {
+                let message = format!(
+                    "|{}| blocks from where the {}:1 ratio puts it ({}, {})",
+                    off, dimension.scale, expected.0, expected.2
+                );
+                let key = pt.true_name_ref();
+                lints.push(Lint::new(LintKind::BadRatio, &dimension, *id, key, message));
+            }
+        }
+    }
+}
+
+pub fn lint(nether_portals: &NetherPortals) -> Vec<Lint> {
+    //! Check every loaded portal, on every side it has
+    let mut lints = Vec::new();
+
+    for (dimension_id, names) in name_indices(nether_portals) {
+        let dimension = nether_portals.dimension(&dimension_id);
+        // Blank names are missing partners, not duplicates
+        let duplicates = names
+            .iter()
+            .filter(|(name, ids)| ids.len() > 1 && !name.trim().is_empty());
+        for (name, ids) in duplicates {
+            for id in ids {
+                let others: Vec<String> = ids
+                    .iter()
+                    .filter(|other| *other != id)
+                    .map(|other| other.to_string())
+                    .collect();
+                let message = format!("Pair(s) |{}| use this name too", others.join(", "));
+                lints.push(Lint::new(
+                    LintKind::Duplicate,
+                    &dimension,
+                    *id,
+                    name,
+                    message,
+                ));
+            }
+        }
+    }
+
+    side_lints(nether_portals, &mut lints);
+    image_lints(nether_portals, &mut lints);
+    ratio_lints(nether_portals, &mut lints);
+
+    lints.sort_by_key(|lint| lint.kind);
+    lints
+}
+
+pub struct LintReport {
+    lints: Vec<Lint>,
+    ran: bool,
+    pub display: GenericWindow,
+}
+
+impl Default for LintReport {
+    fn default() -> Self {
+        Self {
+            lints: Vec::new(),
+            ran: false,
+            display: GenericWindow::new("Lint Report"),
+        }
+    }
+}
+
+impl LintReport {
+    fn show(&mut self, nether_portals: &mut NetherPortals, ui: &mut Ui) {
+        if ui.button("Run Lint").clicked() {
+            self.lints = lint(nether_portals);
+            self.ran = true;
+        }
+        if !self.ran {
+            return;
+        }
+        if self.lints.is_empty() {
+            ui.label("No problems found");
+            return;
+        }
+
+        let mut counts: Vec<(LintKind, usize)> = Vec::new();
+        for lint in &self.lints {
+            match counts.last_mut() {
+                Some((kind, count)) if *kind == lint.kind => *count += 1,
+                _ => counts.push((lint.kind, 1)),
+            }
+        }
+        let summary: Vec<String> = counts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind.name()))
+            .collect();
+        ui.label(summary.join(", "));
+
+        let mut jump = None;
+        ScrollArea::vertical()
+            .id_source("lint report")
+            .max_height(400.0)
+            .show(ui, |ui| {
+                Grid::new("lint report").striped(true).show(ui, |ui| {
+                    ui.strong("Kind");
+                    ui.strong("Dimension");
+                    ui.strong("Portal");
+                    ui.strong("Problem");
+                    ui.end_row();
+
+                    for lint in &self.lints {
+                        ui.label(RichText::new(lint.kind.name()).color(ui.visuals().warn_fg_color));
+                        ui.label(&lint.dimension.name);
+                        if ui.link(&lint.name).clicked() {
+                            jump = Some(lint.id);
+                        }
+                        ui.label(&lint.message);
+                        ui.end_row();
+                    }
+                });
+            });
+
+        // Show the pair the entry is a part of
+        if let Some(id) = jump {
+            nether_portals.select(id);
+        }
+    }
+}
+
+pub fn lint_window(nether_portals: &mut NetherPortals, ctx: &Context) {
+    //! Shows the "Lint Report" window (if its open)
+    let mut report = std::mem::take(nether_portals.lint_report_mut());
+    let mut display = std::mem::take(&mut report.display);
+
+    display.show_with(ctx, |ui| report.show(nether_portals, ui));
+
+    report.display = display;
+    *nether_portals.lint_report_mut() = report;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        images::{ImageDetails, ImageSlot},
+        pages::nether_portals_page::portals::{
+            FieldValue, NetherPortalText, PortalField, PortalText,
+        },
+    };
+
+    fn side(name: &str, cords: (i32, i32, i32)) -> PortalText {
+        let mut pt = PortalText::build(name.to_string(), cords);
+        pt.set_field(PortalField::Owner, FieldValue::Text("steve".to_string()));
+        pt
+    }
+
+    fn portals(npts: Vec<NetherPortalText>) -> NetherPortals {
+        let mut nether_portals = NetherPortals::default();
+        for npt in npts {
+            nether_portals.consume_npt(npt);
+        }
+        nether_portals
+    }
+
+    fn kinds(lints: &[Lint]) -> Vec<(LintKind, i32, String)> {
+        lints
+            .iter()
+            .map(|lint| (lint.kind, lint.id, lint.name.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn good_pairs_have_no_lints() {
+        let nether_portals = portals(vec![
+            NetherPortalText::build_from(
+                1,
+                side("Hub", (800, 70, 80)),
+                side("Hub N", (100, 70, 10)),
+            ),
+            NetherPortalText::build_from(
+                2,
+                side("Farm", (-80, 64, 0)),
+                side("Farm N", (-10, 64, 0)),
+            ),
+        ]);
+        assert!(lint(&nether_portals).is_empty());
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let nether_portals = portals(vec![
+            NetherPortalText::build_from(1, side("Hub", (0, 70, 0)), side("Hub N", (0, 70, 0))),
+            NetherPortalText::build_from(2, side("Hub", (0, 70, 0)), side("Other N", (0, 70, 0))),
+        ]);
+        let lints = lint(&nether_portals);
+        assert_eq!(
+            kinds(&lints),
+            [
+                (LintKind::Duplicate, 1, "Hub".to_string()),
+                (LintKind::Duplicate, 2, "Hub".to_string()),
+            ]
+        );
+        assert_eq!(lints[0].message, "Pair(s) |2| use this name too");
+        assert_eq!(lints[0].dimension, Dimension::overworld());
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let nether_portals = portals(vec![NetherPortalText::build_from(
+            1,
+            side("Hub", (0, 400, 0)),
+            side("Hub N", (0, 70, 0)),
+        )]);
+        let lints = lint(&nether_portals);
+        assert_eq!(
+            kinds(&lints),
+            [(LintKind::InvalidField, 1, "Hub".to_string())]
+        );
+        assert!(lints[0].message.starts_with(PortalField::Ycord.name()));
+    }
+
+    #[test]
+    fn missing_partners() {
+        let nether_portals = portals(vec![
+            NetherPortalText::build_from(1, side("Hub", (0, 70, 0)), PortalText::default()),
+            NetherPortalText::build_from(2, PortalText::default(), side("Lonely N", (0, 70, 0))),
+            NetherPortalText::build_from(3, PortalText::default(), PortalText::default()),
+        ]);
+        let lints = lint(&nether_portals);
+        // Blank sides are not duplicates of each other, and their empty fields are not reported
+        assert_eq!(
+            kinds(&lints),
+            [
+                (LintKind::MissingPartner, 1, "Hub".to_string()),
+                (LintKind::MissingPartner, 2, "Lonely N".to_string()),
+                (LintKind::MissingPartner, 3, "|3|".to_string()),
+                (LintKind::MissingPartner, 3, "|3|".to_string()),
+            ]
+        );
+        assert_eq!(lints[0].message, "No nether side");
+        assert_eq!(lints[0].dimension, Dimension::nether());
+        assert_eq!(lints[1].message, "No overworld side");
+    }
+
+    #[test]
+    fn bad_ratios() {
+        let nether_portals = portals(vec![
+            // 17 blocks past what 8:1 puts it at
+            NetherPortalText::build_from(1, side("Hub", (800, 70, 0)), side("Hub N", (117, 70, 0))),
+            // The end does not link by position
+            NetherPortalText::build_from(
+                2,
+                side("Farm", (800, 70, 0)),
+                side("Farm N", (100, 70, 0)),
+            )
+            .with_sides(BTreeMap::from([(
+                Dimension::the_end().id,
+                side("Farm E", (5000, 70, 0)),
+            )])),
+        ]);
+        let lints = lint(&nether_portals);
+        assert_eq!(
+            kinds(&lints),
+            [(LintKind::BadRatio, 1, "Hub N".to_string())]
+        );
+        assert!(lints[0].message.starts_with("|17| blocks"));
+    }
+
+    #[test]
+    fn empty_owners() {
+        let nether_portals = portals(vec![NetherPortalText::build_from(
+            1,
+            side("Hub", (0, 70, 0)),
+            PortalText::build("Hub N".to_string(), (0, 70, 0)),
+        )]);
+        let lints = lint(&nether_portals);
+        assert_eq!(
+            kinds(&lints),
+            [(LintKind::EmptyOwner, 1, "Hub N".to_string())]
+        );
+        assert_eq!(lints[0].dimension, Dimension::nether());
+    }
+
+    #[test]
+    fn orphaned_images() {
+        let mut nether_portals = portals(vec![NetherPortalText::build_from(
+            1,
+            side("Hub", (0, 70, 0)),
+            side("Hub N", (0, 70, 0)),
+        )]);
+        let details = |name: &str, true_name: &str| ImageDetails {
+            id: 0,
+            name: name.to_string(),
+            true_name: true_name.to_string(),
+            username: "steve".to_string(),
+            local_image: None,
+        };
+        let images = nether_portals
+            .portal_mut(&Realm::Overworld, 1)
+            .unwrap()
+            .images_mut();
+        images.insert(
+            "hub.png".to_string(),
+            ImageSlot::new(details("hub.png", "Hub")),
+        );
+        images.insert(
+            "old.png".to_string(),
+            ImageSlot::new(details("old.png", "Old Hub")),
+        );
+
+        let lints = lint(&nether_portals);
+        assert_eq!(
+            kinds(&lints),
+            [(LintKind::OrphanedImage, 1, "Hub".to_string())]
+        );
+        assert_eq!(
+            lints[0].message,
+            "Image |old.png| belongs to |Old Hub|, which is not loaded"
+        );
+    }
+}
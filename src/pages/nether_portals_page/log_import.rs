@@ -0,0 +1,518 @@
+use super::{
+    paste_location::dimension_from_text,
+    portal_csv::{show_submit_status, submit_new_portals},
+    portals::{NetherPortalText, NetherPortals, PortalText},
+    world_save::{distance, is_known, KNOWN_RADIUS},
+};
+use crate::{
+    dimension::{Dimension, Dimensions},
+    eframe_tools::request_status,
+    thread_tools::{Request, Responder},
+    windows::client_windows::GenericWindow,
+    BorkError, Realm,
+};
+use eframe::egui::{Context, Grid, ScrollArea, Ui};
+use flate2::read::GzDecoder;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{BufRead, BufReader, Read},
+};
+use tokio::runtime::Runtime;
+
+type Cords = (i32, i32, i32);
+
+// Something a log line says about a player
+#[derive(Debug, PartialEq)]
+pub struct LogEvent {
+    pub time: String,
+    pub player: String,
+    pub dimension: Option<Dimension>,
+    pub cords: Option<Cords>,
+    // (cords) is how far the player moved since the last position, not where they are ("moved too quickly")
+    pub moved: bool,
+}
+
+fn is_player_name(name: &str) -> bool {
+    (3..=16).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_cords(text: &str) -> Option<Cords> {
+    //! "100.5, 64.0, -200.5" or "[100.5d, 64.0d, -200.5d]"
+    let numbers: Vec<i32> = text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '[' || c == ']')
+        .filter_map(|word| word.trim_end_matches('d').parse::<f64>().ok())
+        .map(|number| number.floor() as i32)
+        .collect();
+    match numbers[..] {
+        [x, y, z, ..] => Some((x, y, z)),
+        _ => None,
+    }
+}
+
+pub fn parse_line(dimensions: &Dimensions, line: &str) -> Option<LogEvent> {
+    //! [12:34:56] [Server thread/INFO]: <message>
+    let time = line.strip_prefix('[')?.split(']').next()?.to_string();
+    let (_, message) = line.split_once("]: ")?;
+    // Command feedback is wrapped: [Admin: Teleported Steve to 1.5, 64.0, 2.5]
+    let message = match message.strip_prefix('[').and_then(|m| m.strip_suffix(']')) {
+        Some(inner) => inner
+            .split_once(": ")
+            .map_or(inner, |(_, feedback)| feedback),
+        None => message,
+    };
+
+    // Teleported Steve to 100.5, 64.0, -200.5
+    if let Some(rest) = message.strip_prefix("Teleported ") {
+        let (player, cords) = rest.split_once(" to ")?;
+        return Some(LogEvent {
+            time,
+            player: player.to_string(),
+            dimension: dimension_from_text(dimensions, cords),
+            cords: Some(parse_cords(cords)?),
+            moved: false,
+        });
+    }
+
+    // Steve has the following entity data: [100.5d, 64.0d, -200.5d] (or "minecraft:the_nether")
+    // Steve is at 100, 64, -200 in minecraft:the_nether (admin position dumps)
+    // Steve changed dimension to minecraft:the_nether
+    // Steve moved too quickly! 12.5,0.0,-3.2 (how far, from where the server last had them)
+    let (player, rest) = message.split_once(' ')?;
+    if !is_player_name(player) {
+        return None;
+    }
+    if let Some(moved) = rest.strip_prefix("moved too quickly! ") {
+        return Some(LogEvent {
+            time,
+            player: player.to_string(),
+            dimension: None,
+            cords: Some(parse_cords(moved)?),
+            moved: true,
+        });
+    }
+    let rest = rest
+        .strip_prefix("has the following entity data: ")
+        .or_else(|| rest.strip_prefix("is at "))
+        .or_else(|| rest.strip_prefix("changed dimension"))?;
+    let event = LogEvent {
+        time,
+        player: player.to_string(),
+        dimension: dimension_from_text(dimensions, rest),
+        cords: parse_cords(rest),
+        moved: false,
+    };
+
+    match event.dimension.is_some() || event.cords.is_some() {
+        true => Some(event),
+        false => None,
+    }
+}
+
+// A place that was probably a portal: where a player left the overworld and where they showed up in the nether (or back)
+#[derive(Clone, Debug)]
+pub struct PortalCandidate {
+    pub add: bool,
+    pub name: String,
+    pub overworld: Cords,
+    pub nether: Cords,
+    pub uses: usize,
+    pub players: BTreeSet<String>,
+    pub first_seen: String,
+}
+
+#[derive(Default)]
+struct PlayerState {
+    // The dimension id the player is in
+    dimension: Option<String>,
+    last: Option<Cords>,
+    // Where the player was before changing dimension, waiting for where they showed up
+    departure: Option<(String, Cords)>,
+}
+
+pub fn find_candidates(events: &[LogEvent], known: &[NetherPortalText]) -> Vec<PortalCandidate> {
+    //! Follow every player through the log; each dimension change is a portal use
+    //!
+    //! Only trips between the overworld & the nether are nether portals. Trips to the end
+    //! (or a modded dimension) are still followed, so coming back from one is not taken for a portal
+    let mut players: HashMap<&String, PlayerState> = HashMap::new();
+    let mut candidates: Vec<PortalCandidate> = Vec::new();
+
+    for event in events {
+        let state = players.entry(&event.player).or_default();
+
+        if let Some(dimension) = &event.dimension {
+            if state
+                .dimension
+                .as_ref()
+                .is_some_and(|old| *old != dimension.id)
+            {
+                state.departure = state.dimension.clone().zip(state.last);
+                state.last = None;
+            }
+            state.dimension = Some(dimension.id.clone());
+        }
+        let cords = match (event.cords, event.moved) {
+            (Some(cords), false) => cords,
+            // Only useful when it is known where they moved from
+            (Some(moved), true) => match state.last {
+                Some(last) => (last.0 + moved.0, last.1 + moved.1, last.2 + moved.2),
+                None => continue,
+            },
+            (None, _) => continue,
+        };
+        state.last = Some(cords);
+
+        let (from, from_cords) = match state.departure.take() {
+            Some(departure) => departure,
+            None => continue,
+        };
+        let to = state.dimension.as_deref().and_then(Realm::from_id);
+        let (overworld, nether) = match (Realm::from_id(&from), to) {
+            (Some(Realm::Overworld), Some(Realm::Nether)) => (from_cords, cords),
+            (Some(Realm::Nether), Some(Realm::Overworld)) => (cords, from_cords),
+            _ => continue,
+        };
+
+        // The same portal used again
+        let same = candidates.iter_mut().find(|candidate| {
+            distance(candidate.overworld, overworld) <= KNOWN_RADIUS
+                && distance(candidate.nether, nether) <= KNOWN_RADIUS
+        });
+        match same {
+            Some(candidate) => {
+                candidate.uses += 1;
+                candidate.players.insert(event.player.clone());
+            }
+            None => candidates.push(PortalCandidate {
+                add: true,
+                name: format!("{} Portal {}", event.player, candidates.len() + 1),
+                overworld,
+                nether,
+                uses: 1,
+                players: BTreeSet::from([event.player.clone()]),
+                first_seen: event.time.clone(),
+            }),
+        }
+    }
+
+    // Portals that are already loaded are not candidates
+    candidates.retain(|candidate| {
+        !is_known(known, &Realm::Overworld, candidate.overworld)
+            && !is_known(known, &Realm::Nether, candidate.nether)
+    });
+    candidates
+}
+
+pub fn read_log(dimensions: &Dimensions, path: &str) -> Result<Vec<LogEvent>, BorkError> {
+    //! latest.log or a gzipped archive like 2023-04-01-1.log.gz
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = match path.ends_with(".gz") {
+        true => Box::new(GzDecoder::new(file)),
+        false => Box::new(file),
+    };
+
+    let mut events = Vec::new();
+    for line in BufReader::new(reader).split(b'\n') {
+        // Logs can hold bytes that are not utf8 (player chat), they are replaced; a broken archive is an error
+        let line = String::from_utf8_lossy(&line?).into_owned();
+        if let Some(event) = parse_line(dimensions, &line) {
+            events.push(event);
+        }
+    }
+
+    Ok(events)
+}
+
+// What a scan of one log found
+pub struct LogScan {
+    events: usize,
+    candidates: Vec<PortalCandidate>,
+}
+
+pub fn scan_log(
+    dimensions: &Dimensions,
+    path: &str,
+    known: &[NetherPortalText],
+    responder: &Responder<LogScan, BorkError>,
+) -> Result<LogScan, BorkError> {
+    let events = read_log(dimensions, path)?;
+    responder.check_cancelled()?;
+    responder.progress(0.5);
+
+    Ok(LogScan {
+        events: events.len(),
+        candidates: find_candidates(&events, known),
+    })
+}
+
+pub struct LogImport {
+    path: String,
+    scan: Request<LogScan, BorkError>,
+    candidates: Vec<PortalCandidate>,
+    submit_request: Request<(), BorkError>,
+    pub display: GenericWindow,
+}
+
+impl Default for LogImport {
+    fn default() -> Self {
+        Self {
+            path: "logs/latest.log".to_string(),
+            scan: Request::default(),
+            candidates: Vec::new(),
+            submit_request: Request::default(),
+            display: GenericWindow::new("Log Import"),
+        }
+    }
+}
+
+impl LogImport {
+    pub fn scan(&mut self, nether_portals: &NetherPortals, runtime: &Runtime, ctx: &Context) {
+        //! Read the log in the background, archives of a busy server can be big
+        self.candidates = Vec::new();
+        let (dimensions, path) = (nether_portals.dimensions_ref().clone(), self.path.clone());
+        let known = nether_portals.to_npts();
+
+        self.scan.spawn(runtime, ctx, "Scan Log", move |responder| {
+            scan_log(&dimensions, &path, &known, responder)
+        });
+    }
+
+    pub fn submit(&mut self, runtime: &Runtime, ctx: &Context) -> Result<(), BorkError> {
+        let npts: Vec<NetherPortalText> = self
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.add)
+            .map(|candidate| {
+                NetherPortalText::build_from(
+                    0,
+                    PortalText::build(candidate.name.clone(), candidate.overworld),
+                    PortalText::build(candidate.name.clone(), candidate.nether),
+                )
+            })
+            .collect();
+        if npts.is_empty() {
+            return Err(BorkError::validation("No candidates are checked"));
+        }
+
+        submit_new_portals(&mut self.submit_request, npts, runtime, ctx);
+
+        Ok(())
+    }
+
+    fn show_candidates(&mut self, ui: &mut Ui) {
+        ScrollArea::vertical()
+            .id_source("log candidates")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                Grid::new("log candidates").striped(true).show(ui, |ui| {
+                    ui.strong("Add");
+                    ui.strong("Name");
+                    ui.strong("Overworld");
+                    ui.strong("Nether");
+                    ui.strong("Uses");
+                    ui.strong("Players");
+                    ui.strong("First Seen");
+                    ui.end_row();
+
+                    self.candidates.iter_mut().for_each(|candidate| {
+                        ui.checkbox(&mut candidate.add, "");
+                        ui.text_edit_singleline(&mut candidate.name);
+                        ui.label(format!("{:?}", candidate.overworld));
+                        ui.label(format!("{:?}", candidate.nether));
+                        ui.label(candidate.uses.to_string());
+                        ui.label(
+                            candidate
+                                .players
+                                .iter()
+                                .cloned()
+                                .collect::<Vec<String>>()
+                                .join(", "),
+                        );
+                        ui.label(&candidate.first_seen);
+                        ui.end_row();
+                    });
+                });
+            });
+    }
+
+    fn show(
+        &mut self,
+        nether_portals: &NetherPortals,
+        runtime: &Runtime,
+        ui: &mut Ui,
+    ) -> Result<(), BorkError> {
+        let mut result = self.scan.poll_error();
+        ui.horizontal(|ui| {
+            ui.label("Log file (.log or .log.gz):");
+            ui.text_edit_singleline(&mut self.path);
+            if ui.button("Scan Log").clicked() {
+                self.scan(nether_portals, runtime, ui.ctx());
+            }
+            request_status(ui, &mut self.scan);
+        });
+
+        let scan = match self.scan.ready() {
+            Some(scan) => scan,
+            None => return result,
+        };
+        if scan.events == 0 {
+            return result;
+        }
+
+        ui.label(format!(
+            "{} position/dimension line(s), {} candidate portal(s) not loaded yet",
+            scan.events,
+            scan.candidates.len()
+        ));
+        if self.candidates.is_empty() && !scan.candidates.is_empty() {
+            self.candidates = scan.candidates.clone();
+        }
+        if self.candidates.is_empty() {
+            return result;
+        }
+        self.show_candidates(ui);
+        ui.horizontal(|ui| {
+            if ui.button("Add Checked As Portals").clicked() {
+                result = self.submit(runtime, ui.ctx());
+            }
+            show_submit_status(&mut self.submit_request, ui);
+        });
+
+        result
+    }
+}
+
+pub fn log_import_window(
+    nether_portals: &mut NetherPortals,
+    runtime: &Runtime,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! Shows the "Log Import" window (if its open)
+    let mut log_import = std::mem::take(nether_portals.log_import_mut());
+    let mut display = std::mem::take(&mut log_import.display);
+
+    let mut result = Ok(());
+    display.show_with(ctx, |ui| {
+        result = log_import.show(nether_portals, runtime, ui);
+    });
+
+    log_import.display = display;
+    *nether_portals.log_import_mut() = log_import;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    const LOG: &str = "\
+[09:59:00] [Server thread/INFO]: Steve is at 0, 70, 0 in minecraft:overworld
+[10:00:00] [Server thread/INFO]: [Admin: Teleported Steve to 800.5, 70.0, -1600.5]
+[10:00:05] [Server thread/INFO]: Steve changed dimension to minecraft:the_nether
+[10:00:06] [Server thread/INFO]: Steve has the following entity data: [100.5d, 70.0d, -200.5d]
+[10:00:30] [Server thread/INFO]: <Alex> hello there
+[10:01:00] [Server thread/WARN]: Steve moved too quickly! 40.0,0.0,-8.0
+";
+
+    fn events(text: &str) -> Vec<LogEvent> {
+        let dimensions = Dimensions::default();
+        text.lines()
+            .filter_map(|line| parse_line(&dimensions, line))
+            .collect()
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("borkcraft-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn lines_become_events() {
+        let events = events(LOG);
+        assert_eq!(events.len(), 5);
+
+        assert_eq!(events[0].dimension, Some(Dimension::overworld()));
+        assert_eq!(events[1].player, "Steve");
+        assert_eq!(events[1].cords, Some((800, 70, -1601)));
+        assert_eq!(events[2].dimension, Some(Dimension::nether()));
+        assert_eq!(events[2].cords, None);
+        assert_eq!(events[3].cords, Some((100, 70, -201)));
+        assert!(!events[3].moved);
+        assert_eq!(events[4].cords, Some((40, 0, -8)));
+        assert!(events[4].moved);
+    }
+
+    #[test]
+    fn chat_and_noise_are_not_events() {
+        assert!(events("[10:00:30] [Server thread/INFO]: <Alex> hello there").is_empty());
+        assert!(events("[10:00:30] [Server thread/INFO]: Done (3.2s)! For help").is_empty());
+        assert!(events("not a log line").is_empty());
+    }
+
+    #[test]
+    fn a_trip_to_the_nether_is_a_candidate() {
+        let candidates = find_candidates(&events(LOG), &[]);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].overworld, (800, 70, -1601));
+        assert_eq!(candidates[0].nether, (100, 70, -201));
+        assert_eq!(candidates[0].uses, 1);
+    }
+
+    #[test]
+    fn moved_too_quickly_follows_the_last_position() {
+        let log = "\
+[10:00:00] [Server thread/INFO]: Steve is at 800, 70, -1600 in minecraft:overworld
+[10:00:01] [Server thread/WARN]: Steve moved too quickly! 16.0,0.0,-16.0
+[10:00:02] [Server thread/INFO]: Steve changed dimension to minecraft:the_nether
+[10:00:03] [Server thread/INFO]: Steve is at 102, 70, -202 in minecraft:the_nether
+";
+        let candidates = find_candidates(&events(log), &[]);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].overworld, (816, 70, -1616));
+    }
+
+    #[test]
+    fn trips_to_the_end_are_not_nether_portals() {
+        let log = "\
+[10:00:00] [Server thread/INFO]: Steve is at 800, 70, -1600 in minecraft:overworld
+[10:00:02] [Server thread/INFO]: Steve changed dimension to minecraft:the_end
+[10:00:03] [Server thread/INFO]: Steve is at 100, 49, 0 in minecraft:the_end
+[10:05:00] [Server thread/INFO]: Steve changed dimension to minecraft:overworld
+[10:05:01] [Server thread/INFO]: Steve is at 0, 70, 0 in minecraft:overworld
+";
+        assert!(find_candidates(&events(log), &[]).is_empty());
+    }
+
+    #[test]
+    fn gzipped_logs_are_read() {
+        let dir = temp_dir("log-import");
+        let path = dir.join("2023-04-01-1.log.gz");
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        encoder.write_all(LOG.as_bytes()).unwrap();
+        // A line that is not utf8 does not stop the rest from being read
+        encoder
+            .write_all(b"[10:02:00] [Server thread/INFO]: <\xff\xfe> hi\n")
+            .unwrap();
+        encoder
+            .write_all(b"[10:03:00] [Server thread/INFO]: Alex is at 1, 2, 3\n")
+            .unwrap();
+        encoder.finish().unwrap();
+
+        let events = read_log(&Dimensions::default(), path.to_str().unwrap()).unwrap();
+        assert_eq!(events.len(), 6);
+        assert_eq!(events[5].player, "Alex");
+
+        // Cut short, the archive is broken
+        let broken = dir.join("broken.log.gz");
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&broken, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(read_log(&Dimensions::default(), broken.to_str().unwrap()).is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
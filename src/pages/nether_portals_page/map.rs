@@ -0,0 +1,262 @@
+use eframe::egui::{
+    Align2, Button, ComboBox, Context, FontId, Pos2, RichText, Sense, Slider, Stroke, Ui, Vec2,
+};
+
+use super::portals::NetherPortals;
+use crate::{dimension::Dimension, windows::client_windows::GenericWindow, Realm};
+
+// Size of the drawn map, in points
+const MAP_SIZE: Vec2 = Vec2::new(500.0, 400.0);
+// Radius of a portal's dot, in points
+const PIN_RADIUS: f32 = 5.0;
+
+// One portal on the map
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapPin {
+    pub id: i32,
+    pub name: String,
+    // x & z; the map looks down on the world
+    pub cords: (i32, i32),
+    pub tags: Vec<String>,
+}
+
+pub fn pins(nether_portals: &NetherPortals, dimension: &Dimension) -> Vec<MapPin> {
+    //! Every pair with a side in (dimension), unless the tag filter hides it (same as the list)
+    nether_portals
+        .pairs_ref()
+        .iter()
+        .filter(|(_, pair)| {
+            let pts =
+                [&Realm::Overworld, &Realm::Nether].map(|realm| pair.realm_ref(realm).pt_ref());
+            nether_portals.tags_ref().allows(&pts)
+        })
+        .filter_map(|(id, _)| {
+            let pt = nether_portals.side_text(*id, &dimension.id)?;
+            let (x, _, z) = pt.cords();
+            Some(MapPin {
+                id: *id,
+                name: pt.true_name_ref().clone(),
+                cords: (x, z),
+                tags: pt.tags(),
+            })
+        })
+        .collect()
+}
+
+// State of the "Map" window
+pub struct MapView {
+    dimension: Dimension,
+    // The block at the middle of the map (x, z)
+    center: (f32, f32),
+    // Blocks per point
+    zoom: f32,
+    pub display: GenericWindow,
+}
+
+impl Default for MapView {
+    fn default() -> Self {
+        Self {
+            dimension: Dimension::overworld(),
+            center: (0.0, 0.0),
+            zoom: 8.0,
+            display: GenericWindow::new("Map"),
+        }
+    }
+}
+
+impl MapView {
+    fn show(&mut self, nether_portals: &mut NetherPortals, ui: &mut Ui) {
+        let pins = pins(nether_portals, &self.dimension);
+
+        ui.horizontal(|ui| {
+            ComboBox::from_id_source("map dimension")
+                .selected_text(&self.dimension.name)
+                .show_ui(ui, |ui| {
+                    for dimension in nether_portals.dimensions_ref().all() {
+                        let name = dimension.name.clone();
+                        ui.selectable_value(&mut self.dimension, dimension, name);
+                    }
+                });
+            ui.add(
+                Slider::new(&mut self.zoom, 0.5..=256.0)
+                    .logarithmic(true)
+                    .text("Blocks per point"),
+            );
+            let selected = nether_portals.selected().and_then(|id| {
+                pins.iter()
+                    .find(|pin| pin.id == id)
+                    .map(|pin| (pin.cords.0 as f32, pin.cords.1 as f32))
+            });
+            if ui
+                .add_enabled(selected.is_some(), Button::new("Center on Selected"))
+                .clicked()
+            {
+                self.center = selected.unwrap_or(self.center);
+            }
+        });
+        ui.label("Drag to move around; each portal is colored by its first tag");
+
+        let (response, painter) = ui.allocate_painter(MAP_SIZE, Sense::click_and_drag());
+        let rect = response.rect;
+        if response.dragged() {
+            let delta = response.drag_delta() * self.zoom;
+            self.center.0 -= delta.x;
+            self.center.1 -= delta.y;
+        }
+
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 4.0, visuals.extreme_bg_color);
+        // The 0, 0 lines
+        let origin = self.to_screen(rect.center(), (0, 0));
+        let axis = Stroke::new(1.0, visuals.weak_text_color());
+        painter.hline(
+            rect.x_range(),
+            origin.y.clamp(rect.top(), rect.bottom()),
+            axis,
+        );
+        painter.vline(
+            origin.x.clamp(rect.left(), rect.right()),
+            rect.y_range(),
+            axis,
+        );
+
+        let tags = nether_portals.tags_ref();
+        let selected = nether_portals.selected();
+        let hover = response.hover_pos();
+        let mut hovered = None;
+        for pin in &pins {
+            let pos = self.to_screen(rect.center(), pin.cords);
+            if !rect.contains(pos) {
+                continue;
+            }
+            let color = pin
+                .tags
+                .first()
+                .map_or(visuals.text_color(), |tag| tags.color(tag));
+            painter.circle_filled(pos, PIN_RADIUS, color);
+            if Some(pin.id) == selected {
+                painter.circle_stroke(
+                    pos,
+                    PIN_RADIUS + 3.0,
+                    Stroke::new(2.0, visuals.strong_text_color()),
+                );
+            }
+            painter.text(
+                pos + Vec2::new(PIN_RADIUS + 2.0, 0.0),
+                Align2::LEFT_CENTER,
+                &pin.name,
+                FontId::proportional(12.0),
+                visuals.text_color(),
+            );
+            // Its tags under the name, each in its own color
+            let mut tag_pos = pos + Vec2::new(PIN_RADIUS + 2.0, 7.0);
+            for tag in &pin.tags {
+                let tag_rect = painter.text(
+                    tag_pos,
+                    Align2::LEFT_TOP,
+                    tag,
+                    FontId::proportional(10.0),
+                    tags.color(tag),
+                );
+                tag_pos.x += tag_rect.width() + 4.0;
+            }
+            if hover.is_some_and(|hover| hover.distance(pos) <= PIN_RADIUS + 2.0) {
+                hovered = Some(pin);
+            }
+        }
+
+        let clicked = response.clicked();
+        if let Some(pin) = hovered {
+            let tag_colors: Vec<_> = pin
+                .tags
+                .iter()
+                .map(|tag| (tag.clone(), tags.color(tag)))
+                .collect();
+            response.on_hover_ui_at_pointer(|ui| {
+                ui.strong(&pin.name);
+                ui.label(format!("x: {}, z: {}", pin.cords.0, pin.cords.1));
+                ui.horizontal_wrapped(|ui| {
+                    for (tag, color) in tag_colors {
+                        ui.label(RichText::new(tag).strong().color(color));
+                    }
+                });
+            });
+            // Show the pair that was clicked on
+            if clicked {
+                nether_portals.select(pin.id);
+            }
+        }
+    }
+
+    fn to_screen(&self, middle: Pos2, cords: (i32, i32)) -> Pos2 {
+        //! Where (x, z) is drawn; north (-z) is up
+        let x = (cords.0 as f32 - self.center.0) / self.zoom;
+        let z = (cords.1 as f32 - self.center.1) / self.zoom;
+        middle + Vec2::new(x, z)
+    }
+}
+
+pub fn map_window(nether_portals: &mut NetherPortals, ctx: &Context) {
+    //! Shows the "Map" window (if its open)
+    let mut map = std::mem::take(nether_portals.map_view_mut());
+    let mut display = std::mem::take(&mut map.display);
+
+    display.show_with(ctx, |ui| map.show(nether_portals, ui));
+
+    map.display = display;
+    *nether_portals.map_view_mut() = map;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pages::nether_portals_page::portals::{
+        FieldValue, NetherPortalText, PortalField, PortalText,
+    };
+    use std::collections::BTreeMap;
+
+    fn side(name: &str, cords: (i32, i32, i32), tags: &str) -> PortalText {
+        let mut pt = PortalText::build(name.to_string(), cords);
+        pt.set_field(PortalField::Tags, FieldValue::Text(tags.to_string()));
+        pt
+    }
+
+    #[test]
+    fn pins_carry_tags_and_follow_the_filter() {
+        let mut nether_portals = NetherPortals::default();
+        nether_portals.consume_npt(NetherPortalText::build_from(
+            1,
+            side("Hub", (800, 70, -80), "hub, public"),
+            side("Hub N", (100, 70, -10), ""),
+        ));
+        nether_portals.consume_npt(
+            NetherPortalText::build_from(
+                2,
+                side("Farm", (40, 64, 16), "farm"),
+                side("Farm N", (5, 64, 2), ""),
+            )
+            .with_sides(BTreeMap::from([(
+                Dimension::the_end().id,
+                side("Farm E", (0, 60, 0), "farm"),
+            )])),
+        );
+
+        let hub = MapPin {
+            id: 1,
+            name: "Hub".to_string(),
+            cords: (800, -80),
+            tags: vec!["hub".to_string(), "public".to_string()],
+        };
+        assert_eq!(pins(&nether_portals, &Dimension::overworld())[0], hub);
+        assert_eq!(pins(&nether_portals, &Dimension::overworld()).len(), 2);
+        // Only pair 2 has a side in the end
+        let end: Vec<i32> = pins(&nether_portals, &Dimension::the_end())
+            .iter()
+            .map(|pin| pin.id)
+            .collect();
+        assert_eq!(end, [2]);
+
+        nether_portals.tags_mut().set_filter("public");
+        assert_eq!(pins(&nether_portals, &Dimension::overworld()), [hub]);
+    }
+}
@@ -0,0 +1,254 @@
+use eframe::egui::{CollapsingHeader, ComboBox, Context, DragValue, Grid, Ui};
+
+use super::{
+    paste_location::{parse_location, travel_dimension},
+    portals::{NetherPortals, WORLD_BORDER},
+};
+use crate::{dimension::Dimension, windows::client_windows::GenericWindow};
+
+type Cords = (i32, i32, i32);
+
+// How many portals the "Nearest to this portal" section lists
+const NEAREST_TO_SELECTED: usize = 5;
+
+// A portal and how to get there from some point
+#[derive(Clone, Debug)]
+pub struct Nearby {
+    pub id: i32,
+    pub name: String,
+    pub cords: Cords,
+    pub distance_2d: f64,
+    pub distance_3d: f64,
+    // Degrees clockwise from north (-z)
+    pub bearing: f64,
+    // Blocks walked if you take the trip in the other dimension instead (None == the pair has no side there)
+    pub other_distance: Option<f64>,
+}
+
+fn distance_2d(a: Cords, b: Cords) -> f64 {
+    let (dx, dz) = ((b.0 - a.0) as f64, (b.2 - a.2) as f64);
+    (dx * dx + dz * dz).sqrt()
+}
+
+fn distance_3d(a: Cords, b: Cords) -> f64 {
+    let dy = (b.1 - a.1) as f64;
+    (distance_2d(a, b).powi(2) + dy * dy).sqrt()
+}
+
+pub fn bearing(from: Cords, to: Cords) -> f64 {
+    //! North is -z & east is +x in minecraft
+    let (dx, dz) = ((to.0 - from.0) as f64, (to.2 - from.2) as f64);
+    dx.atan2(-dz).to_degrees().rem_euclid(360.0)
+}
+
+pub fn compass(bearing: f64) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    POINTS[((bearing + 22.5) / 45.0) as usize % POINTS.len()]
+}
+
+pub fn nearest(
+    nether_portals: &NetherPortals,
+    dimension: &Dimension,
+    from: Cords,
+    count: usize,
+) -> Vec<Nearby> {
+    //! The (count) portals in (dimension) closest to (from), closest first (by 2D distance)
+    //!
+    //! Pairs without a side in (dimension) are left out
+    let other = travel_dimension(dimension);
+    let from_other = dimension.convert_cords(&other, from);
+
+    let mut nearby: Vec<Nearby> = nether_portals
+        .pairs_ref()
+        .keys()
+        .filter_map(|id| {
+            let here = nether_portals.side_text(*id, &dimension.id)?;
+            let there = nether_portals.side_text(*id, &other.id);
+            let cords = here.cords();
+            Some(Nearby {
+                id: *id,
+                name: here.true_name_ref().clone(),
+                cords,
+                distance_2d: distance_2d(from, cords),
+                distance_3d: distance_3d(from, cords),
+                bearing: bearing(from, cords),
+                other_distance: there.map(|there| distance_2d(from_other, there.cords())),
+            })
+        })
+        .collect();
+
+    nearby.sort_by(|a, b| a.distance_2d.total_cmp(&b.distance_2d));
+    nearby.truncate(count);
+    nearby
+}
+
+fn nearby_grid(nearby: &[Nearby], dimension: &Dimension, id: &str, ui: &mut Ui) -> Option<i32> {
+    //! Returns the id of a pair the user wants to look at
+    let mut select = None;
+    Grid::new(id).striped(true).show(ui, |ui| {
+        ui.strong("Name");
+        ui.strong("Cords");
+        ui.strong("2D");
+        ui.strong("3D");
+        ui.strong("Direction");
+        ui.strong(format!("Walk in {}", travel_dimension(dimension).name));
+        ui.end_row();
+
+        for portal in nearby {
+            ui.label(&portal.name);
+            ui.label(format!("{:?}", portal.cords));
+            ui.label(format!("{:.0}", portal.distance_2d));
+            ui.label(format!("{:.0}", portal.distance_3d));
+            ui.label(format!(
+                "{} ({:.0}°)",
+                compass(portal.bearing),
+                portal.bearing
+            ));
+            match portal.other_distance {
+                Some(distance) => ui.label(format!("{:.0}", distance)),
+                None => ui.weak("-"),
+            };
+            if ui.button("Select").clicked() {
+                select = Some(portal.id);
+            }
+            ui.end_row();
+        }
+    });
+    select
+}
+
+pub fn nearest_to_selected(nether_portals: &mut NetherPortals, id: i32, ui: &mut Ui) {
+    //! The portals closest to the overworld side of the selected pair
+    let select = CollapsingHeader::new("Nearest to this portal")
+        .show(ui, |ui| {
+            let overworld = Dimension::overworld();
+            let from = nether_portals.side_text(id, &overworld.id)?.cords();
+            // One more, because the closest one is the portal itself
+            let mut nearby = nearest(nether_portals, &overworld, from, NEAREST_TO_SELECTED + 1);
+            nearby.retain(|portal| portal.id != id);
+            nearby_grid(&nearby, &overworld, "nearest to selected", ui)
+        })
+        .body_returned
+        .flatten();
+
+    if let Some(id) = select {
+        nether_portals.select(id);
+    }
+}
+
+// State of the "Nearest Portals" window
+pub struct NearestTool {
+    cords: Cords,
+    dimension: Dimension,
+    count: usize,
+    // F3+C, a waypoint share or "x y z"
+    text: String,
+    // The dimension the results are in
+    results: (Dimension, Vec<Nearby>),
+    pub display: GenericWindow,
+}
+
+impl Default for NearestTool {
+    fn default() -> Self {
+        Self {
+            cords: (0, 64, 0),
+            dimension: Dimension::overworld(),
+            count: 10,
+            text: String::default(),
+            results: (Dimension::overworld(), Vec::new()),
+            display: GenericWindow::new("Nearest Portals"),
+        }
+    }
+}
+
+impl NearestTool {
+    fn show(&mut self, nether_portals: &mut NetherPortals, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("From text:");
+            ui.text_edit_singleline(&mut self.text);
+            if ui.button("Use").clicked() {
+                if let Some(location) = parse_location(nether_portals.dimensions_ref(), &self.text)
+                {
+                    self.cords = location.cords;
+                    if let Some(dimension) = location.dimension {
+                        self.dimension = dimension;
+                    }
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            let border = -WORLD_BORDER..=WORLD_BORDER;
+            ui.label("X:");
+            ui.add(DragValue::new(&mut self.cords.0).clamp_range(border.clone()));
+            ui.label("Y:");
+            ui.add(DragValue::new(&mut self.cords.1));
+            ui.label("Z:");
+            ui.add(DragValue::new(&mut self.cords.2).clamp_range(border));
+            ComboBox::from_id_source("nearest dimension")
+                .selected_text(&self.dimension.name)
+                .show_ui(ui, |ui| {
+                    for dimension in nether_portals.dimensions_ref().all() {
+                        let name = dimension.name.clone();
+                        ui.selectable_value(&mut self.dimension, dimension, name);
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("How many:");
+            ui.add(DragValue::new(&mut self.count).clamp_range(1..=100));
+            if ui.button("Find Nearest").clicked() {
+                let dimension = self.dimension.clone();
+                let results = nearest(nether_portals, &dimension, self.cords, self.count);
+                self.results = (dimension, results);
+            }
+        });
+        ui.separator();
+
+        let (dimension, results) = &self.results;
+        if results.is_empty() {
+            ui.label("No results yet");
+            return;
+        }
+        if let Some(id) = nearby_grid(results, dimension, "nearest portals", ui) {
+            nether_portals.select(id);
+        }
+    }
+}
+
+pub fn nearest_window(nether_portals: &mut NetherPortals, ctx: &Context) {
+    //! Shows the "Nearest Portals" window (if its open)
+    let mut tool = std::mem::take(nether_portals.nearest_mut());
+    let mut display = std::mem::take(&mut tool.display);
+
+    display.show_with(ctx, |ui| tool.show(nether_portals, ui));
+
+    tool.display = display;
+    *nether_portals.nearest_mut() = tool;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bearings_follow_minecraft_axes() {
+        let from = (10, 64, 10);
+        assert_eq!(bearing(from, (10, 64, -90)), 0.0);
+        assert_eq!(bearing(from, (110, 64, 10)), 90.0);
+        assert_eq!(bearing(from, (10, 64, 110)), 180.0);
+        assert_eq!(bearing(from, (-90, 64, 10)), 270.0);
+        assert_eq!(bearing(from, (20, 0, 0)), 45.0);
+    }
+
+    #[test]
+    fn compass_points() {
+        assert_eq!(compass(0.0), "N");
+        assert_eq!(compass(22.4), "N");
+        assert_eq!(compass(22.5), "NE");
+        assert_eq!(compass(90.0), "E");
+        assert_eq!(compass(200.0), "S");
+        assert_eq!(compass(315.0), "NW");
+        // Back around to north
+        assert_eq!(compass(359.9), "N");
+    }
+}
@@ -1,203 +1,517 @@
 use crate::{
-    err_tools::ErrorX,
-    increment::Inc,
+    connection::{call_unless, Service},
+    dimension::Dimension,
     pages::nether_portals_page::{
         download_npt::download_nether_portals,
-        portals::{NetherPortals, PortalText},
+        portals::{FieldChange, NetherPortals, SaveScope},
     },
-    thread_tools::SPromise,
+    thread_tools::{RequestState, WakingSender},
     url_tools::{Routes, Urls},
     windows::{client_windows::Loglet, error_messages::ErrorMessage},
-    HandleError, MagicError, StatusCheck,
+    BorkError, HandleError, Realm, StatusCheck,
 };
-use eframe::egui::Ui;
-use std::sync::mpsc::Sender;
+use eframe::egui::{Context, ProgressBar, ScrollArea, Ui, Window};
 use std::sync::Once;
 use tokio::runtime::Runtime;
 
-use super::{display::displayer, portals::NetherPortalText};
+use super::{
+    audit::AuditEntry,
+    backup::backup_window,
+    display::displayer,
+    history::{history_shortcuts, history_window, HistoryAction},
+    lint::lint_window,
+    log_import::log_import_window,
+    map::map_window,
+    nearest::nearest_window,
+    portal_csv::csv_window,
+    portals::{NetherPortalText, SavedPair},
+    sides::dimensions_window,
+    tags::tags_window,
+    waypoints::waypoint_window,
+    world_save::world_import_window,
+};
 
 // Globals
 static START: Once = Once::new();
 
 fn check_promises() {}
 
-fn save_nether_portal(npt: NetherPortalText) -> Result<ureq::Response, MagicError> {
+pub fn save_nether_portal(npt: NetherPortalText) -> Result<ureq::Response, BorkError> {
     let url = &Urls::default(Routes::UpdateNetherPortalText);
     let response = ureq::post(url).send_json(npt)?;
 
     Ok(response)
 }
 
-//fn save_all_changes() {
-//}
+fn build_npt(nether_portals: &NetherPortals, id: i32) -> Result<NetherPortalText, BorkError> {
+    //! Build a NetherPortalText from the saved PortalTexts of a pair
+    nether_portals
+        .to_npt(id)
+        .ok_or(BorkError::validation(&format!(
+            "Failed to save struct NetherPortal because of bad id: |{}|",
+            id
+        )))
+}
 
-fn save_this_change(
+fn send_npts(
     nether_portals: &mut NetherPortals,
-    neth_key: &String,
+    saves: Vec<(NetherPortalText, Option<SaveScope>, AuditEntry)>,
     runtime: &Runtime,
-    ow_key: &String,
-) -> Result<(), MagicError> {
-    // Error Message Maker
-    let errmsg = |key: &String| {
-        format!(
-            "Failed to save struct NetherPortal because of bad key: |{}|",
-            key
-        )
-    };
+    ctx: &Context,
+) {
+    //! Send every NetherPortalText to the server, one after another, in a single background Request
+    //!
+    //! What the server said about each one goes to the audit log; the Request only keeps the first failure.
+    //! Each one the server takes goes back to NetherPortals, so its edits become the saved text (try_update_saved)
+    let audit_sender = nether_portals.audit_log_mut().sender_clone();
+    let saved_sender = nether_portals.saved_sender_clone();
+    let name = format!("Save |{}| portal(s)", saves.len());
+
+    // A retry sends every one of them again
+    nether_portals
+        .text_request_mut()
+        .spawn(runtime, ctx, &name, move |responder| {
+            let mut failure = None;
+            for (index, (npt, scope, audit_entry)) in saves.iter().enumerate() {
+                responder.check_cancelled()?;
+                // Saving the same portal twice changes nothing, so it can be retried
+                let subfn = || -> Result<(), BorkError> {
+                    save_nether_portal(npt.clone())?.status_check()?;
+                    Ok(())
+                };
+                let result = call_unless(Service::Text, true, || responder.is_cancelled(), subfn);
+                audit_sender.send(audit_entry.clone().respond(&result)).ok();
+                match result {
+                    Ok(()) => {
+                        let saved = SavedPair {
+                            npt: npt.clone(),
+                            scope: *scope,
+                        };
+                        saved_sender.send(saved).ok();
+                    }
+                    Err(err) => {
+                        failure.get_or_insert(err);
+                    }
+                }
+                responder.progress((index + 1) as f32 / saves.len() as f32);
+            }
+
+            match failure {
+                None => Ok(()),
+                Some(err) => Err(err),
+            }
+        });
+}
+
+fn audit_entry(
+    nether_portals: &NetherPortals,
+    npt: &NetherPortalText,
+    changes: Vec<String>,
+) -> AuditEntry {
+    let username = nether_portals.audit_log_ref().username_ref().clone();
+    AuditEntry::new(username, npt.get_id(), npt.ow_true_name(), changes)
+}
+
+fn scope_pairs(nether_portals: &NetherPortals, scope: SaveScope) -> Vec<i32> {
+    //! The ids of the pairs that a SaveScope covers
+    match scope {
+        SaveScope::All => nether_portals.dirty_pairs(),
+        _ => nether_portals.selected().into_iter().collect(),
+    }
+}
+
+fn true_name(nether_portals: &NetherPortals, realm: &Realm, id: i32) -> String {
+    nether_portals
+        .pair_ref(id)
+        .map(|pair| pair.true_name_ref(realm).clone())
+        .unwrap_or_default()
+}
 
-    // Get Refs to BOTH nether&overworld btrees
-    let ow_btree = nether_portals
-        .overworld_ref()
-        .get(ow_key)
-        .ok_or(ErrorX::new_box(&errmsg(ow_key)))?
-        .btree_ref();
-    let neth_btree = nether_portals
-        .nether_ref()
-        .get(neth_key)
-        .ok_or(ErrorX::new_box(&errmsg(ow_key)))?
-        .btree_ref();
-
-    // Convert BOTH to type PortalText structs
-    let overworld = PortalText::from_btree(ow_btree)?;
-    let nether = PortalText::from_btree(neth_btree)?;
-
-    // Build a NetherPortalText the PortalTexts
-    let ow_id = nether_portals.overworld_ref()[ow_key].get_id();
-    let neth_id = nether_portals.nether_ref()[neth_key].get_id();
-    let id = if ow_id < 0 { neth_id } else { ow_id };
-
-    let npt = NetherPortalText::build_from(id, overworld, nether);
-
-    // Create a notifier
-    let (spromise, sender) = SPromise::make_promise();
-    nether_portals.set_text_request(spromise);
-
-    // spawn a async thread to handle the request
-    runtime.spawn(async move {
-        let subfn = || -> Result<(), MagicError> {
-            save_nether_portal(npt)?.status_check()?;
-            Ok(())
-        };
-        // Some == Err() & None == Ok(); Result<> doesnt impl Default so i couldn't use it lol
-        if let Err(err) = subfn() {
-            sender.send(Some(err.to_string()))
-        } else {
-            sender.send(None)
+fn save_changes(
+    nether_portals: &mut NetherPortals,
+    scope: SaveScope,
+    runtime: &Runtime,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! Send the edits covered by (scope) to the server
+    //!
+    //! The edits only become the saved text once the server takes them; a failed save leaves them dirty
+    let mut saves = Vec::new();
+    for id in scope_pairs(nether_portals, scope) {
+        let mut changes = Vec::new();
+        for realm in scope.realms() {
+            if let Some(nether_portal) = nether_portals.portal_ref(&realm, id) {
+                nether_portal.changes().iter().for_each(|change| {
+                    changes.push(format!(
+                        "{} {}: |{}| -> |{}|",
+                        realm.name(),
+                        change.field.name(),
+                        change.old,
+                        change.new
+                    ))
+                });
+            }
         }
-    });
+        if scope.includes_sides() {
+            for (dimension_id, side) in nether_portals.dirty_sides(id) {
+                let name = nether_portals.dimension_name(&dimension_id);
+                side.changes().iter().for_each(|change| {
+                    changes.push(format!(
+                        "{} {}: |{}| -> |{}|",
+                        name,
+                        change.field.name(),
+                        change.old,
+                        change.new
+                    ))
+                });
+            }
+        }
+        let npt = nether_portals
+            .to_edited_npt(id, scope)
+            .ok_or(BorkError::validation(&format!(
+                "Failed to save struct NetherPortal because of bad id: |{}|",
+                id
+            )))?;
+        let audit_entry = audit_entry(nether_portals, &npt, changes);
+        saves.push((npt, Some(scope), audit_entry));
+    }
+
+    if saves.is_empty() {
+        return Err(BorkError::validation("There are no changes to save"));
+    }
+    tracing::info!(portals = saves.len(), "Saving changes");
+    send_npts(nether_portals, saves, runtime, ctx);
 
     Ok(())
 }
 
 fn setup_displayables(nether_portals: &mut NetherPortals) {
-    //! Iter through each NP and make a key collection from them
-
-    // If its empty iter through and convert NP to BTree
-    if !nether_portals.is_overworld_empty() {
-        nether_portals
-            .overworld_mut()
-            .iter_mut()
-            .for_each(|(_, nether_portal)| {
-                if nether_portal.is_empty() {
-                    nether_portal.set_as_btree()
-                }
-            });
+    //! Iter through each pair and make an id collection from them
 
-        // Create a collection of keys to be iterated back&forth over
-        let keys: Vec<String> = nether_portals
-            .overworld_ref()
-            .keys()
-            .map(|ref_s| ref_s.to_string())
-            .collect();
+    if !nether_portals.is_empty() {
+        // Create a collection of ids to be iterated back&forth over
+        let ids: Vec<i32> = nether_portals.pairs_ref().keys().copied().collect();
 
         // Append to NetherPortals
-        nether_portals.set_ow_pos(keys);
+        nether_portals.set_positions(ids);
     }
-    if !nether_portals.is_nether_empty() {
-        nether_portals
-            .nether_mut()
-            .iter_mut()
-            .for_each(|(_, nether_portal)| {
-                if nether_portal.is_empty() {
-                    nether_portal.set_as_btree()
-                }
-            });
-        let keys: Vec<String> = nether_portals
-            .nether_ref()
-            .keys()
-            .map(|ref_s| ref_s.to_string())
-            .collect();
-        nether_portals.set_neth_pos(keys);
+}
+
+fn is_request_processing(
+    nether_portals: &mut NetherPortals,
+    runtime: &Runtime,
+    ui: &mut Ui,
+) -> Result<(), BorkError> {
+    //! Shows how the last save is going. Err() once, when it just failed
+    let request = nether_portals.text_request_mut();
+    let finished = request.poll();
+
+    match request.state_ref() {
+        RequestState::Pending => {
+            ui.spinner();
+        }
+        RequestState::Progress(done) => {
+            ui.add(ProgressBar::new(*done).desired_width(100.0));
+        }
+        RequestState::Error(err) => {
+            let err = err.clone();
+            if ui.button("Retry Save").clicked() {
+                tracing::info!("Retrying the save");
+                request.retry(runtime, ui.ctx());
+            }
+            if finished {
+                return Err(err);
+            }
+        }
+        RequestState::Idle | RequestState::Success(_) => {}
     }
+    if request.is_pending() && ui.button("Cancel Save").clicked() {
+        tracing::info!("Cancelled the save");
+        request.cancel();
+    }
+
+    Ok(())
 }
 
-fn is_request_processing(np: &NetherPortals, ui: &mut Ui) -> Result<(), MagicError> {
-    // Bind let: if  there is currently a Promise to uphold inside SPromise
-    let promise = match np.text_request_ref().spromise_ref() {
-        Some(promise) => promise,
+fn save_npt(
+    nether_portals: &mut NetherPortals,
+    runtime: &Runtime,
+    ui: &mut Ui,
+) -> Result<(), BorkError> {
+    // If the request is still processing: Ui.Spinner; if it failed, a retry button
+    is_request_processing(nether_portals, runtime, ui)?;
+
+    // On button click, check the form(s) and show what is about to be saved
+    let buttons = [
+        ("Save Overworld", SaveScope::Overworld),
+        ("Save Nether", SaveScope::Nether),
+        ("Save This Change", SaveScope::Pair),
+        ("Save All Changes", SaveScope::All),
+    ];
+    for (name, scope) in buttons {
+        if ui.button(name).clicked() {
+            // Dont bother the server with a form that has problems
+            check_for_problems(nether_portals, scope)?;
+
+            nether_portals.set_save_preview(Some(scope));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_for_problems(nether_portals: &NetherPortals, scope: SaveScope) -> Result<(), BorkError> {
+    //! Validate every edit form the scope covers; Err() lists every problem found
+    let mut problems = Vec::new();
+    for id in scope_pairs(nether_portals, scope) {
+        for realm in scope.realms() {
+            let nether_portal = match nether_portals.portal_ref(&realm, id) {
+                Some(nether_portal) => nether_portal,
+                None => continue,
+            };
+            for (field, problem) in nether_portal.edit_ref().validate(&realm.dimension()) {
+                problems.push(format!(
+                    "{} |{}| {}: {}",
+                    realm.name(),
+                    nether_portal.pt_ref().true_name_ref(),
+                    field.name(),
+                    problem
+                ));
+            }
+        }
+
+        if !scope.includes_sides() {
+            continue;
+        }
+        for (dimension_id, side) in nether_portals.dirty_sides(id) {
+            // A side in a dimension that is no longer known still gets checked against the overworld's limits
+            let dimension = nether_portals
+                .dimensions_ref()
+                .get(&dimension_id)
+                .unwrap_or_else(Dimension::overworld);
+            for (field, problem) in side.edit_ref().validate(&dimension) {
+                problems.push(format!(
+                    "{} |{}| {}: {}",
+                    dimension.name,
+                    true_name(nether_portals, &Realm::Overworld, id),
+                    field.name(),
+                    problem
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(BorkError::validation(&format!(
+        "Can not save, fix these first:\n{}",
+        problems.join("\n")
+    )))
+}
+
+fn show_changes(ui: &mut Ui, name: &str, changes: &[FieldChange]) {
+    ui.strong(name);
+    if changes.is_empty() {
+        ui.label("No changes");
+    }
+    changes.iter().for_each(|change| {
+        ui.label(format!(
+            "{}: |{}| -> |{}|",
+            change.field.name(),
+            change.old,
+            change.new
+        ));
+    });
+}
+
+fn save_preview(
+    nether_portals: &mut NetherPortals,
+    runtime: &Runtime,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! A window that shows the difference between the saved and edited text before it is saved
+    let scope = match nether_portals.get_save_preview() {
+        Some(scope) => scope,
         None => return Ok(()),
     };
 
-    // if promise is NOT ready(request not finished), THEN ui.spinner
-    // TODO change Option<String> to Result<(), MagicError>
-    let some_err = match promise.ready() {
-        Some(err) => err,
-        None => {
-            ui.spinner();
-            return Ok(());
+    // (name, changes) of every side that would be saved
+    let mut sides = Vec::new();
+    for id in scope_pairs(nether_portals, scope) {
+        for realm in scope.realms() {
+            let changes = nether_portals
+                .portal_ref(&realm, id)
+                .map(|nether_portal| nether_portal.changes())
+                .unwrap_or_default();
+            let key = true_name(nether_portals, &realm, id);
+            sides.push((format!("{}: {}", realm.name(), key), changes));
         }
-    };
+        if scope.includes_sides() {
+            let ow_key = true_name(nether_portals, &Realm::Overworld, id);
+            for (dimension_id, side) in nether_portals.dirty_sides(id) {
+                let name = nether_portals.dimension_name(&dimension_id);
+                sides.push((format!("{}: {}", name, ow_key), side.changes()));
+            }
+        }
+    }
+
+    let mut confirmed = false;
+    let mut is_open = true;
+    Window::new("Save These Changes?")
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                if sides.is_empty() {
+                    ui.label("Nothing has changed");
+                }
+                sides.iter().for_each(|(name, changes)| {
+                    show_changes(ui, name, changes);
+                    ui.separator();
+                });
+            });
+            ui.horizontal(|ui| {
+                confirmed = ui.button("Confirm Save").clicked();
+                if ui.button("Cancel").clicked() {
+                    is_open = false;
+                }
+            });
+        });
 
-    // Some(err) == Err(), None == Successful request!
-    match some_err {
-        Some(err) => Err(ErrorX::new_box(err)),
-        None => Ok(()),
+    if !confirmed {
+        nether_portals.set_save_preview(is_open.then_some(scope));
+        return Ok(());
     }
+    nether_portals.set_save_preview(None);
+
+    // Execute async request
+    save_changes(nether_portals, scope, runtime, ctx)
 }
 
-fn save_npt(
+fn revert_saved_edit(
     nether_portals: &mut NetherPortals,
+    index: usize,
     runtime: &Runtime,
-    ui: &mut Ui,
-) -> Result<(), MagicError> {
-    // If the request is still processing: True => Ui.Spinner; False => Reset the np.request
-    is_request_processing(nether_portals, ui).otherwise(|_| {
-        nether_portals.set_text_request(SPromise::make_no_promise(None));
-    })?;
-    // TODO use .inspect_err() in the future when it is stable (currently unstable only)
-
-    // On button click, Save Changes
-    if ui.button("Save This Change").clicked() {
-        let ow_key = &nether_portals.get_ow_pos().unwrap();
-        let neth_key = &nether_portals.get_neth_pos().unwrap();
-
-        // Set both (NetherPortals.portal_text)s with data from .as_btree
-        nether_portals
-            .overworld_mut()
-            .get_mut(ow_key)
-            .unwrap()
-            .set_pt()?;
-        nether_portals
-            .nether_mut()
-            .get_mut(neth_key)
-            .unwrap()
-            .set_pt()?;
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! Undo an edit that was already saved by sending the old value back to the server
+    let edit = nether_portals
+        .history_ref()
+        .undo_ref()
+        .get(index)
+        .cloned()
+        .ok_or(BorkError::validation(
+            "That edit is no longer in the history",
+        ))?;
+
+    if nether_portals.pair_ref(edit.id).is_none() {
+        return Err(BorkError::validation(&format!(
+            "Can not revert, |{}| is no longer loaded",
+            edit.name
+        )));
+    }
+
+    // Put the old value back and remember that it happened
+    nether_portals.revert_field(&edit.dimension.id, edit.id, edit.field, edit.old.clone())?;
+    let revert = edit.inverse();
+    let changes = vec![format!("(revert) {}", revert.describe())];
+    nether_portals.history_mut().record_saved(revert);
+
+    // The compensating update
+    let npt = build_npt(nether_portals, edit.id)?;
+    let audit_entry = audit_entry(nether_portals, &npt, changes);
+    send_npts(nether_portals, vec![(npt, None, audit_entry)], runtime, ctx);
+
+    Ok(())
+}
 
-        // Execute async request
-        save_this_change(nether_portals, neth_key, runtime, ow_key)?;
+fn edit_history(
+    nether_portals: &mut NetherPortals,
+    runtime: &Runtime,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! The history window & the undo/redo keyboard shortcuts
+    let action = history_window(nether_portals.history_mut(), ctx).or(history_shortcuts(ctx));
+
+    match action {
+        Some(HistoryAction::Undo) => {
+            nether_portals.undo();
+        }
+        Some(HistoryAction::Redo) => {
+            nether_portals.redo();
+        }
+        Some(HistoryAction::Revert(index)) => {
+            revert_saved_edit(nether_portals, index, runtime, ctx)?;
+        }
+        None => {}
     }
 
     Ok(())
 }
 
+fn portal_history(nether_portals: &mut NetherPortals, runtime: &Runtime, ui: &mut Ui) {
+    //! Buttons for the server side revision history and the local audit log
+    if ui.button("Fetch Portal History").clicked() {
+        if let Some(id) = nether_portals.selected() {
+            let revision_history = nether_portals.revision_history_mut();
+            revision_history.fetch(id, runtime, ui.ctx());
+            revision_history.display.open_window();
+        }
+    }
+    nether_portals
+        .audit_log_mut()
+        .display
+        .show_open_window_on_click_button(ui, "Audit Log");
+    nether_portals
+        .csv_import_mut()
+        .display
+        .show_open_window_on_click_button(ui, "CSV Import/Export");
+    nether_portals
+        .waypoints_mut()
+        .display
+        .show_open_window_on_click_button(ui, "Waypoints");
+    nether_portals
+        .backup_restore_mut()
+        .display
+        .show_open_window_on_click_button(ui, "Backup/Restore");
+    nether_portals
+        .world_import_mut()
+        .display
+        .show_open_window_on_click_button(ui, "World Import");
+    nether_portals
+        .log_import_mut()
+        .display
+        .show_open_window_on_click_button(ui, "Log Import");
+    nether_portals
+        .side_forms_mut()
+        .display
+        .show_open_window_on_click_button(ui, "Dimensions");
+    nether_portals
+        .tags_mut()
+        .display
+        .show_open_window_on_click_button(ui, "Tags");
+    nether_portals
+        .nearest_mut()
+        .display
+        .show_open_window_on_click_button(ui, "Nearest Portals");
+    nether_portals
+        .lint_report_mut()
+        .display
+        .show_open_window_on_click_button(ui, "Lint Report");
+    nether_portals
+        .map_view_mut()
+        .display
+        .show_open_window_on_click_button(ui, "Map");
+}
+
 fn reload_npt(
-    nether_portal_sender: Sender<NetherPortalText>,
-    err_msg_sender: Sender<Loglet>,
+    nether_portal_sender: WakingSender<NetherPortalText>,
+    err_msg_sender: WakingSender<Loglet>,
     runtime: &Runtime,
     ui: &mut Ui,
 ) {
     if ui.button("Reload From DB").clicked() {
+        tracing::info!("Reloading portals from the db");
         download_nether_portals(nether_portal_sender, err_msg_sender, runtime);
     }
 }
@@ -205,7 +519,6 @@ fn reload_npt(
 // Big Boi Function
 pub fn display_nether_portals_page(
     nether_portals: &mut NetherPortals,
-    unique: &mut Inc,
     err_msg: &mut ErrorMessage,
     runtime: &Runtime,
     ui: &mut Ui,
@@ -229,9 +542,32 @@ pub fn display_nether_portals_page(
             &runtime,
             ui,
         );
+        nether_portals
+            .history_mut()
+            .display
+            .show_open_window_on_click_button(ui, "Edit History");
+    });
+    ui.horizontal(|ui| {
+        portal_history(nether_portals, runtime, ui);
     });
 
-    displayer(nether_portals, unique, ui);
+    displayer(nether_portals, ui);
+
+    save_preview(nether_portals, runtime, &ui.ctx().clone()).consume_error(err_msg);
+    edit_history(nether_portals, runtime, &ui.ctx().clone()).consume_error(err_msg);
+    nether_portals
+        .audit_windows(&ui.ctx().clone())
+        .consume_error(err_msg);
+    csv_window(nether_portals, runtime, &ui.ctx().clone()).consume_error(err_msg);
+    waypoint_window(nether_portals, runtime, &ui.ctx().clone()).consume_error(err_msg);
+    backup_window(nether_portals, runtime, &ui.ctx().clone()).consume_error(err_msg);
+    world_import_window(nether_portals, runtime, &ui.ctx().clone()).consume_error(err_msg);
+    log_import_window(nether_portals, runtime, &ui.ctx().clone()).consume_error(err_msg);
+    dimensions_window(nether_portals, &ui.ctx().clone()).consume_error(err_msg);
+    tags_window(nether_portals, &ui.ctx().clone()).consume_error(err_msg);
+    nearest_window(nether_portals, &ui.ctx().clone());
+    lint_window(nether_portals, &ui.ctx().clone());
+    map_window(nether_portals, &ui.ctx().clone());
 
     check_promises();
 }
@@ -0,0 +1,361 @@
+use eframe::egui::{ComboBox, Ui};
+
+use super::{
+    history::Edit,
+    portals::{FieldValue, NetherPortals, PortalField},
+};
+use crate::dimension::{Dimension, Dimensions};
+
+type Cords = (i32, i32, i32);
+
+// Where a pasted location is. (dimension) is None when the text does not say
+#[derive(Clone, Debug, PartialEq)]
+pub struct PastedLocation {
+    pub dimension: Option<Dimension>,
+    pub cords: Cords,
+}
+
+pub fn dimension_from_text(dimensions: &Dimensions, text: &str) -> Option<Dimension> {
+    //! Dimension names as the game, server logs, Xaero's, JourneyMap & VoxelMap write them
+    //!
+    //! "minecraft:the_nether", "the-nether", "Nether", "DIM-1", "dim%-1"; custom dimensions by id, path or name
+    let text = text.to_lowercase();
+    let all = dimensions.all();
+    // The most exact spelling wins, so "minecraft:the_end" is not found as a custom "end"
+    let spellings: [fn(&Dimension) -> String; 4] = [
+        |dimension| dimension.id.clone(),
+        |dimension| dimension.path().to_string(),
+        |dimension| dimension.path().replace('_', "-"),
+        |dimension| dimension.name.to_lowercase(),
+    ];
+    for spelling in spellings {
+        if let Some(dimension) = all.iter().find(|d| text.contains(&spelling(d))) {
+            return Some(dimension.clone());
+        }
+    }
+
+    // Old numbered folders (Forge DIM-1, Xaero's dim%-1)
+    let numbered = [
+        ("-1", Dimension::nether()),
+        ("1", Dimension::the_end()),
+        ("0", Dimension::overworld()),
+    ];
+    numbered.into_iter().find_map(|(number, dimension)| {
+        let found = ["dim%", "dim"]
+            .iter()
+            .any(|prefix| text.contains(&format!("{}{}", prefix, number)));
+        found.then_some(dimension)
+    })
+}
+
+fn number(text: &str) -> Option<i32> {
+    //! Block cords from a number that may have decimals (F3+C writes 12.50)
+    let number = text
+        .trim()
+        .trim_matches(|c: char| c == ',' || c == ']' || c == '[' || c == '"');
+    number.parse::<f64>().ok().map(|n| n.floor() as i32)
+}
+
+fn parse_f3c(dimensions: &Dimensions, text: &str) -> Option<PastedLocation> {
+    //! /execute in minecraft:the_nether run tp @s 12.50 64.00 -30.70 90.00 0.00
+    let rest = text.trim().strip_prefix("/execute in ")?;
+    let (dimension, rest) = rest.split_once(' ')?;
+    let rest = rest.trim().strip_prefix("run tp @s")?;
+    let parts: Vec<&str> = rest.split_whitespace().collect();
+
+    Some(PastedLocation {
+        dimension: dimension_from_text(dimensions, dimension),
+        cords: (
+            number(parts.first()?)?,
+            number(parts.get(1)?)?,
+            number(parts.get(2)?)?,
+        ),
+    })
+}
+
+fn parse_xaero_share(dimensions: &Dimensions, text: &str) -> Option<PastedLocation> {
+    //! xaero-waypoint:Home:H:100:64:-200:6:false:0:Internal-the-nether-waypoints
+    let start = text.find("xaero-waypoint:")?;
+    let parts: Vec<&str> = text[start..].split(':').collect();
+    // Xaero's writes '~' for an unknown height
+    let y = number(parts.get(4)?).unwrap_or(64);
+
+    Some(PastedLocation {
+        dimension: parts
+            .last()
+            .and_then(|last| dimension_from_text(dimensions, last)),
+        cords: (number(parts.get(3)?)?, y, number(parts.get(5)?)?),
+    })
+}
+
+fn parse_key_values(dimensions: &Dimensions, text: &str) -> Option<PastedLocation> {
+    //! [name:"Home", x:100, y:64, z:-200, dim:minecraft:the_nether] (JourneyMap & VoxelMap share this shape)
+    let value = |key: &str| -> Option<&str> {
+        text.trim()
+            .trim_matches(|c| c == '[' || c == ']')
+            .split(',')
+            .find_map(|part| part.trim().strip_prefix(key)?.strip_prefix(':'))
+    };
+
+    Some(PastedLocation {
+        dimension: value("dim")
+            .or_else(|| value("dimension"))
+            .and_then(|dimension| dimension_from_text(dimensions, dimension)),
+        cords: (
+            number(value("x")?)?,
+            number(value("y")?)?,
+            number(value("z")?)?,
+        ),
+    })
+}
+
+fn parse_triple(dimensions: &Dimensions, text: &str) -> Option<PastedLocation> {
+    //! The first three numbers in a row: "100 64 -200", "100, 64, -200", "X: 100 Y: 64 Z: -200"
+    let words: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ':' || c == '/')
+        .filter(|word| !word.is_empty())
+        // The axis labels, so they do not split up the numbers
+        .filter(|word| !["x", "y", "z"].contains(&word.to_lowercase().as_str()))
+        .collect();
+    let cords = words
+        .windows(3)
+        .find_map(|window| Some((number(window[0])?, number(window[1])?, number(window[2])?)));
+
+    Some(PastedLocation {
+        dimension: dimension_from_text(dimensions, text),
+        cords: cords?,
+    })
+}
+
+pub fn parse_location(dimensions: &Dimensions, text: &str) -> Option<PastedLocation> {
+    //! Try the specific formats first, a plain triple last
+    parse_f3c(dimensions, text)
+        .or_else(|| parse_xaero_share(dimensions, text))
+        .or_else(|| parse_key_values(dimensions, text))
+        .or_else(|| parse_triple(dimensions, text))
+}
+
+pub fn travel_dimension(dimension: &Dimension) -> Dimension {
+    //! Where the other end of a trip from (dimension) is: the nether for the overworld, the overworld for the rest
+    match dimension.id == Dimension::overworld().id {
+        true => Dimension::nether(),
+        false => Dimension::overworld(),
+    }
+}
+
+// State of the "Paste location" box
+#[derive(Default)]
+pub struct PasteLocation {
+    text: String,
+    // The side to fill when the text does not say which one (a dimension id); None == the overworld
+    fallback: Option<String>,
+    message: String,
+    // The cords the other side should (probably) have
+    suggestion: Option<(Dimension, Cords)>,
+}
+
+fn set_cords(nether_portals: &mut NetherPortals, dimension: &Dimension, id: i32, cords: Cords) {
+    //! Fill x, y & z of an edit form; each change goes into the edit history
+    let name = match nether_portals.side_text(id, &dimension.id) {
+        Some(pt) => pt.true_name_ref().clone(),
+        None => return,
+    };
+    let edit = match nether_portals.side_edit_mut(id, &dimension.id) {
+        Some(edit) => edit,
+        None => return,
+    };
+
+    let mut edits = Vec::new();
+    for (field, cord) in [
+        (PortalField::Xcord, cords.0),
+        (PortalField::Ycord, cords.1),
+        (PortalField::Zcord, cords.2),
+    ] {
+        let old = edit.field_value(field);
+        let new = FieldValue::Cord(cord);
+        if old != new {
+            edit.set_field(field, new.clone());
+            edits.push(Edit::new(
+                dimension.clone(),
+                id,
+                name.clone(),
+                field,
+                old,
+                new,
+            ));
+        }
+    }
+
+    edits
+        .into_iter()
+        .for_each(|edit| nether_portals.history_mut().record(edit));
+}
+
+pub fn paste_location(nether_portals: &mut NetherPortals, id: i32, ui: &mut Ui) {
+    //! A text box that takes F3+C, waypoint share strings or plain "x y z" and fills the matching side
+    let mut paste = std::mem::take(nether_portals.paste_location_mut());
+
+    // Only the sides this pair has can be filled
+    let sides: Vec<Dimension> = nether_portals
+        .side_texts(id)
+        .into_iter()
+        .map(|(dimension, _)| dimension)
+        .collect();
+    let fallback = paste
+        .fallback
+        .as_ref()
+        .and_then(|fallback| sides.iter().find(|side| &side.id == fallback))
+        .cloned()
+        .unwrap_or_else(Dimension::overworld);
+
+    ui.horizontal(|ui| {
+        ui.label("Paste location:");
+        ui.text_edit_singleline(&mut paste.text);
+        ComboBox::from_id_source("paste fallback")
+            .selected_text(format!("{} (if not in the text)", fallback.name))
+            .show_ui(ui, |ui| {
+                for side in &sides {
+                    ui.selectable_value(&mut paste.fallback, Some(side.id.clone()), &side.name);
+                }
+            });
+
+        if ui.button("Fill").clicked() {
+            match parse_location(nether_portals.dimensions_ref(), &paste.text) {
+                Some(location) => {
+                    let dimension = location.dimension.unwrap_or(fallback);
+                    match sides.iter().any(|side| side.id == dimension.id) {
+                        true => {
+                            set_cords(nether_portals, &dimension, id, location.cords);
+
+                            let other = travel_dimension(&dimension);
+                            let cords = dimension.convert_cords(&other, location.cords);
+                            paste.suggestion = Some((other, cords));
+                            paste.message =
+                                format!("Filled {} with {:?}", dimension.name, location.cords);
+                        }
+                        false => {
+                            paste.suggestion = None;
+                            paste.message =
+                                format!("This pair has no {} side, add one first", dimension.name);
+                        }
+                    }
+                }
+                None => {
+                    paste.suggestion = None;
+                    paste.message = "No coordinates found in the text".to_string();
+                }
+            }
+        }
+    });
+
+    ui.horizontal(|ui| {
+        if !paste.message.is_empty() {
+            ui.label(&paste.message);
+        }
+        if let Some((other, cords)) = paste.suggestion.clone() {
+            ui.label(format!("Suggested {}: {:?}", other.name, cords));
+            if ui.button("Use Suggestion").clicked() {
+                set_cords(nether_portals, &other, id, cords);
+                paste.suggestion = None;
+            }
+        }
+    });
+
+    *nether_portals.paste_location_mut() = paste;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimensions_are_found_in_every_spelling() {
+        let dimensions = Dimensions::default();
+        let found = |text: &str| dimension_from_text(&dimensions, text).map(|d| d.id);
+        let nether = Some(Dimension::nether().id);
+        let end = Some(Dimension::the_end().id);
+
+        assert_eq!(found("minecraft:the_nether"), nether);
+        assert_eq!(found("Internal-the-nether-waypoints"), nether);
+        assert_eq!(found("dim%-1"), nether);
+        assert_eq!(found("DIM-1"), nether);
+        assert_eq!(found("minecraft:the_end"), end);
+        assert_eq!(found("Internal-the-end-waypoints"), end);
+        assert_eq!(found("dim%1"), end);
+        assert_eq!(found("Overworld"), Some(Dimension::overworld().id));
+        assert_eq!(found("100 64 -200"), None);
+    }
+
+    #[test]
+    fn custom_dimensions_are_found_by_id() {
+        let mut dimensions = Dimensions::default();
+        let custom = Dimension::new("twilightforest:twilight_forest", "Twilight", 1.0, 0, 255);
+        dimensions.add(custom.clone()).ok();
+
+        let found = dimension_from_text(&dimensions, "dim:twilightforest:twilight_forest");
+        assert_eq!(found, Some(custom));
+    }
+
+    fn parsed(text: &str) -> Option<(Option<String>, Cords)> {
+        parse_location(&Dimensions::default(), text)
+            .map(|location| (location.dimension.map(|d| d.id), location.cords))
+    }
+
+    #[test]
+    fn parses_f3c() {
+        assert_eq!(
+            parsed("/execute in minecraft:the_nether run tp @s 12.50 64.00 -30.70 90.00 0.00"),
+            Some((Some(Dimension::nether().id), (12, 64, -31)))
+        );
+    }
+
+    #[test]
+    fn parses_xaero_share() {
+        assert_eq!(
+            parsed("xaero-waypoint:Home:H:100:64:-200:6:false:0:Internal-the-nether-waypoints"),
+            Some((Some(Dimension::nether().id), (100, 64, -200)))
+        );
+        // No height is written as '~'
+        assert_eq!(
+            parsed("xaero-waypoint:Home:H:100:~:-200:6:false:0:Internal-overworld-waypoints"),
+            Some((Some(Dimension::overworld().id), (100, 64, -200)))
+        );
+    }
+
+    #[test]
+    fn parses_key_values() {
+        assert_eq!(
+            parsed("[name:\"Home\", x:100, y:64, z:-200, dim:minecraft:the_end]"),
+            Some((Some(Dimension::the_end().id), (100, 64, -200)))
+        );
+        assert_eq!(parsed("[x:1, y:2, z:3]"), Some((None, (1, 2, 3))));
+    }
+
+    #[test]
+    fn parses_triples() {
+        assert_eq!(parsed("100 64 -200"), Some((None, (100, 64, -200))));
+        assert_eq!(parsed("100, 64, -200"), Some((None, (100, 64, -200))));
+        assert_eq!(
+            parsed("Nether X: 8 Y: 70 Z: -4"),
+            Some((Some(Dimension::nether().id), (8, 70, -4)))
+        );
+        assert_eq!(parsed("just 2 numbers 5"), None);
+        assert_eq!(parsed(""), None);
+    }
+
+    #[test]
+    fn other_side_of_a_trip() {
+        assert_eq!(
+            travel_dimension(&Dimension::overworld()),
+            Dimension::nether()
+        );
+        assert_eq!(
+            travel_dimension(&Dimension::nether()),
+            Dimension::overworld()
+        );
+        assert_eq!(
+            travel_dimension(&Dimension::the_end()),
+            Dimension::overworld()
+        );
+    }
+}
@@ -0,0 +1,539 @@
+use super::portals::{FieldValue, NetherPortalText, NetherPortals, PortalField, PortalText};
+use crate::{
+    connection::{call_unless, Service},
+    dimension::Dimension,
+    eframe_tools::{request_status, ModalMachine},
+    profiles::world_path,
+    thread_tools::Request,
+    url_tools::{Routes, Urls},
+    windows::client_windows::GenericWindow,
+    BorkError, Realm, StatusCheck,
+};
+use eframe::egui::{Context, Grid, ScrollArea, Ui};
+use std::collections::{BTreeMap, HashSet};
+use tokio::runtime::Runtime;
+
+// Shown in a column mapping when no column of the csv file is used
+const NO_COLUMN: &str = "(none)";
+
+// A column of the portal csv file
+#[derive(Clone)]
+enum CsvColumn {
+    Id,
+    Username,
+    Side(Realm, PortalField),
+    // A side in any other dimension, by dimension id
+    Extra(String, PortalField),
+}
+
+impl CsvColumn {
+    fn all(extra: &[Dimension]) -> Vec<CsvColumn> {
+        let mut columns = vec![CsvColumn::Id, CsvColumn::Username];
+        for realm in [Realm::Overworld, Realm::Nether] {
+            for field in PortalField::ALL {
+                columns.push(CsvColumn::Side(realm, field));
+            }
+        }
+        for dimension in extra {
+            for field in PortalField::ALL {
+                columns.push(CsvColumn::Extra(dimension.id.clone(), field));
+            }
+        }
+        columns
+    }
+
+    fn header(&self) -> String {
+        //! Same names as the server's json
+        match self {
+            CsvColumn::Id => "Id".to_string(),
+            CsvColumn::Username => "Username".to_string(),
+            CsvColumn::Side(realm, field) => {
+                format!("{}_{}", realm.matcher("OverWorld", "Nether"), field.name())
+            }
+            CsvColumn::Extra(dimension_id, field) => format!("{}_{}", dimension_id, field.name()),
+        }
+    }
+
+    fn value(&self, npt: &NetherPortalText) -> String {
+        match self {
+            CsvColumn::Id => npt.get_id().to_string(),
+            CsvColumn::Username => npt.username_ref().clone(),
+            CsvColumn::Side(realm, field) => npt.realm_ref(realm).field_string(*field),
+            // Empty when the portal has no side there
+            CsvColumn::Extra(dimension_id, field) => npt
+                .sides_ref()
+                .get(dimension_id)
+                .map(|pt| pt.field_string(*field))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+pub fn export_csv(
+    npts: &[NetherPortalText],
+    extra: &[Dimension],
+    path: &str,
+) -> Result<(), BorkError> {
+    //! Write every NetherPortalText as a row of a csv file; (extra) dimensions get columns too
+    let columns = CsvColumn::all(extra);
+    let mut writer = csv::Writer::from_path(path)?;
+
+    writer.write_record(columns.iter().map(|column| column.header()))?;
+    for npt in npts {
+        writer.write_record(columns.iter().map(|column| column.value(npt)))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn add_nether_portal(npt: NetherPortalText) -> Result<ureq::Response, BorkError> {
+    let url = &Urls::default(Routes::AddNetherPortalText);
+    let response = ureq::post(url).send_json(npt)?;
+
+    Ok(response)
+}
+
+pub fn submit_new_portals(
+    request: &mut Request<(), BorkError>,
+    npts: Vec<NetherPortalText>,
+    runtime: &Runtime,
+    ctx: &Context,
+) {
+    //! Add every NetherPortalText to the server, one after another, in a single background Request
+    //!
+    //! Every portal that could not be added is logged; the Request keeps the first failure
+    let name = format!("Add |{}| portal(s)", npts.len());
+    request.spawn(runtime, ctx, &name, move |responder| {
+        let mut failure = None;
+        for (index, npt) in npts.iter().enumerate() {
+            responder.check_cancelled()?;
+            // Adding is not idempotent, a retry could add it twice
+            let subfn = || -> Result<(), BorkError> {
+                add_nether_portal(npt.clone())?.status_check()?;
+                Ok(())
+            };
+            if let Err(err) = call_unless(Service::Text, false, || responder.is_cancelled(), subfn)
+            {
+                tracing::warn!("Could not add |{}|: {}", npt.ow_true_name(), err);
+                failure.get_or_insert(err);
+            }
+            responder.progress((index + 1) as f32 / npts.len() as f32);
+        }
+
+        match failure {
+            None => Ok(()),
+            Some(err) => Err(err),
+        }
+    });
+}
+
+pub fn show_submit_status(request: &mut Request<(), BorkError>, ui: &mut Ui) {
+    //! How far the submit is, then what happened
+    request.poll();
+    request_status(ui, request);
+    if request.ready().is_some() {
+        ui.label("Everything was added. Use \"Reload From DB\" to see it");
+    }
+}
+
+// A checked row of the csv file; only rows without problems can be submitted
+pub struct CsvRow {
+    pub line: usize,
+    pub npt: NetherPortalText,
+    pub problems: Vec<String>,
+}
+
+pub struct CsvImport {
+    path: String,
+    export_path: String,
+    headers: Vec<String>,
+    records: Vec<Vec<String>>,
+    mapping: Vec<(CsvColumn, ModalMachine)>,
+    rows: Vec<CsvRow>,
+    submit_request: Request<(), BorkError>,
+    pub display: GenericWindow,
+}
+
+impl Default for CsvImport {
+    fn default() -> Self {
+        Self {
+            path: world_path("portals.csv"),
+            export_path: world_path("portals.csv"),
+            headers: Vec::new(),
+            records: Vec::new(),
+            mapping: Vec::new(),
+            rows: Vec::new(),
+            submit_request: Request::default(),
+            display: GenericWindow::new("CSV Import/Export"),
+        }
+    }
+}
+
+impl CsvImport {
+    pub fn load(&mut self, extra: &[Dimension]) -> Result<(), BorkError> {
+        //! Read the csv file at (path) and guess which of its columns go where
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_path(&self.path)?;
+
+        self.headers = reader.headers()?.iter().map(|s| s.to_string()).collect();
+        self.records = Vec::new();
+        for record in reader.records() {
+            self.records
+                .push(record?.iter().map(|s| s.trim().to_string()).collect());
+        }
+
+        // Ids are made by the server, so they are never imported
+        let mut options = vec![NO_COLUMN.to_string()];
+        options.extend(self.headers.iter().cloned());
+        self.mapping = CsvColumn::all(extra)
+            .into_iter()
+            .filter(|column| !matches!(column, CsvColumn::Id))
+            .map(|column| {
+                let guess = self
+                    .headers
+                    .iter()
+                    .find(|header| header.eq_ignore_ascii_case(&column.header()))
+                    .cloned()
+                    .unwrap_or(NO_COLUMN.to_string());
+                let mm = ModalMachine::new(guess, options.clone(), column.header());
+                (column, mm)
+            })
+            .collect();
+        self.rows = Vec::new();
+
+        Ok(())
+    }
+
+    fn column_value<'a>(
+        &self,
+        record: &'a [String],
+        mm: &ModalMachine,
+    ) -> Result<Option<&'a String>, String> {
+        //! Ok(None) == the column is not mapped; Err() == it is, but the row is too short to have it
+        let selected = mm.get_selected_option();
+        let index = match self.headers.iter().position(|header| *header == selected) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        match record.get(index) {
+            Some(value) => Ok(Some(value)),
+            None => Err(format!(
+                "Column |{}| is missing, the row only has {} value(s)",
+                selected,
+                record.len()
+            )),
+        }
+    }
+
+    pub fn check_rows(&mut self, nether_portals: &NetherPortals) {
+        //! Turn every record into a NetherPortalText and list everything wrong with it
+        let mut seen: HashSet<(&'static str, String)> = HashSet::new();
+        let mut rows = Vec::new();
+
+        for (index, record) in self.records.iter().enumerate() {
+            let mut problems = Vec::new();
+            let mut overworld = PortalText::default();
+            let mut nether = PortalText::default();
+            let mut username = String::default();
+            let mut sides: BTreeMap<String, PortalText> = BTreeMap::new();
+
+            for (column, mm) in self.mapping.iter() {
+                // The reader is flexible, so a short row is read; what it is missing is a problem
+                let value = match self.column_value(record, mm) {
+                    Ok(Some(value)) => value.clone(),
+                    Ok(None) => continue,
+                    Err(problem) => {
+                        problems.push(problem);
+                        continue;
+                    }
+                };
+                let (pt, side_name, field) = match column {
+                    CsvColumn::Side(realm, field) => (
+                        realm.matcher(&mut overworld, &mut nether),
+                        realm.name(),
+                        field,
+                    ),
+                    // A side is only made when its dimension's columns have something in them
+                    CsvColumn::Extra(dimension_id, field) if !value.is_empty() => (
+                        sides.entry(dimension_id.clone()).or_default(),
+                        dimension_id.as_str(),
+                        field,
+                    ),
+                    CsvColumn::Extra(..) | CsvColumn::Id => continue,
+                    CsvColumn::Username => {
+                        username = value;
+                        continue;
+                    }
+                };
+                match field.is_cord() {
+                    true => match value.parse::<i32>() {
+                        Ok(cord) => pt.set_field(*field, FieldValue::Cord(cord)),
+                        Err(_) => problems.push(format!(
+                            "{} {}: |{}| is not a number",
+                            side_name,
+                            field.name(),
+                            value
+                        )),
+                    },
+                    false => pt.set_field(*field, FieldValue::Text(value)),
+                }
+            }
+
+            for (realm, pt) in [(Realm::Overworld, &overworld), (Realm::Nether, &nether)] {
+                for (field, problem) in pt.validate(&realm.dimension()) {
+                    problems.push(format!("{} {}: {}", realm.name(), field.name(), problem));
+                }
+
+                // Duplicate true_names would collapse into each other
+                let true_name = pt.true_name_ref().clone();
+                if nether_portals.has_true_name(&realm, &true_name) {
+                    problems.push(format!("{} |{}| already exists", realm.name(), true_name));
+                }
+                if !seen.insert((realm.name(), true_name.clone())) {
+                    problems.push(format!(
+                        "{} |{}| is in this file more than once",
+                        realm.name(),
+                        true_name
+                    ));
+                }
+            }
+
+            for (dimension_id, pt) in sides.iter() {
+                let dimension = nether_portals
+                    .dimensions_ref()
+                    .get(dimension_id)
+                    .unwrap_or_else(Dimension::overworld);
+                for (field, problem) in pt.validate(&dimension) {
+                    problems.push(format!("{} {}: {}", dimension.name, field.name(), problem));
+                }
+            }
+
+            rows.push(CsvRow {
+                // +2 == the header line & counting from one
+                line: index + 2,
+                npt: NetherPortalText::build_from(0, overworld, nether)
+                    .with_username(username)
+                    .with_sides(sides),
+                problems,
+            });
+        }
+
+        self.rows = rows;
+    }
+
+    pub fn submit(&mut self, runtime: &Runtime, ctx: &Context) -> Result<(), BorkError> {
+        //! Add every row without problems to the server
+        let npts: Vec<NetherPortalText> = self
+            .rows
+            .iter()
+            .filter(|row| row.problems.is_empty())
+            .map(|row| row.npt.clone())
+            .collect();
+        if npts.is_empty() {
+            return Err(BorkError::validation(
+                "There are no rows without problems to submit",
+            ));
+        }
+
+        submit_new_portals(&mut self.submit_request, npts, runtime, ctx);
+
+        Ok(())
+    }
+
+    fn show_mapping(&mut self, ui: &mut Ui) {
+        Grid::new("csv column mapping").show(ui, |ui| {
+            self.mapping
+                .iter_mut()
+                .enumerate()
+                .for_each(|(index, (_, mm))| {
+                    mm.modal_machine(index as i64, ui);
+                    if index % 2 == 1 {
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    fn show_rows(&self, ui: &mut Ui) {
+        let valid = self
+            .rows
+            .iter()
+            .filter(|row| row.problems.is_empty())
+            .count();
+        ui.label(format!(
+            "{} row(s) ready, {} row(s) with problems",
+            valid,
+            self.rows.len() - valid
+        ));
+
+        ScrollArea::vertical()
+            .id_source("csv rows")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                Grid::new("csv rows").striped(true).show(ui, |ui| {
+                    ui.strong("Line");
+                    ui.strong("Overworld");
+                    ui.strong("Nether");
+                    ui.strong("Problems");
+                    ui.end_row();
+
+                    self.rows.iter().for_each(|row| {
+                        ui.label(row.line.to_string());
+                        ui.label(row.npt.ow_true_name());
+                        ui.label(row.npt.nether_true_name());
+                        match row.problems.is_empty() {
+                            true => ui.label("Ready"),
+                            false => ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                row.problems.join("\n"),
+                            ),
+                        };
+                        ui.end_row();
+                    });
+                });
+            });
+    }
+
+    fn show(
+        &mut self,
+        nether_portals: &NetherPortals,
+        runtime: &Runtime,
+        ui: &mut Ui,
+    ) -> Result<(), BorkError> {
+        let mut result = Ok(());
+
+        // Export
+        ui.strong("Export");
+        ui.horizontal(|ui| {
+            ui.label("Save to:");
+            ui.text_edit_singleline(&mut self.export_path);
+            if ui.button("Export CSV").clicked() {
+                result = export_csv(
+                    &nether_portals.to_npts(),
+                    &nether_portals.dimensions_ref().extra(),
+                    &self.export_path,
+                );
+            }
+        });
+        ui.separator();
+
+        // Import
+        ui.strong("Import");
+        ui.horizontal(|ui| {
+            ui.label("Load from:");
+            ui.text_edit_singleline(&mut self.path);
+            if ui.button("Load CSV").clicked() {
+                result = self.load(&nether_portals.dimensions_ref().extra());
+            }
+        });
+        if self.headers.is_empty() {
+            return result;
+        }
+
+        self.show_mapping(ui);
+        ui.horizontal(|ui| {
+            if ui.button("Check Rows").clicked() {
+                self.check_rows(nether_portals);
+            }
+            if !self.rows.is_empty() && ui.button("Submit Ready Rows").clicked() {
+                result = self.submit(runtime, ui.ctx());
+            }
+            show_submit_status(&mut self.submit_request, ui);
+        });
+        if !self.rows.is_empty() {
+            self.show_rows(ui);
+        }
+
+        result
+    }
+}
+
+pub fn csv_window(
+    nether_portals: &mut NetherPortals,
+    runtime: &Runtime,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! Shows the "CSV Import/Export" window (if its open)
+    let mut csv_import = std::mem::take(nether_portals.csv_import_mut());
+    let mut display = std::mem::take(&mut csv_import.display);
+
+    let mut result = Ok(());
+    display.show_with(ctx, |ui| {
+        result = csv_import.show(nether_portals, runtime, ui);
+    });
+
+    csv_import.display = display;
+    *nether_portals.csv_import_mut() = csv_import;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checked(csv: &str, name: &str) -> Vec<CsvRow> {
+        let dir = std::env::temp_dir().join(format!("borkcraft-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("portals.csv");
+        std::fs::write(&path, csv).unwrap();
+
+        let mut import = CsvImport {
+            path: path.to_string_lossy().to_string(),
+            ..CsvImport::default()
+        };
+        import.load(&[]).unwrap();
+        import.check_rows(&NetherPortals::default());
+
+        std::fs::remove_dir_all(dir).ok();
+        import.rows
+    }
+
+    const HEADER: &str = "OverWorld_True_Name,OverWorld_Xcord,OverWorld_Ycord,OverWorld_Zcord,\
+OverWorld_Owner,Nether_True_Name,Nether_Xcord,Nether_Ycord,Nether_Zcord,Nether_Owner\n";
+
+    #[test]
+    fn full_rows_have_no_problems() {
+        let csv = format!(
+            "{}Home,800,70,-1600,Steve,Home Nether,100,70,-200,Steve\n",
+            HEADER
+        );
+        let rows = checked(&csv, "csv-full");
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].problems, Vec::<String>::new());
+        assert_eq!(rows[0].npt.ow_true_name(), "Home");
+    }
+
+    #[test]
+    fn short_rows_report_every_missing_column() {
+        let csv = format!("{}Home,800,70,-1600,Steve,Home Nether,100\n", HEADER);
+        let rows = checked(&csv, "csv-short");
+
+        assert_eq!(rows.len(), 1);
+        let missing: Vec<&String> = rows[0]
+            .problems
+            .iter()
+            .filter(|problem| problem.contains("is missing"))
+            .collect();
+        assert_eq!(missing.len(), 3);
+        assert!(missing[0].contains("Nether_Ycord"));
+        assert!(missing[0].contains("7 value(s)"));
+    }
+
+    #[test]
+    fn cords_that_are_not_numbers_are_problems() {
+        let csv = format!(
+            "{}Home,east,70,-1600,Steve,Home Nether,100,70,-200,Steve\n",
+            HEADER
+        );
+        let rows = checked(&csv, "csv-number");
+
+        assert!(rows[0]
+            .problems
+            .iter()
+            .any(|p| p.contains("|east| is not a number")));
+    }
+}
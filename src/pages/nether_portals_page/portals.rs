@@ -1,24 +1,38 @@
-use std::{
-    collections::BTreeMap,
-    future::Future,
-    sync::mpsc::{Receiver, Sender},
-};
+use std::{collections::BTreeMap, future::Future, sync::mpsc::Receiver};
 
 use serde_derive::{Deserialize, Serialize};
 
 //windows::client_windows::Loglet,
+use super::{
+    audit::{audit_windows, AuditLog, RevisionHistory},
+    backup::BackupRestore,
+    history::EditHistory,
+    lint::LintReport,
+    log_import::LogImport,
+    map::MapView,
+    nearest::NearestTool,
+    paste_location::PasteLocation,
+    portal_csv::CsvImport,
+    sides::SideForms,
+    tags::Tags,
+    waypoints::WaypointTransfer,
+    world_save::WorldImport,
+};
 use crate::{
+    dimension::{Dimension, Dimensions},
     eframe_tools::ModalMachine,
-    images::Imager,
-    thread_tools::{Communicator, SPromise},
-    MagicError, Realm,
+    images::{Imager, ImagerList},
+    thread_tools::{waking_channel, Communicator, Request, SPromise, WakingSender},
+    windows::client_windows::GenericWindow,
+    BorkError, Realm,
 };
+use eframe::egui::Context;
 use std::mem;
 
 // Traits
 //use crate::New;
 
-#[derive(Deserialize, Serialize, Default, Debug)]
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
 pub struct PortalText {
     #[serde(rename = "Xcord")]
     xcord: i32,
@@ -34,42 +48,232 @@ pub struct PortalText {
     notes: String,
     #[serde(rename = "True_Name")]
     true_name: String,
+    // Comma separated: "base, farm"; older json has no tags
+    #[serde(rename = "Tags", default, skip_serializing_if = "String::is_empty")]
+    tags: String,
+}
+
+// The world border; nothing can be placed outside of it
+pub const WORLD_BORDER: i32 = 29_999_984;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PortalField {
+    Xcord,
+    Ycord,
+    Zcord,
+    Locale,
+    Owner,
+    Notes,
+    TrueName,
+    Tags,
+}
+
+impl PortalField {
+    pub const ALL: [PortalField; 8] = [
+        PortalField::TrueName,
+        PortalField::Xcord,
+        PortalField::Ycord,
+        PortalField::Zcord,
+        PortalField::Locale,
+        PortalField::Owner,
+        PortalField::Notes,
+        PortalField::Tags,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PortalField::Xcord => "Xcord",
+            PortalField::Ycord => "Ycord",
+            PortalField::Zcord => "Zcord",
+            PortalField::Locale => "Locale",
+            PortalField::Owner => "Owner",
+            PortalField::Notes => "Notes",
+            PortalField::TrueName => "True_Name",
+            PortalField::Tags => "Tags",
+        }
+    }
+    pub fn is_cord(&self) -> bool {
+        matches!(
+            self,
+            PortalField::Xcord | PortalField::Ycord | PortalField::Zcord
+        )
+    }
+}
+
+// The value of a single PortalField
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue {
+    Cord(i32),
+    Text(String),
+}
+
+impl std::fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FieldValue::Cord(cord) => write!(f, "{}", cord),
+            FieldValue::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+pub struct FieldChange {
+    pub field: PortalField,
+    pub old: String,
+    pub new: String,
 }
+
 impl PortalText {
-    pub fn to_btree(&self) -> BTreeMap<String, String> {
-        // Serialize pt(PortalText) to json
-        let pt_as_json = serde_json::to_value(&self).unwrap();
+    pub fn build(true_name: String, cords: (i32, i32, i32)) -> Self {
+        //! A PortalText that only knows its name and where it is
+        let (xcord, ycord, zcord) = cords;
+        Self {
+            xcord,
+            ycord,
+            zcord,
+            true_name,
+            ..Self::default()
+        }
+    }
+
+    // Getters
+    pub fn true_name_ref(&self) -> &String {
+        &self.true_name
+    }
+    pub fn cords(&self) -> (i32, i32, i32) {
+        (self.xcord, self.ycord, self.zcord)
+    }
+    pub fn owner_ref(&self) -> &String {
+        &self.owner
+    }
+    pub fn tags(&self) -> Vec<String> {
+        self.tags
+            .split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+    pub fn with_tag(&self, tag: &str) -> String {
+        //! The tags field with (tag) added to the end, unless it already has it
+        let mut tags = self.tags();
+        if !self.has_tag(tag) {
+            tags.push(tag.trim().to_string());
+        }
+        tags.join(", ")
+    }
+    pub fn field_string(&self, field: PortalField) -> String {
+        //! Displayable version of a single field
+        match field {
+            PortalField::Xcord => self.xcord.to_string(),
+            PortalField::Ycord => self.ycord.to_string(),
+            PortalField::Zcord => self.zcord.to_string(),
+            PortalField::Locale => self.locale.clone(),
+            PortalField::Owner => self.owner.clone(),
+            PortalField::Notes => self.notes.clone(),
+            PortalField::TrueName => self.true_name.clone(),
+            PortalField::Tags => self.tags.clone(),
+        }
+    }
+    pub fn field_value(&self, field: PortalField) -> FieldValue {
+        match field {
+            PortalField::Xcord => FieldValue::Cord(self.xcord),
+            PortalField::Ycord => FieldValue::Cord(self.ycord),
+            PortalField::Zcord => FieldValue::Cord(self.zcord),
+            _ => FieldValue::Text(self.field_string(field)),
+        }
+    }
+
+    // Setters
+    pub fn set_field(&mut self, field: PortalField, value: FieldValue) {
+        //! A value of the wrong kind (Text for a cord) is ignored
+        match value {
+            FieldValue::Cord(new_cord) => {
+                if let Some(cord) = self.cord_mut(field) {
+                    *cord = new_cord;
+                }
+            }
+            FieldValue::Text(new_text) => {
+                if let Some(text) = self.text_mut(field) {
+                    *text = new_text;
+                }
+            }
+        }
+    }
 
-        // Create a btree for storage
-        let mut new_btree: BTreeMap<String, String> = BTreeMap::new();
+    // Mutable Getters (For the edit form)
+    pub fn cord_mut(&mut self, field: PortalField) -> Option<&mut i32> {
+        match field {
+            PortalField::Xcord => Some(&mut self.xcord),
+            PortalField::Ycord => Some(&mut self.ycord),
+            PortalField::Zcord => Some(&mut self.zcord),
+            _ => None,
+        }
+    }
+    pub fn text_mut(&mut self, field: PortalField) -> Option<&mut String> {
+        match field {
+            PortalField::Locale => Some(&mut self.locale),
+            PortalField::Owner => Some(&mut self.owner),
+            PortalField::Notes => Some(&mut self.notes),
+            PortalField::TrueName => Some(&mut self.true_name),
+            PortalField::Tags => Some(&mut self.tags),
+            _ => None,
+        }
+    }
 
-        // Iter through json as an object, Convert each field to a string
-        pt_as_json
-            .as_object()
-            .unwrap()
+    pub fn diff(&self, edited: &PortalText) -> Vec<FieldChange> {
+        //! Every field that is different between (self) and (edited)
+        PortalField::ALL
             .iter()
-            .for_each(|(key, value)| {
-                new_btree.insert(key.clone(), value.to_string());
-            });
+            .filter_map(|field| {
+                let old = self.field_string(*field);
+                let new = edited.field_string(*field);
+                (old != new).then_some(FieldChange {
+                    field: *field,
+                    old,
+                    new,
+                })
+            })
+            .collect()
+    }
+
+    pub fn validate(&self, dimension: &Dimension) -> BTreeMap<PortalField, String> {
+        //! Check each field. Returns a message for every field that is not valid
+        let mut problems = BTreeMap::new();
+
+        if self.true_name.trim().is_empty() {
+            problems.insert(PortalField::TrueName, "A name is required".to_string());
+        } else if self.true_name.trim() != self.true_name {
+            problems.insert(
+                PortalField::TrueName,
+                "Remove the spaces at the start/end".to_string(),
+            );
+        }
 
-        new_btree
-    }
-    pub fn from_btree(btree: &BTreeMap<String, String>) -> Result<PortalText, MagicError> {
-        let portal_text = PortalText {
-            xcord: btree["Xcord"].parse::<i32>()?,
-            ycord: btree["Ycord"].parse::<i32>()?,
-            zcord: btree["Zcord"].parse::<i32>()?,
-            locale: btree["Locale"].clone().replace('"', ""),
-            owner: btree["Owner"].clone().replace('"', ""),
-            notes: btree["Notes"].clone().replace('"', ""),
-            true_name: btree["True_Name"].clone().replace('"', ""),
-        };
+        let (min_y, max_y) = dimension.build_limits();
+        if self.ycord < min_y || self.ycord > max_y {
+            problems.insert(
+                PortalField::Ycord,
+                format!("Must be between {} and {}", min_y, max_y),
+            );
+        }
+
+        for field in [PortalField::Xcord, PortalField::Zcord] {
+            let cord = match field {
+                PortalField::Xcord => self.xcord,
+                _ => self.zcord,
+            };
+            if cord.abs() > WORLD_BORDER {
+                problems.insert(field, "Outside of the world border".to_string());
+            }
+        }
 
-        Ok(portal_text)
+        problems
     }
 }
 
-#[derive(Deserialize, Serialize, Default)]
+#[derive(Deserialize, Serialize, Default, Clone)]
 pub struct NetherPortalText {
     #[serde(rename = "Id")]
     id: i32,
@@ -79,6 +283,10 @@ pub struct NetherPortalText {
     nether: PortalText,
     #[serde(rename = "Username")]
     username: String,
+    // Sides in any other dimension (The End, modded ones), keyed by dimension id
+    // Older two sided json has no "Sides", so it loads with none
+    #[serde(rename = "Sides", default, skip_serializing_if = "BTreeMap::is_empty")]
+    sides: BTreeMap<String, PortalText>,
 }
 
 impl NetherPortalText {
@@ -86,18 +294,46 @@ impl NetherPortalText {
         //! Return new String from nether.true_name's field
         self.nether.true_name.clone()
     }
+    pub fn get_id(&self) -> i32 {
+        self.id
+    }
     pub fn ow_true_name(&self) -> String {
         //! Return new String from overworld.true_name's field
         self.overworld.true_name.clone()
     }
+    pub fn username_ref(&self) -> &String {
+        &self.username
+    }
+    pub fn realm_ref(&self, realm: &Realm) -> &PortalText {
+        realm.matcher(&self.overworld, &self.nether)
+    }
     pub fn build_from(id: i32, overworld: PortalText, nether: PortalText) -> Self {
         Self {
             id,
             overworld,
             nether,
             username: String::default(),
+            sides: BTreeMap::new(),
         }
     }
+    pub fn with_sides(mut self, sides: BTreeMap<String, PortalText>) -> Self {
+        self.sides = sides;
+        self
+    }
+    pub fn sides_ref(&self) -> &BTreeMap<String, PortalText> {
+        &self.sides
+    }
+    pub fn side_ref(&self, dimension: &Dimension) -> Option<&PortalText> {
+        //! Any side, the overworld & nether included
+        match Realm::from_dimension(dimension) {
+            Some(realm) => Some(self.realm_ref(&realm)),
+            None => self.sides.get(&dimension.id),
+        }
+    }
+    pub fn with_username(mut self, username: String) -> Self {
+        self.username = username;
+        self
+    }
     //pub fn convert_np(np: NetherPortal) -> NetherPortalText{
     //    let npt = NetherPortalText {
     //        id: 0,
@@ -107,13 +343,13 @@ impl NetherPortalText {
 }
 
 type F = Box<dyn Future<Output = ()> + Unpin>;
-type PortalTextBTree = BTreeMap<String, String>;
 // Images should be stored by keys with their name
 pub struct NetherPortal {
     original_id: i32,
+    username: String,
     portal_text: SPromise<PortalText, F>,
-    as_btree: PortalTextBTree,
-    images: BTreeMap<String, SPromise<Imager, F>>,
+    edit: PortalText,
+    images: ImagerList,
     image_position: String,
     image_list_request: SPromise<Option<String>, Box<dyn Future<Output = ()> + Unpin>>,
 }
@@ -133,11 +369,11 @@ impl NetherPortal {
         &mut self.image_position
     }
 
-    pub fn images_ref(&self) -> &BTreeMap<String, SPromise<Imager, F>> {
+    pub fn images_ref(&self) -> &ImagerList {
         &self.images
     }
 
-    pub fn images_mut(&mut self) -> &mut BTreeMap<String, SPromise<Imager, F>> {
+    pub fn images_mut(&mut self) -> &mut ImagerList {
         &mut self.images
     }
 
@@ -154,8 +390,8 @@ impl NetherPortal {
     pub fn init_img_pos(&mut self) -> Option<()> {
         //! initialize the image position if its empty. None== there is no key;
         if self.image_position == String::default() {
-            for (key, imager) in self.images.iter() {
-                imager.spromise_ref()?.ready()?;
+            for (key, slot) in self.images.iter() {
+                slot.request.ready()?;
                 self.image_position = key.clone();
                 return Some(());
             }
@@ -165,46 +401,79 @@ impl NetherPortal {
 }
 
 impl NetherPortal {
+    pub fn new(original_id: i32, username: String, pt: PortalText) -> Self {
+        Self {
+            original_id,
+            username,
+            edit: pt.clone(),
+            portal_text: SPromise::make_no_promise(pt),
+            images: BTreeMap::new(),
+            image_position: String::default(),
+            image_list_request: SPromise::make_no_promise(None),
+        }
+    }
     pub fn get_id(&self) -> i32 {
         self.original_id
     }
+    pub fn username_ref(&self) -> &String {
+        &self.username
+    }
     pub fn add_portal_text(&mut self, pt: PortalText) {
+        //! Replace the portal text. Edits that have not been made yet follow along, real edits are kept
+        let is_dirty = self.is_dirty();
         self.portal_text = SPromise::make_no_promise(pt);
+        if !is_dirty {
+            self.reset_edit();
+        }
     }
-    pub fn set_pt(&mut self) -> Result<(), MagicError> {
-        //! Set portal_text with converted NetherPortal.as_btree values.
-        let pt = PortalText::from_btree(&self.as_btree)?;
+    pub fn set_pt(&mut self, pt: PortalText) {
+        //! Set portal_text to what the server saved; the edit form keeps anything typed since
         self.portal_text.set_value(pt);
-
-        Ok(())
+    }
+    pub fn pt_ref(&self) -> &PortalText {
+        self.portal_text.ref_value()
+    }
+    pub fn revert_field(&mut self, field: PortalField, value: FieldValue) {
+        //! Put a value back into BOTH the saved PortalText and the edit form
+        self.portal_text.mut_value().set_field(field, value.clone());
+        self.edit.set_field(field, value);
     }
 
-    // BTree Stuff
-    pub fn set_as_btree(&mut self) {
-        //! Create a Btree from PortalText and set it to (as_btree)'s value
-        self.as_btree = self.portal_text.ref_value().to_btree();
+    // Edit Form Stuff
+    pub fn reset_edit(&mut self) {
+        //! Throw away any edits; the form goes back to the saved PortalText
+        self.edit = self.portal_text.ref_value().clone();
+    }
+    pub fn edit_ref(&self) -> &PortalText {
+        &self.edit
     }
-    pub fn btree_ref(&self) -> &PortalTextBTree {
-        &self.as_btree
+    pub fn edit_mut(&mut self) -> &mut PortalText {
+        &mut self.edit
     }
-    pub fn btree_mut(&mut self) -> &mut PortalTextBTree {
-        &mut self.as_btree
+    pub fn changes(&self) -> Vec<FieldChange> {
+        self.portal_text.ref_value().diff(&self.edit)
     }
 
     // Checkers
-    pub fn is_empty(&self) -> bool {
-        self.as_btree.is_empty()
+    pub fn is_dirty(&self) -> bool {
+        self.portal_text.ref_value() != &self.edit
     }
 }
 
+// Pair ids in the order they are stepped through
 #[derive(Default)]
 struct Keys {
-    keys: Vec<String>,
+    keys: Vec<i32>,
     index: usize,
 }
 impl Keys {
-    pub fn set_keys(&mut self, keys: Vec<String>) {
+    pub fn set_keys(&mut self, keys: Vec<i32>) {
+        //! The same pair stays selected when pairs are added or removed around it
+        let current = self.current();
         self.keys = keys;
+        if let Some(id) = current {
+            self.select(id);
+        }
     }
     pub fn set_pos(&mut self, index: usize) {
         self.index = index;
@@ -212,23 +481,127 @@ impl Keys {
     pub fn get_index(&self) -> usize {
         self.index
     }
-    pub fn current(&self) -> Option<String> {
+    pub fn current(&self) -> Option<i32> {
         // Keys[index] == Gives position inside Vec
-        self.keys.get(self.index).cloned()
+        self.keys.get(self.index).copied()
     }
-    pub fn current_mut(&mut self) -> Option<&mut String> {
-        self.keys.get_mut(self.index)
+    pub fn select(&mut self, id: i32) -> Option<()> {
+        self.index = self.keys.iter().position(|key| *key == id)?;
+        Some(())
     }
     pub fn len(&self) -> usize {
         self.keys.len()
     }
 }
 
-pub type NetherPortalBTree = BTreeMap<String, NetherPortal>;
+// Both sides of one NetherPortalText. Its id never changes, true_names can
+pub struct PortalPair {
+    overworld: NetherPortal,
+    nether: NetherPortal,
+}
+
+impl PortalPair {
+    pub fn get_id(&self) -> i32 {
+        self.overworld.get_id()
+    }
+    pub fn username_ref(&self) -> &String {
+        self.overworld.username_ref()
+    }
+    pub fn realm_ref(&self, realm: &Realm) -> &NetherPortal {
+        realm.matcher(&self.overworld, &self.nether)
+    }
+    pub fn realm_mut(&mut self, realm: &Realm) -> &mut NetherPortal {
+        realm.matcher(&mut self.overworld, &mut self.nether)
+    }
+    pub fn true_name_ref(&self, realm: &Realm) -> &String {
+        //! The saved true_name, not the one in the edit form
+        self.realm_ref(realm).pt_ref().true_name_ref()
+    }
+    pub fn label(&self) -> String {
+        format!(
+            "{} & {}",
+            self.true_name_ref(&Realm::Overworld),
+            self.true_name_ref(&Realm::Nether)
+        )
+    }
+    pub fn is_dirty(&self) -> bool {
+        self.overworld.is_dirty() || self.nether.is_dirty()
+    }
+}
+
+pub type PortalPairs = BTreeMap<i32, PortalPair>;
+// true_name -> id of every pair with a side of that name (more than one == duplicate names)
+pub type NameIndex = BTreeMap<String, Vec<i32>>;
+
+// A side of a pair in a dimension other than the overworld & nether
+#[derive(Clone, Default)]
+pub struct ExtraSide {
+    // None == it was added in this client and the server does not have it yet
+    saved: Option<PortalText>,
+    edit: PortalText,
+}
+
+impl ExtraSide {
+    pub fn from_saved(pt: PortalText) -> Self {
+        Self {
+            saved: Some(pt.clone()),
+            edit: pt,
+        }
+    }
+    pub fn new_side(true_name: String) -> Self {
+        Self {
+            saved: None,
+            edit: PortalText::build(true_name, (0, 64, 0)),
+        }
+    }
+    pub fn is_dirty(&self) -> bool {
+        self.saved.as_ref() != Some(&self.edit)
+    }
+    pub fn add_saved(&mut self, pt: PortalText) {
+        //! Same as NetherPortal::add_portal_text; real edits are kept
+        if !self.is_dirty() {
+            self.edit = pt.clone();
+        }
+        self.saved = Some(pt);
+    }
+    pub fn set_saved(&mut self, pt: PortalText) {
+        //! What the server saved; the edit keeps anything typed since
+        self.saved = Some(pt);
+    }
+    pub fn revert_field(&mut self, field: PortalField, value: FieldValue) {
+        //! Same as NetherPortal::revert_field
+        if let Some(saved) = &mut self.saved {
+            saved.set_field(field, value.clone());
+        }
+        self.edit.set_field(field, value);
+    }
+    pub fn reset(&mut self) -> bool {
+        //! Throw the edit away. false == there is nothing to go back to, remove the side
+        match &self.saved {
+            Some(saved) => {
+                self.edit = saved.clone();
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn changes(&self) -> Vec<FieldChange> {
+        self.saved.clone().unwrap_or_default().diff(&self.edit)
+    }
+    pub fn saved_ref(&self) -> Option<&PortalText> {
+        self.saved.as_ref()
+    }
+    pub fn edit_ref(&self) -> &PortalText {
+        &self.edit
+    }
+    pub fn edit_mut(&mut self) -> &mut PortalText {
+        &mut self.edit
+    }
+}
 
 pub struct ModalMachineX {
     mm: ModalMachine,
-    options_x: Vec<(String, String)>,
+    options_x: Vec<i32>,
 }
 
 // A wrapper for ModalMachine (ModalMachineX)
@@ -239,17 +612,13 @@ impl ModalMachineX {
             options_x: Vec::default(),
         }
     }
-    pub fn get_selected_option(&self) -> (String, String) {
-        // Each options is inlayed with a string number as the first character
-        let pos: usize = self
-            .mm
-            .get_selected_option()
-            .remove(0)
-            .to_string()
-            .parse()
-            .unwrap();
+    pub fn get_selected_option(&self) -> Option<i32> {
+        //! The pair id of the selected option. Each option starts with its index: "3: name & name"
+        let selected = self.mm.get_selected_option();
+        let (pos, _) = selected.split_once(':')?;
+        let pos: usize = pos.parse().ok()?;
 
-        self.options_x_ref().get(pos).unwrap().clone()
+        self.options_x_ref().get(pos).copied()
     }
 
     pub fn use_event(&mut self) -> Option<()> {
@@ -259,23 +628,59 @@ impl ModalMachineX {
     pub fn modal_machine(&mut self, id: i64, ui: &mut eframe::egui::Ui) {
         self.mm.modal_machine(id, ui);
     }
-    fn options_x_ref(&self) -> &Vec<(String, String)> {
+    fn options_x_ref(&self) -> &Vec<i32> {
         &self.options_x
     }
-    fn options_x_mut(&mut self) -> &mut Vec<(String, String)> {
+    fn options_x_mut(&mut self) -> &mut Vec<i32> {
         &mut self.options_x
     }
-    pub fn set_options(&mut self, options_x: Vec<(String, String)>) {
+    pub fn set_options(&mut self, options_x: Vec<i32>) {
         *self.options_x_mut() = options_x;
     }
 }
 
-// The Keys of NetherPortals BTreeMap members should be the PortalText.true_name
+// What the user asked to be saved
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveScope {
+    Overworld,
+    Nether,
+    Pair,
+    All,
+}
+
+impl SaveScope {
+    pub fn realms(&self) -> Vec<Realm> {
+        //! The realm(s) whose edits are saved
+        match self {
+            SaveScope::Overworld => vec![Realm::Overworld],
+            SaveScope::Nether => vec![Realm::Nether],
+            SaveScope::Pair | SaveScope::All => vec![Realm::Overworld, Realm::Nether],
+        }
+    }
+    pub fn includes_sides(&self) -> bool {
+        //! Extra sides (The End, modded dimensions) are saved with the whole pair
+        matches!(self, SaveScope::Pair | SaveScope::All)
+    }
+}
+
+// A save the server took; what it covered moves from the edit forms into the saved text
+pub struct SavedPair {
+    pub npt: NetherPortalText,
+    // None == there is nothing to move (a revert is already in place)
+    pub scope: Option<SaveScope>,
+}
+
+// Pairs are keyed by NetherPortalText.id; true_names are only looked up through the name indices
 pub struct NetherPortals {
-    overworld: BTreeMap<String, NetherPortal>,
-    ow_position: Keys,
-    nether: BTreeMap<String, NetherPortal>,
-    nether_position: Keys,
+    pairs: PortalPairs,
+    position: Keys,
+    ow_names: NameIndex,
+    nether_names: NameIndex,
+    // Extra sides of each pair, keyed by the pair's original_id & then by dimension id
+    sides: BTreeMap<i32, BTreeMap<String, ExtraSide>>,
+    dimensions: Dimensions,
+    side_forms: SideForms,
+    tags: Tags,
 
     // Channels
     nether_portal_text_comm: Communicator<NetherPortalText>,
@@ -283,7 +688,24 @@ pub struct NetherPortals {
 
     // Misc
     mutate: bool,
-    text_request: SPromise<Option<String>, Box<dyn Future<Output = ()> + Unpin>>,
+    history: EditHistory,
+    revision_history: RevisionHistory,
+    audit_log: AuditLog,
+    csv_import: CsvImport,
+    waypoints: WaypointTransfer,
+    backup_restore: BackupRestore,
+    world_import: WorldImport,
+    paste_location: PasteLocation,
+    log_import: LogImport,
+    nearest: NearestTool,
+    lint_report: LintReport,
+    map_view: MapView,
+    save_preview: Option<SaveScope>,
+    // Saves of portal text; Err() lists every pair that failed
+    text_request: Request<(), BorkError>,
+    // Every pair the server took, as it takes them
+    saved_sender: WakingSender<SavedPair>,
+    saved_receiver: Receiver<SavedPair>,
 
     // Image Modals
     overworld_image_modal: ModalMachine,
@@ -303,22 +725,23 @@ impl NetherPortals {
     // shove them into an array
     // allow user to modal through the array
     pub fn show_keys(&self) {
-        let mut keys: Vec<String> = Vec::new();
-        keys.append(&mut self.overworld.keys().map(|s| s.to_owned()).collect());
-        keys.append(&mut self.nether.keys().map(|s| s.to_owned()).collect());
-
-        keys.iter().for_each(|key| {
-            println!("Key: |{}|", key);
+        self.pairs.iter().for_each(|(id, pair)| {
+            tracing::info!("Key: |{}| |{}|", id, pair.label());
         });
     }
-    fn make_realm_modal_options(&self) -> (Vec<String>, Vec<(String, String)>) {
-        let mut options_x: Vec<(String, String)> = Vec::default();
-        let mut options: Vec<String> = self.overworld.keys().map(|s| s.to_owned()).collect();
-        let neth_keys: Vec<String> = self.nether.keys().map(|s| s.to_owned()).collect();
+    fn make_realm_modal_options(&self) -> (Vec<String>, Vec<i32>) {
+        let mut options_x: Vec<i32> = Vec::default();
+        let mut options: Vec<String> = Vec::default();
 
-        for (index, key) in options.iter_mut().enumerate() {
-            options_x.push((key.clone(), neth_keys[index].clone()));
-            *key = format!("{}: {} & {}", index, key, neth_keys[index]);
+        // Pairs the tag filter hides are left out
+        for (id, pair) in &self.pairs {
+            let pts =
+                [&Realm::Overworld, &Realm::Nether].map(|realm| pair.realm_ref(realm).pt_ref());
+            if !self.tags.allows(&pts) {
+                continue;
+            }
+            options.push(format!("{}: {}", options_x.len(), pair.label()));
+            options_x.push(*id);
         }
 
         //let mut cnt = 0;
@@ -336,6 +759,12 @@ impl NetherPortals {
 
         (options, options_x)
     }
+    pub fn tags_ref(&self) -> &Tags {
+        &self.tags
+    }
+    pub fn tags_mut(&mut self) -> &mut Tags {
+        &mut self.tags
+    }
     pub fn realm_modal_mut(&mut self) -> &mut ModalMachineX {
         &mut self.realm_modal
     }
@@ -382,46 +811,62 @@ impl NetherPortals {
 
 impl NetherPortals {
     pub fn default() -> Self {
+        let (saved_sender, saved_receiver) = waking_channel();
         Self {
-            overworld: BTreeMap::new(),
-            nether: BTreeMap::new(),
+            pairs: BTreeMap::new(),
+            position: Keys::default(),
+            ow_names: BTreeMap::new(),
+            nether_names: BTreeMap::new(),
             nether_portal_text_comm: Communicator::new(),
             imager_comm: Communicator::new(),
-            ow_position: Keys::default(),
-            nether_position: Keys::default(),
+            sides: BTreeMap::new(),
+            dimensions: Dimensions::default(),
+            side_forms: SideForms::default(),
+            tags: Tags::new(),
             mutate: bool::default(),
-            text_request: SPromise::make_no_promise(None),
+            history: EditHistory::default(),
+            revision_history: RevisionHistory::default(),
+            audit_log: AuditLog::default(),
+            csv_import: CsvImport::default(),
+            waypoints: WaypointTransfer::default(),
+            backup_restore: BackupRestore::default(),
+            world_import: WorldImport::default(),
+            paste_location: PasteLocation::default(),
+            log_import: LogImport::default(),
+            nearest: NearestTool::default(),
+            lint_report: LintReport::default(),
+            map_view: MapView::default(),
+            save_preview: None,
+            text_request: Request::default(),
+            saved_sender,
+            saved_receiver,
             overworld_image_modal: ModalMachine::default(),
             nether_image_modal: ModalMachine::default(),
             realm_modal: ModalMachineX::default(),
         }
     }
     // Experimental
-    pub fn realm_ref(&self, realm: &Realm) -> &NetherPortalBTree {
-        realm.matcher(&self.overworld, &self.nether)
+    pub fn pairs_ref(&self) -> &PortalPairs {
+        &self.pairs
     }
-    pub fn realm_mut(&mut self, realm: &Realm) -> &mut NetherPortalBTree {
-        realm.matcher(&mut self.overworld, &mut self.nether)
+    pub fn pair_ref(&self, id: i32) -> Option<&PortalPair> {
+        self.pairs.get(&id)
     }
-
-    pub fn realm_pos(&self, realm: &Realm) -> Option<String> {
-        realm
-            .matcher(&self.ow_position, &self.nether_position)
-            .current()
+    pub fn pair_mut(&mut self, id: i32) -> Option<&mut PortalPair> {
+        self.pairs.get_mut(&id)
     }
-    fn realm_pos_mut(&mut self, realm: &Realm) -> Option<&mut String> {
-        realm
-            .matcher(&mut self.ow_position, &mut self.nether_position)
-            .current_mut()
+    pub fn portal_ref(&self, realm: &Realm, id: i32) -> Option<&NetherPortal> {
+        Some(self.pair_ref(id)?.realm_ref(realm))
     }
-    pub fn realm_pos_set(&mut self, realm: &Realm, pos: String) -> Option<()> {
-        *self.realm_pos_mut(realm)? = pos;
-        Some(())
+    pub fn portal_mut(&mut self, realm: &Realm, id: i32) -> Option<&mut NetherPortal> {
+        Some(self.pair_mut(id)?.realm_mut(realm))
     }
-    pub fn realm_pos_set2(&mut self, pos: (String, String)) -> Option<()> {
-        *self.realm_pos_mut(&Realm::Overworld)? = pos.0;
-        *self.realm_pos_mut(&Realm::Nether)? = pos.1;
-        Some(())
+
+    pub fn selected(&self) -> Option<i32> {
+        self.position.current()
+    }
+    pub fn select(&mut self, id: i32) -> Option<()> {
+        self.position.select(id)
     }
 
     //pub fn realm_pos_set(&mut self, realm: &Realm, pos: String) -> Option<()> {
@@ -434,45 +879,84 @@ impl NetherPortals {
     // ============>
 
     // Getters
-    pub fn text_request_ref(
-        &self,
-    ) -> &SPromise<Option<String>, Box<dyn Future<Output = ()> + Unpin>> {
+    pub fn text_request_ref(&self) -> &Request<(), BorkError> {
         &self.text_request
     }
-    pub fn text_request_mut(
-        &mut self,
-    ) -> &mut SPromise<Option<String>, Box<dyn Future<Output = ()> + Unpin>> {
+    pub fn text_request_mut(&mut self) -> &mut Request<(), BorkError> {
         &mut self.text_request
     }
-    pub fn set_text_request(
-        &mut self,
-        spromise: SPromise<Option<String>, Box<dyn Future<Output = ()> + Unpin>>,
-    ) {
-        self.text_request = spromise;
-    }
 
-    pub fn overworld_mut(&mut self) -> &mut NetherPortalBTree {
-        &mut self.overworld
+    pub fn names_ref(&self, realm: &Realm) -> &NameIndex {
+        realm.matcher(&self.ow_names, &self.nether_names)
+    }
+    pub fn history_ref(&self) -> &EditHistory {
+        &self.history
+    }
+    pub fn history_mut(&mut self) -> &mut EditHistory {
+        &mut self.history
+    }
+    pub fn revision_history_mut(&mut self) -> &mut RevisionHistory {
+        &mut self.revision_history
+    }
+    pub fn audit_log_ref(&self) -> &AuditLog {
+        &self.audit_log
+    }
+    pub fn audit_log_mut(&mut self) -> &mut AuditLog {
+        &mut self.audit_log
+    }
+    pub fn csv_import_mut(&mut self) -> &mut CsvImport {
+        &mut self.csv_import
+    }
+    pub fn waypoints_mut(&mut self) -> &mut WaypointTransfer {
+        &mut self.waypoints
     }
-    pub fn overworld_ref(&self) -> &NetherPortalBTree {
-        &self.overworld
+    pub fn backup_restore_mut(&mut self) -> &mut BackupRestore {
+        &mut self.backup_restore
     }
-    pub fn nether_mut(&mut self) -> &mut NetherPortalBTree {
-        &mut self.nether
+    pub fn world_import_mut(&mut self) -> &mut WorldImport {
+        &mut self.world_import
     }
-    pub fn nether_ref(&self) -> &NetherPortalBTree {
-        &self.nether
+    pub fn paste_location_mut(&mut self) -> &mut PasteLocation {
+        &mut self.paste_location
+    }
+    pub fn log_import_mut(&mut self) -> &mut LogImport {
+        &mut self.log_import
+    }
+    pub fn nearest_mut(&mut self) -> &mut NearestTool {
+        &mut self.nearest
+    }
+    pub fn lint_report_mut(&mut self) -> &mut LintReport {
+        &mut self.lint_report
+    }
+    pub fn map_view_mut(&mut self) -> &mut MapView {
+        &mut self.map_view
+    }
+    pub fn windows_mut(&mut self) -> Vec<&mut GenericWindow> {
+        //! Every window of the page, so their open states can be kept between restarts
+        vec![
+            &mut self.history.display,
+            &mut self.revision_history.display,
+            &mut self.audit_log.display,
+            &mut self.csv_import.display,
+            &mut self.waypoints.display,
+            &mut self.backup_restore.display,
+            &mut self.world_import.display,
+            &mut self.log_import.display,
+            &mut self.side_forms.display,
+            &mut self.tags.display,
+            &mut self.nearest.display,
+            &mut self.lint_report.display,
+            &mut self.map_view.display,
+        ]
+    }
+    pub fn audit_windows(&mut self, ctx: &Context) -> Result<(), BorkError> {
+        audit_windows(&mut self.revision_history, &mut self.audit_log, ctx)
     }
     pub fn get_mutate(&self) -> bool {
         self.mutate
     }
-
-    // Keys Getters
-    pub fn get_ow_pos(&self) -> Option<String> {
-        self.ow_position.current()
-    }
-    pub fn get_neth_pos(&self) -> Option<String> {
-        self.nether_position.current()
+    pub fn get_save_preview(&self) -> Option<SaveScope> {
+        self.save_preview
     }
 
     // Setters
@@ -481,80 +965,340 @@ impl NetherPortals {
 
         self.mutate
     }
+    pub fn set_save_preview(&mut self, scope: Option<SaveScope>) {
+        self.save_preview = scope;
+    }
 
     // Key Setters
-    pub fn set_ow_pos(&mut self, keys: Vec<String>) {
-        self.ow_position.set_keys(keys);
+    pub fn set_positions(&mut self, ids: Vec<i32>) {
+        self.position.set_keys(ids);
+    }
+
+    // Name Indices
+    pub fn ids_named(&self, realm: &Realm, true_name: &str) -> &[i32] {
+        self.names_ref(realm)
+            .get(true_name)
+            .map_or(&[], |ids| ids.as_slice())
+    }
+    fn unindex(&mut self, id: i32) {
+        //! Take (id) out of both name indices
+        for realm in [Realm::Overworld, Realm::Nether] {
+            let true_name = match self.pair_ref(id) {
+                Some(pair) => pair.true_name_ref(&realm).clone(),
+                None => return,
+            };
+            let names = realm.matcher(&mut self.ow_names, &mut self.nether_names);
+            if let Some(ids) = names.get_mut(&true_name) {
+                ids.retain(|other| *other != id);
+                if ids.is_empty() {
+                    names.remove(&true_name);
+                }
+            }
+        }
     }
-    pub fn set_neth_pos(&mut self, keys: Vec<String>) {
-        self.nether_position.set_keys(keys);
+    fn index(&mut self, id: i32) {
+        for realm in [Realm::Overworld, Realm::Nether] {
+            let true_name = match self.pair_ref(id) {
+                Some(pair) => pair.true_name_ref(&realm).clone(),
+                None => return,
+            };
+            realm
+                .matcher(&mut self.ow_names, &mut self.nether_names)
+                .entry(true_name)
+                .or_default()
+                .push(id);
+        }
+    }
+    pub fn set_pt(&mut self, realm: &Realm, id: i32, pt: PortalText) {
+        //! A side was saved. Its true_name may have changed, so the indices follow
+        self.unindex(id);
+        if let Some(nether_portal) = self.portal_mut(realm, id) {
+            nether_portal.set_pt(pt);
+        }
+        self.index(id);
     }
+    pub fn revert_field(
+        &mut self,
+        dimension_id: &str,
+        id: i32,
+        field: PortalField,
+        value: FieldValue,
+    ) -> Result<(), BorkError> {
+        //! Put a value back into a side (in any dimension), saved text & edit form both
+        //!
+        //! Refused while the field has an unsaved edit, that edit would be thrown away without a word
+        let saved = self
+            .side_text(id, dimension_id)
+            .map(|pt| pt.field_value(field));
+        let edit = self
+            .side_edit_ref(id, dimension_id)
+            .map(|pt| pt.field_value(field));
+        if saved != edit {
+            return Err(BorkError::validation(&format!(
+                "|{}| has an unsaved edit, save or reset it before reverting",
+                field.name()
+            )));
+        }
+        let realm = match Realm::from_id(dimension_id) {
+            Some(realm) => realm,
+            None => {
+                if let Some(side) = self
+                    .sides
+                    .get_mut(&id)
+                    .and_then(|s| s.get_mut(dimension_id))
+                {
+                    side.revert_field(field, value);
+                }
+                return Ok(());
+            }
+        };
+        self.unindex(id);
+        if let Some(nether_portal) = self.portal_mut(&realm, id) {
+            nether_portal.revert_field(field, value);
+        }
+        self.index(id);
 
-    // Increment&Decrement OverWorld Position
-    pub fn ow_pos_up(&mut self) {
-        let index = self.ow_position.get_index();
-        // TODO when the npt server was off, this panicked as an integer overflow
-        // // Fix in the future
-        if index < self.ow_position.len() - 1 {
-            self.ow_position.set_pos(index + 1)
+        Ok(())
+    }
+    pub fn dimensions_ref(&self) -> &Dimensions {
+        &self.dimensions
+    }
+    pub fn dimensions_mut(&mut self) -> &mut Dimensions {
+        &mut self.dimensions
+    }
+    pub fn side_forms_mut(&mut self) -> &mut SideForms {
+        &mut self.side_forms
+    }
+    pub fn dimension_name(&self, dimension_id: &str) -> String {
+        self.dimensions
+            .get(dimension_id)
+            .map_or(dimension_id.to_string(), |dimension| dimension.name)
+    }
+    pub fn dimension(&self, dimension_id: &str) -> Dimension {
+        self.dimensions.get_or_unknown(dimension_id)
+    }
+    pub fn side_text(&self, id: i32, dimension_id: &str) -> Option<&PortalText> {
+        //! The saved text of a side in any dimension (the overworld & nether included)
+        //!
+        //! An extra side the server does not have yet gives its edit
+        match Realm::from_id(dimension_id) {
+            Some(realm) => Some(self.portal_ref(&realm, id)?.pt_ref()),
+            None => {
+                let side = self.sides.get(&id)?.get(dimension_id)?;
+                Some(side.saved_ref().unwrap_or(side.edit_ref()))
+            }
         }
     }
-    pub fn ow_pos_down(&mut self) {
-        let index = self.ow_position.get_index();
-        if index > 0 {
-            self.ow_position.set_pos(index - 1)
+    pub fn side_texts(&self, id: i32) -> Vec<(Dimension, &PortalText)> {
+        //! Every side pair (id) has, the overworld & nether first
+        let mut dimension_ids: Vec<String> = Dimensions::primary()
+            .into_iter()
+            .map(|dimension| dimension.id)
+            .collect();
+        dimension_ids.extend(
+            self.sides
+                .get(&id)
+                .into_iter()
+                .flat_map(|s| s.keys().cloned()),
+        );
+
+        dimension_ids
+            .iter()
+            .filter_map(|dimension_id| {
+                let pt = self.side_text(id, dimension_id)?;
+                Some((self.dimension(dimension_id), pt))
+            })
+            .collect()
+    }
+    pub fn side_edit_ref(&self, id: i32, dimension_id: &str) -> Option<&PortalText> {
+        //! The edit form of a side in any dimension
+        match Realm::from_id(dimension_id) {
+            Some(realm) => Some(self.portal_ref(&realm, id)?.edit_ref()),
+            None => Some(self.sides.get(&id)?.get(dimension_id)?.edit_ref()),
+        }
+    }
+    pub fn side_edit_mut(&mut self, id: i32, dimension_id: &str) -> Option<&mut PortalText> {
+        //! The edit form of a side in any dimension
+        match Realm::from_id(dimension_id) {
+            Some(realm) => Some(self.portal_mut(&realm, id)?.edit_mut()),
+            None => Some(self.sides.get_mut(&id)?.get_mut(dimension_id)?.edit_mut()),
+        }
+    }
+    pub fn dirty_sides(&self, id: i32) -> Vec<(String, &ExtraSide)> {
+        //! (dimension id, side) of every extra side with an unsaved edit
+        self.sides
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .filter(|(_, side)| side.is_dirty())
+            .map(|(dimension_id, side)| (dimension_id.clone(), side))
+            .collect()
+    }
+    pub fn sides_ref(&self, id: i32) -> Option<&BTreeMap<String, ExtraSide>> {
+        self.sides.get(&id)
+    }
+    pub fn sides_mut(&mut self, id: i32) -> &mut BTreeMap<String, ExtraSide> {
+        self.sides.entry(id).or_default()
+    }
+    pub fn saved_sides(&self, id: i32) -> BTreeMap<String, PortalText> {
+        //! The extra sides the server knows about, ready to go into a NetherPortalText
+        self.sides
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .filter_map(|(dimension_id, side)| {
+                Some((dimension_id.clone(), side.saved_ref()?.clone()))
+            })
+            .collect()
+    }
+    pub fn are_sides_dirty(&self, id: i32) -> bool {
+        self.sides
+            .get(&id)
+            .is_some_and(|sides| sides.values().any(|side| side.is_dirty()))
+    }
+    pub fn to_npt(&self, id: i32) -> Option<NetherPortalText> {
+        //! Rebuild the NetherPortalText of a pair, with the saved text (not the edits)
+        let pair = self.pair_ref(id)?;
+        let npt = NetherPortalText::build_from(
+            id,
+            pair.overworld.pt_ref().clone(),
+            pair.nether.pt_ref().clone(),
+        )
+        .with_username(pair.username_ref().clone())
+        .with_sides(self.saved_sides(id));
+
+        Some(npt)
+    }
+    pub fn to_edited_npt(&self, id: i32, scope: SaveScope) -> Option<NetherPortalText> {
+        //! The NetherPortalText a save of (scope) sends: the edits it covers, the saved text for the rest
+        let pair = self.pair_ref(id)?;
+        let side = |realm: &Realm| {
+            let np = pair.realm_ref(realm);
+            match scope.realms().contains(realm) {
+                true => np.edit_ref().clone(),
+                false => np.pt_ref().clone(),
+            }
+        };
+        let sides = match scope.includes_sides() {
+            true => self
+                .sides
+                .get(&id)
+                .into_iter()
+                .flatten()
+                .map(|(dimension_id, side)| (dimension_id.clone(), side.edit_ref().clone()))
+                .collect(),
+            false => self.saved_sides(id),
+        };
+        let npt = NetherPortalText::build_from(id, side(&Realm::Overworld), side(&Realm::Nether))
+            .with_username(pair.username_ref().clone())
+            .with_sides(sides);
+
+        Some(npt)
+    }
+    pub fn saved_sender_clone(&self) -> WakingSender<SavedPair> {
+        self.saved_sender.clone()
+    }
+    pub fn try_update_saved(&mut self) {
+        //! Move what the server took into the saved text, as each save finishes
+        while let Ok(saved) = self.saved_receiver.try_recv() {
+            let scope = match saved.scope {
+                Some(scope) => scope,
+                None => continue,
+            };
+            let (id, mut npt) = (saved.npt.id, saved.npt);
+            for realm in scope.realms() {
+                let pt = mem::take(realm.matcher(&mut npt.overworld, &mut npt.nether));
+                self.set_pt(&realm, id, pt);
+                self.history.mark_saved(&realm.dimension().id, id);
+            }
+            if !scope.includes_sides() {
+                continue;
+            }
+            for (dimension_id, pt) in npt.sides {
+                if let Some(side) = self
+                    .sides
+                    .get_mut(&id)
+                    .and_then(|s| s.get_mut(&dimension_id))
+                {
+                    side.set_saved(pt);
+                }
+                self.history.mark_saved(&dimension_id, id);
+            }
+        }
+    }
+    pub fn to_npts(&self) -> Vec<NetherPortalText> {
+        self.pairs
+            .keys()
+            .filter_map(|id| self.to_npt(*id))
+            .collect()
+    }
+    pub fn has_true_name(&self, realm: &Realm, true_name: &str) -> bool {
+        !self.ids_named(realm, true_name).is_empty()
+    }
+    pub fn dirty_pairs(&self) -> Vec<i32> {
+        //! Every pair that has an unsaved edit on any side
+        self.pairs
+            .iter()
+            .filter(|(id, pair)| pair.is_dirty() || self.are_sides_dirty(**id))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    // Undo & Redo
+    fn apply_field(&mut self, dimension_id: &str, id: i32, field: PortalField, value: FieldValue) {
+        if let Some(edit) = self.side_edit_mut(id, dimension_id) {
+            edit.set_field(field, value);
         }
     }
+    pub fn undo(&mut self) -> Option<()> {
+        //! Put the last edit's old value back into its edit form
+        let edit = self.history.take_undo()?;
+        self.apply_field(&edit.dimension.id, edit.id, edit.field, edit.old);
+        Some(())
+    }
+    pub fn redo(&mut self) -> Option<()> {
+        let edit = self.history.take_redo()?;
+        self.apply_field(&edit.dimension.id, edit.id, edit.field, edit.new);
+        Some(())
+    }
 
-    // Increment&Decrement Nether Position
-    pub fn neth_pos_up(&mut self) {
-        let index = self.nether_position.get_index();
-        if index < self.nether_position.len() - 1 {
-            self.nether_position.set_pos(index + 1);
+    // Increment&Decrement Position
+    pub fn pos_up(&mut self) {
+        let index = self.position.get_index();
+        // When the npt server was off, this used to panic as an integer overflow (len() - 1 of nothing)
+        if index + 1 < self.position.len() {
+            self.position.set_pos(index + 1)
         }
     }
-    pub fn neth_pos_down(&mut self) {
-        let index = self.nether_position.get_index();
+    pub fn pos_down(&mut self) {
+        let index = self.position.get_index();
         if index > 0 {
-            self.nether_position.set_pos(index - 1);
+            self.position.set_pos(index - 1)
         }
     }
 
     // Senders
-    pub fn npt_sender_clone(&self) -> Sender<NetherPortalText> {
+    pub fn npt_sender_clone(&self) -> WakingSender<NetherPortalText> {
         self.nether_portal_text_comm.downloader_sender_clone()
     }
-    pub fn imager_sender_clone(&self) -> Sender<Imager> {
+    pub fn imager_sender_clone(&self) -> WakingSender<Imager> {
         self.imager_comm.downloader_sender_clone()
     }
 
     // Test Function
-    pub fn is_nether_empty(&self) -> bool {
-        //! Checks if nether is empty
-        self.nether.is_empty()
-    }
-    pub fn is_overworld_empty(&self) -> bool {
-        //! Checks if overworld is empty
-        self.overworld.is_empty()
+    pub fn is_empty(&self) -> bool {
+        //! Checks if there are no pairs
+        self.pairs.is_empty()
     }
     pub fn quickn(&self) -> String {
-        format!(
-            "Is this BTreeMap empty?: Answer: |{}|",
-            self.is_nether_empty()
-        )
+        format!("Is this BTreeMap empty?: Answer: |{}|", self.is_empty())
     }
     pub fn quicko(&self) -> String {
-        format!(
-            "Is this BTreeMap empty?: Answer: |{}|",
-            self.is_overworld_empty()
-        )
+        format!("Is this BTreeMap empty?: Answer: |{}|", self.is_empty())
     }
     pub fn quick_portal(&self) -> &PortalText {
-        self.overworld
-            .get("Luke SpawnPoint")
-            .unwrap()
-            .portal_text
-            .quick_value()
+        let id = self.ids_named(&Realm::Overworld, "Luke SpawnPoint")[0];
+        self.pairs[&id].overworld.portal_text.quick_value()
         ////-> &NetherPortalText {
         //let mut string = String::default();
         //self.overworld.iter().for_each(|(key, value)| {
@@ -576,56 +1320,71 @@ impl NetherPortals {
         &self.nether_portal_text_comm.downloader_receiver()
     }
 
-    pub fn consume_npt_helper(
-        original_id: i32,
-        key: String,
-        np_list: &mut BTreeMap<String, NetherPortal>,
-        pt: PortalText,
-    ) {
-        match np_list.contains_key(&key) {
-            // If Key exists, UPDATE value
-            true => {
-                np_list.get_mut(&key).unwrap().add_portal_text(pt);
-            }
-            false => {
-                // if Key DOES NOT exist, INSERT new value
-                let overworld_np = NetherPortal {
-                    original_id,
-                    portal_text: SPromise::make_no_promise(pt),
-                    as_btree: BTreeMap::new(),
-                    images: BTreeMap::new(),
-                    image_position: String::default(),
-                    image_list_request: SPromise::make_no_promise(None),
-                };
-                np_list.insert(key, overworld_np);
-            }
-        }
-    }
-
     pub fn consume_npt(&mut self, mut npt: NetherPortalText) {
         //! Given a moved NetherPortalText struct: (npt)
         //!
         //! Take its members and give them to NetherPortals struct
 
-        // Take/Append OverWorld (use mem::take to avoid Partial Move|| maybe rust will update compiler to fix this?)
+        // Take both sides (use mem::take to avoid Partial Move|| maybe rust will update compiler to fix this?)
         let og_id = npt.id;
-
-        //let key = npt.ow_true_name();
-        let key = npt.ow_true_name();
+        let username = npt.username.clone();
         let overworld = mem::take(&mut npt.overworld);
-        Self::consume_npt_helper(og_id, key, &mut self.overworld, overworld);
-
-        // Take/Append Nether
-        let key = npt.nether_true_name();
-        let nether = npt.nether;
-        Self::consume_npt_helper(og_id, key, &mut self.nether, nether);
+        let nether = mem::take(&mut npt.nether);
+
+        // If the pair exists, UPDATE it; otherwise INSERT it. Either way its names are indexed again
+        self.unindex(og_id);
+        match self.pairs.get_mut(&og_id) {
+            Some(pair) => {
+                pair.overworld.add_portal_text(overworld);
+                pair.nether.add_portal_text(nether);
+            }
+            None => {
+                let pair = PortalPair {
+                    overworld: NetherPortal::new(og_id, username.clone(), overworld),
+                    nether: NetherPortal::new(og_id, username, nether),
+                };
+                self.pairs.insert(og_id, pair);
+            }
+        }
+        self.index(og_id);
+
+        // Take/Append every other side
+        let sides = self.sides_mut(og_id);
+        for (dimension_id, pt) in npt.sides {
+            match sides.get_mut(&dimension_id) {
+                Some(side) => side.add_saved(pt),
+                None => {
+                    sides.insert(dimension_id, ExtraSide::from_saved(pt));
+                }
+            }
+        }
     }
 
     //pub fn add_imager_to_nether_portal(&mut self, key: String, imager: Imager) {
     //    if self.nether
     //}
 
-    pub fn try_update_npt(&mut self) -> Result<(), MagicError> {
+    pub fn poll_requests(&mut self) {
+        //! Take in whatever the image downloads have finished
+        //!
+        //! Downloads for pairs that are no longer selected are cancelled; they start again when the pair is
+        let selected = self.selected();
+        for (id, pair) in self.pairs.iter_mut() {
+            for realm in [Realm::Overworld, Realm::Nether] {
+                pair.realm_mut(&realm)
+                    .images_mut()
+                    .values_mut()
+                    .for_each(|slot| {
+                        if Some(*id) != selected && slot.request.is_pending() {
+                            slot.request.reset();
+                        }
+                        slot.request.poll();
+                    });
+            }
+        }
+    }
+
+    pub fn try_update_npt(&mut self) -> Result<(), BorkError> {
         while let Ok(nether_portal_text) = self.npt_receiver().try_recv() {
             self.consume_npt(nether_portal_text);
             // If npt is updated then you also need to update is dependencies
@@ -634,3 +1393,195 @@ impl NetherPortals {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hub() -> PortalText {
+        let mut pt = PortalText::build("Hub".to_string(), (100, 70, -200));
+        pt.set_field(PortalField::Owner, FieldValue::Text("steve".to_string()));
+        pt
+    }
+
+    #[test]
+    fn quotes_in_notes_survive_saving() {
+        let mut pt = hub();
+        let notes = r#"The "old" hub, by the {lake}: 'don't' \ go"#;
+        pt.set_field(PortalField::Notes, FieldValue::Text(notes.to_string()));
+
+        let json = serde_json::to_string(&pt).unwrap();
+        let read: PortalText = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.field_string(PortalField::Notes), notes);
+        assert_eq!(read, pt);
+    }
+
+    #[test]
+    fn fields_are_typed() {
+        let mut pt = hub();
+        assert_eq!(pt.field_value(PortalField::Xcord), FieldValue::Cord(100));
+        assert_eq!(
+            pt.field_value(PortalField::TrueName),
+            FieldValue::Text("Hub".to_string())
+        );
+
+        pt.set_field(PortalField::Zcord, FieldValue::Cord(5));
+        assert_eq!(pt.cords(), (100, 70, 5));
+        // The wrong kind of value is ignored
+        pt.set_field(PortalField::Ycord, FieldValue::Text("12".to_string()));
+        pt.set_field(PortalField::Owner, FieldValue::Cord(12));
+        assert_eq!(pt.cords(), (100, 70, 5));
+        assert_eq!(pt.owner_ref(), "steve");
+    }
+
+    #[test]
+    fn diff_lists_every_changed_field() {
+        let old = hub();
+        let mut new = hub();
+        assert!(old.diff(&new).is_empty());
+
+        new.set_field(PortalField::Xcord, FieldValue::Cord(-8));
+        new.set_field(PortalField::Notes, FieldValue::Text("moved".to_string()));
+        let changes: Vec<(PortalField, String, String)> = old
+            .diff(&new)
+            .into_iter()
+            .map(|change| (change.field, change.old, change.new))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (PortalField::Xcord, "100".to_string(), "-8".to_string()),
+                (PortalField::Notes, String::new(), "moved".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_each_field() {
+        assert!(hub().validate(&Dimension::overworld()).is_empty());
+
+        let mut pt = PortalText::build(" ".to_string(), (WORLD_BORDER + 1, 300, 0));
+        let problems = pt.validate(&Dimension::nether());
+        let fields: Vec<&PortalField> = problems.keys().collect();
+        assert_eq!(
+            fields,
+            [
+                &PortalField::Xcord,
+                &PortalField::Ycord,
+                &PortalField::TrueName
+            ]
+        );
+        assert_eq!(problems[&PortalField::Ycord], "Must be between 0 and 255");
+        // 300 is fine in the overworld
+        assert!(!pt
+            .validate(&Dimension::overworld())
+            .contains_key(&PortalField::Ycord));
+
+        pt.set_field(PortalField::TrueName, FieldValue::Text("Hub ".to_string()));
+        assert_eq!(
+            pt.validate(&Dimension::overworld())[&PortalField::TrueName],
+            "Remove the spaces at the start/end"
+        );
+    }
+
+    #[test]
+    fn edits_are_tracked_against_the_saved_text() {
+        let mut np = NetherPortal::new(1, "steve".to_string(), hub());
+        assert!(!np.is_dirty());
+
+        np.edit_mut()
+            .set_field(PortalField::Locale, FieldValue::Text("spawn".to_string()));
+        assert!(np.is_dirty());
+        assert_eq!(np.changes()[0].field, PortalField::Locale);
+
+        // A reload from the server keeps what was typed
+        let mut reloaded = hub();
+        reloaded.set_field(PortalField::Ycord, FieldValue::Cord(80));
+        np.add_portal_text(reloaded.clone());
+        assert_eq!(np.edit_ref().field_string(PortalField::Locale), "spawn");
+        assert_eq!(np.edit_ref().cords(), (100, 70, -200));
+
+        np.reset_edit();
+        assert!(!np.is_dirty());
+        assert_eq!(np.edit_ref(), &reloaded);
+        // Without edits a reload is followed along
+        let mut moved = hub();
+        moved.set_field(PortalField::Xcord, FieldValue::Cord(0));
+        np.add_portal_text(moved);
+        assert_eq!(np.edit_ref().cords(), (0, 70, -200));
+    }
+
+    fn pair(id: i32, ow: &str, nether: &str) -> NetherPortalText {
+        NetherPortalText::build_from(
+            id,
+            PortalText::build(ow.to_string(), (0, 70, 0)),
+            PortalText::build(nether.to_string(), (0, 70, 0)),
+        )
+        .with_username("steve".to_string())
+    }
+
+    #[test]
+    fn pairs_are_keyed_by_id() {
+        let mut nether_portals = NetherPortals::default();
+        // Names that sort differently in each realm & a shared overworld name
+        nether_portals.consume_npt(pair(7, "Zoo", "Alpha N"));
+        nether_portals.consume_npt(pair(3, "Base", "Zulu N"));
+        nether_portals.consume_npt(pair(5, "Base", "Mid N"));
+
+        let labels: Vec<String> = nether_portals
+            .pairs_ref()
+            .values()
+            .map(|pair| pair.label())
+            .collect();
+        assert_eq!(labels, ["Base & Zulu N", "Base & Mid N", "Zoo & Alpha N"]);
+        assert_eq!(nether_portals.ids_named(&Realm::Overworld, "Base"), [3, 5]);
+        assert_eq!(nether_portals.ids_named(&Realm::Nether, "Alpha N"), [7]);
+        assert!(nether_portals.ids_named(&Realm::Nether, "Base").is_empty());
+
+        // The list steps through ids, so each entry is a whole pair
+        let (options, ids) = nether_portals.make_realm_modal_options();
+        assert_eq!(ids, [3, 5, 7]);
+        assert_eq!(options[2], "2: Zoo & Alpha N");
+        nether_portals.set_positions(ids);
+        nether_portals.select(5).unwrap();
+        assert_eq!(nether_portals.selected(), Some(5));
+        assert!(nether_portals.select(4).is_none());
+    }
+
+    #[test]
+    fn renames_move_the_name_indices() {
+        let mut nether_portals = NetherPortals::default();
+        nether_portals.consume_npt(pair(1, "Hub", "Hub N"));
+        nether_portals.consume_npt(pair(2, "Farm", "Farm N"));
+
+        // Reloaded from the server with a new name
+        nether_portals.consume_npt(pair(1, "Spawn", "Hub N"));
+        assert!(!nether_portals.has_true_name(&Realm::Overworld, "Hub"));
+        assert_eq!(nether_portals.ids_named(&Realm::Overworld, "Spawn"), [1]);
+        assert_eq!(nether_portals.pairs_ref().len(), 2);
+
+        // Saved from the edit form
+        let farm = PortalText::build("Old Farm N".to_string(), (0, 70, 0));
+        nether_portals.set_pt(&Realm::Nether, 2, farm);
+        assert!(!nether_portals.has_true_name(&Realm::Nether, "Farm N"));
+        assert_eq!(nether_portals.ids_named(&Realm::Nether, "Old Farm N"), [2]);
+        assert_eq!(nether_portals.ids_named(&Realm::Overworld, "Farm"), [2]);
+    }
+
+    #[test]
+    fn pairs_turn_back_into_what_was_loaded() {
+        let mut nether_portals = NetherPortals::default();
+        let end = BTreeMap::from([(
+            Dimension::the_end().id,
+            PortalText::build("Hub E".to_string(), (0, 60, 0)),
+        )]);
+        let npt = pair(9, "Hub", "Hub N").with_sides(end);
+        nether_portals.consume_npt(npt.clone());
+
+        assert_eq!(
+            serde_json::to_value(nether_portals.to_npt(9).unwrap()).unwrap(),
+            serde_json::to_value(npt).unwrap()
+        );
+        assert!(nether_portals.to_npt(8).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+use eframe::egui::{Context, DragValue, Grid, Ui};
+
+use super::portals::{ExtraSide, NetherPortals};
+use crate::{
+    dimension::Dimension, eframe_tools::ModalMachine, windows::client_windows::GenericWindow,
+    BorkError, Realm,
+};
+
+// The "Add Side" picker & the custom dimension form
+pub struct SideForms {
+    picker: ModalMachine,
+    new_dimension: Dimension,
+    pub display: GenericWindow,
+}
+
+impl Default for SideForms {
+    fn default() -> Self {
+        Self {
+            picker: ModalMachine::default(),
+            new_dimension: Dimension::new("mymod:dimension", "My Dimension", 1.0, 0, 255),
+            display: GenericWindow::new("Dimensions"),
+        }
+    }
+}
+
+pub fn add_side_picker(nether_portals: &mut NetherPortals, id: i32, ui: &mut Ui) {
+    //! Pick a dimension this pair does not have a side in yet and add one
+    let true_name = match nether_portals.pair_ref(id) {
+        Some(pair) => pair.true_name_ref(&Realm::Overworld).clone(),
+        None => return,
+    };
+    let sides = nether_portals.sides_ref(id);
+    let options: Vec<String> = nether_portals
+        .dimensions_ref()
+        .extra()
+        .into_iter()
+        .filter(|dimension| !sides.is_some_and(|sides| sides.contains_key(&dimension.id)))
+        .map(|dimension| dimension.id)
+        .collect();
+    if options.is_empty() {
+        return;
+    }
+
+    // Keep the selection between frames, as long as it is still an option
+    let forms = nether_portals.side_forms_mut();
+    let selected = forms.picker.get_selected_option();
+    let selected = match options.contains(&selected) {
+        true => selected,
+        false => options[0].clone(),
+    };
+    forms.picker = ModalMachine::new(selected, options, "Side".to_string());
+
+    let mut add = None;
+    ui.horizontal(|ui| {
+        forms.picker.modal_machine(0, ui);
+        if ui.button("Add Side").clicked() {
+            add = Some(forms.picker.get_selected_option());
+        }
+    });
+    if let Some(dimension_id) = add {
+        nether_portals
+            .sides_mut(id)
+            .insert(dimension_id, ExtraSide::new_side(true_name));
+    }
+}
+
+pub fn reset_sides(nether_portals: &mut NetherPortals, id: i32) {
+    //! Throw away the edits of every extra side; sides the server does not have are removed
+    nether_portals.sides_mut(id).retain(|_, side| side.reset());
+}
+
+fn dimension_form(new_dimension: &mut Dimension, ui: &mut Ui) {
+    Grid::new("new dimension").show(ui, |ui| {
+        ui.label("Id:");
+        ui.text_edit_singleline(&mut new_dimension.id);
+        ui.end_row();
+        ui.label("Name:");
+        ui.text_edit_singleline(&mut new_dimension.name);
+        ui.end_row();
+        ui.label("Scale:");
+        ui.add(
+            DragValue::new(&mut new_dimension.scale)
+                .speed(0.125)
+                .clamp_range(0.0001..=1024.0),
+        );
+        ui.end_row();
+        ui.label("Min Y:");
+        ui.add(DragValue::new(&mut new_dimension.min_y));
+        ui.end_row();
+        ui.label("Max Y:");
+        ui.add(DragValue::new(&mut new_dimension.max_y));
+        ui.end_row();
+    });
+}
+
+pub fn dimensions_window(
+    nether_portals: &mut NetherPortals,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! Shows the "Dimensions" window (if its open); every known dimension & a form for custom ones
+    let mut forms = std::mem::take(nether_portals.side_forms_mut());
+    let mut display = std::mem::take(&mut forms.display);
+
+    let mut result = Ok(());
+    display.show_with(ctx, |ui| {
+        let dimensions = nether_portals.dimensions_mut();
+        Grid::new("dimensions").striped(true).show(ui, |ui| {
+            ui.strong("Id");
+            ui.strong("Name");
+            ui.strong("Scale");
+            ui.strong("Build Limits");
+            ui.end_row();
+
+            let custom: Vec<String> = dimensions
+                .custom_ref()
+                .iter()
+                .map(|d| d.id.clone())
+                .collect();
+            for dimension in dimensions.all() {
+                ui.label(&dimension.id);
+                ui.label(&dimension.name);
+                ui.label(dimension.scale.to_string());
+                ui.label(format!("{} to {}", dimension.min_y, dimension.max_y));
+                if custom.contains(&dimension.id) && ui.button("Remove").clicked() {
+                    dimensions.remove(&dimension.id);
+                }
+                ui.end_row();
+            }
+        });
+        ui.separator();
+
+        ui.strong("Custom Dimension");
+        dimension_form(&mut forms.new_dimension, ui);
+        ui.horizontal(|ui| {
+            if ui.button("Add Dimension").clicked() {
+                result = dimensions.add(forms.new_dimension.clone());
+            }
+            if ui.button("Save Dimensions").clicked() {
+                result = dimensions.save();
+            }
+        });
+    });
+
+    forms.display = display;
+    *nether_portals.side_forms_mut() = forms;
+
+    result
+}
@@ -0,0 +1,283 @@
+use eframe::egui::{Color32, ComboBox, Context, Grid, RichText, Ui};
+use serde_derive::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::portals::{FieldValue, NetherPortals, PortalField, PortalText};
+use crate::{profiles::world_path, windows::client_windows::GenericWindow, BorkError};
+
+// Where tag colors are kept between sessions (one file per world)
+pub const TAGS_FILE: &str = "borkcraft_tags.json";
+
+// Tags that are not defined (typed by hand, or from another world) get this color
+const UNKNOWN_TAG: Color32 = Color32::GRAY;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TagDef {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Color")]
+    pub color: [u8; 3],
+}
+
+impl TagDef {
+    pub fn new(name: &str, color: [u8; 3]) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+        }
+    }
+}
+
+pub struct Tags {
+    defs: Vec<TagDef>,
+    // Only pairs with this tag are listed; empty lists every pair
+    filter: String,
+    new_tag: TagDef,
+    pub display: GenericWindow,
+}
+
+impl Default for Tags {
+    fn default() -> Self {
+        Self {
+            defs: Vec::new(),
+            filter: String::default(),
+            new_tag: TagDef::new("new tag", [255, 85, 255]),
+            display: GenericWindow::new("Tags"),
+        }
+    }
+}
+
+impl Tags {
+    pub fn new() -> Self {
+        //! The active world's tags; a missing or broken file just means the starter tags
+        let defs = Self::load(&world_path(TAGS_FILE)).unwrap_or_else(|_| {
+            vec![
+                TagDef::new("base", [85, 85, 255]),
+                TagDef::new("farm", [85, 255, 85]),
+                TagDef::new("hub", [255, 170, 0]),
+                TagDef::new("public", [85, 255, 255]),
+                TagDef::new("abandoned", [170, 0, 0]),
+            ]
+        });
+        Self {
+            defs,
+            ..Self::default()
+        }
+    }
+
+    fn load(path: &str) -> Result<Vec<TagDef>, BorkError> {
+        if !Path::new(path).exists() {
+            return Err(BorkError::Io("No tags file".to_string()));
+        }
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<(), BorkError> {
+        std::fs::write(
+            world_path(TAGS_FILE),
+            serde_json::to_vec_pretty(&self.defs)?,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn color(&self, tag: &str) -> Color32 {
+        self.defs
+            .iter()
+            .find(|def| def.name.eq_ignore_ascii_case(tag))
+            .map_or(UNKNOWN_TAG, |def| {
+                let [r, g, b] = def.color;
+                Color32::from_rgb(r, g, b)
+            })
+    }
+
+    pub fn add(&mut self, def: TagDef) -> Result<(), BorkError> {
+        let name = def.name.trim().to_string();
+        if name.is_empty() || name.contains(',') {
+            return Err(BorkError::validation("A tag needs a name without commas"));
+        }
+        if self.defs.iter().any(|d| d.name.eq_ignore_ascii_case(&name)) {
+            return Err(BorkError::validation(&format!("|{}| already exists", name)));
+        }
+        self.defs.push(TagDef { name, ..def });
+
+        Ok(())
+    }
+
+    pub fn set_filter(&mut self, tag: &str) {
+        //! Only list the pairs with (tag); empty lists every pair
+        self.filter = tag.to_string();
+    }
+
+    pub fn allows(&self, pts: &[&PortalText]) -> bool {
+        //! A pair is listed when any of its sides has the filter tag
+        self.filter.is_empty() || pts.iter().any(|pt| pt.has_tag(&self.filter))
+    }
+}
+
+pub fn tag_chips(tags: &Tags, pt: &PortalText, ui: &mut Ui) {
+    //! Every tag of (pt) in its color
+    ui.horizontal_wrapped(|ui| {
+        for tag in pt.tags() {
+            ui.label(RichText::new(&tag).strong().color(tags.color(&tag)));
+        }
+    });
+}
+
+pub fn tag_picker(
+    tags: &Tags,
+    edit: &mut PortalText,
+    ui: &mut Ui,
+) -> Option<(FieldValue, FieldValue)> {
+    //! A button for every defined tag (pt) does not have yet. Returns the (old, new) tags field on a click
+    let missing: Vec<&TagDef> = tags
+        .defs
+        .iter()
+        .filter(|def| !edit.has_tag(&def.name))
+        .collect();
+
+    let mut changed = None;
+    ui.horizontal_wrapped(|ui| {
+        for def in missing {
+            let text = RichText::new(format!("+ {}", def.name)).color(tags.color(&def.name));
+            if ui.button(text).clicked() {
+                let old = edit.field_value(PortalField::Tags);
+                let new = FieldValue::Text(edit.with_tag(&def.name));
+                edit.set_field(PortalField::Tags, new.clone());
+                changed = Some((old, new));
+            }
+        }
+    });
+    changed
+}
+
+pub fn tag_filter(nether_portals: &mut NetherPortals, ui: &mut Ui) {
+    //! Pick a tag to only list the pairs that have it
+    let names: Vec<String> = nether_portals
+        .tags_ref()
+        .defs
+        .iter()
+        .map(|def| def.name.clone())
+        .collect();
+    let mut filter = nether_portals.tags_ref().filter.clone();
+    let selected_text = match filter.is_empty() {
+        true => "(every tag)".to_string(),
+        false => filter.clone(),
+    };
+
+    ComboBox::from_label("Tag Filter")
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut filter, String::default(), "(every tag)");
+            for name in names {
+                let text = RichText::new(&name).color(nether_portals.tags_ref().color(&name));
+                ui.selectable_value(&mut filter, name, text);
+            }
+        });
+
+    // The list only changes when the filter does
+    if filter != nether_portals.tags_ref().filter {
+        nether_portals.tags_mut().set_filter(&filter);
+        nether_portals.realm_modal_set();
+    }
+}
+
+pub fn tags_window(nether_portals: &mut NetherPortals, ctx: &Context) -> Result<(), BorkError> {
+    //! Shows the "Tags" window (if its open); every tag with its color & a form for new ones
+    let mut tags = std::mem::take(nether_portals.tags_mut());
+    let mut display = std::mem::take(&mut tags.display);
+
+    let mut result = Ok(());
+    display.show_with(ctx, |ui| {
+        let mut remove = None;
+        Grid::new("tags").striped(true).show(ui, |ui| {
+            ui.strong("Tag");
+            ui.strong("Color");
+            ui.end_row();
+            for (index, def) in tags.defs.iter_mut().enumerate() {
+                ui.label(RichText::new(&def.name).strong());
+                ui.color_edit_button_srgb(&mut def.color);
+                if ui.button("Remove").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = remove {
+            tags.defs.remove(index);
+        }
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut tags.new_tag.name);
+            ui.color_edit_button_srgb(&mut tags.new_tag.color);
+            if ui.button("Add Tag").clicked() {
+                result = tags.add(tags.new_tag.clone());
+            }
+        });
+        if ui.button("Save Tags").clicked() {
+            result = tags.save();
+        }
+    });
+
+    tags.display = display;
+    *nether_portals.tags_mut() = tags;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tagged(tags: &str) -> PortalText {
+        let mut pt = PortalText::default();
+        pt.set_field(PortalField::Tags, FieldValue::Text(tags.to_string()));
+        pt
+    }
+
+    #[test]
+    fn tags_are_split_on_commas() {
+        assert_eq!(tagged(" base, farm ,hub").tags(), ["base", "farm", "hub"]);
+        // Blank entries are not tags
+        assert_eq!(tagged("base,, ,farm,").tags(), ["base", "farm"]);
+        assert!(tagged("").tags().is_empty());
+        assert!(tagged("Base").has_tag("base"));
+        assert!(!tagged("base").has_tag("bas"));
+    }
+
+    #[test]
+    fn with_tag_does_not_repeat_tags() {
+        assert_eq!(tagged("").with_tag("hub"), "hub");
+        assert_eq!(tagged("base,farm").with_tag("hub"), "base, farm, hub");
+        assert_eq!(tagged("base, farm").with_tag("farm"), "base, farm");
+        assert_eq!(tagged("base, farm").with_tag("FARM"), "base, farm");
+    }
+
+    #[test]
+    fn filter_allows_pairs_with_the_tag_on_either_side() {
+        let mut tags = Tags::default();
+        let (hub, farm, untagged) = (tagged("hub, public"), tagged("Farm"), tagged(""));
+        assert!(tags.allows(&[&untagged, &untagged]));
+
+        tags.set_filter("farm");
+        assert!(tags.allows(&[&farm, &untagged]));
+        assert!(tags.allows(&[&untagged, &farm]));
+        assert!(!tags.allows(&[&hub, &untagged]));
+        assert!(!tags.allows(&[&untagged, &untagged]));
+
+        tags.set_filter("");
+        assert!(tags.allows(&[&hub, &untagged]));
+    }
+
+    #[test]
+    fn tag_names_are_checked() {
+        let mut tags = Tags::default();
+        tags.add(TagDef::new(" farm ", [0, 255, 0])).unwrap();
+        assert_eq!(tags.color("Farm"), Color32::from_rgb(0, 255, 0));
+        assert_eq!(tags.color("base"), UNKNOWN_TAG);
+        assert!(tags.add(TagDef::new("FARM", [0, 0, 0])).is_err());
+        assert!(tags.add(TagDef::new("a, b", [0, 0, 0])).is_err());
+        assert!(tags.add(TagDef::new("  ", [0, 0, 0])).is_err());
+    }
+}
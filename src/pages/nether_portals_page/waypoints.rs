@@ -0,0 +1,887 @@
+use super::{
+    portal_csv::{show_submit_status, submit_new_portals},
+    portals::{NetherPortalText, NetherPortals, PortalText},
+};
+use crate::{
+    dimension::{Dimension, Dimensions},
+    eframe_tools::ModalMachine,
+    profiles::world_path,
+    thread_tools::Request,
+    windows::client_windows::GenericWindow,
+    BorkError, Realm,
+};
+use eframe::egui::{Context, Grid, ScrollArea, Ui};
+use serde_json::{json, Value};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+use tokio::runtime::Runtime;
+
+// Minecraft's 16 chat colors. Xaero's Minimap stores the index, the others store the color
+const PALETTE: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [0, 0, 170],
+    [0, 170, 0],
+    [0, 170, 170],
+    [170, 0, 0],
+    [170, 0, 170],
+    [255, 170, 0],
+    [170, 170, 170],
+    [85, 85, 85],
+    [85, 85, 255],
+    [85, 255, 85],
+    [85, 255, 255],
+    [255, 85, 85],
+    [255, 85, 255],
+    [255, 255, 85],
+    [255, 255, 255],
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WaypointFormat {
+    Xaero,
+    JourneyMap,
+    VoxelMap,
+}
+
+impl WaypointFormat {
+    const ALL: [WaypointFormat; 3] = [
+        WaypointFormat::Xaero,
+        WaypointFormat::JourneyMap,
+        WaypointFormat::VoxelMap,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            WaypointFormat::Xaero => "Xaero's Minimap",
+            WaypointFormat::JourneyMap => "JourneyMap",
+            WaypointFormat::VoxelMap => "VoxelMap",
+        }
+    }
+
+    fn from_name(name: &str) -> WaypointFormat {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name() == name)
+            .unwrap_or(WaypointFormat::Xaero)
+    }
+
+    fn modal() -> ModalMachine {
+        let options: Vec<String> = Self::ALL.iter().map(|f| f.name().to_string()).collect();
+        ModalMachine::new(options[0].clone(), options, "Format".to_string())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Waypoint {
+    pub name: String,
+    pub dimension: Dimension,
+    pub cords: (i32, i32, i32),
+    pub color: usize,
+}
+
+impl Waypoint {
+    fn realm(&self) -> Option<Realm> {
+        //! None for waypoints that are not on one of the two sides of a portal
+        Realm::from_dimension(&self.dimension)
+    }
+
+    fn rgb(&self) -> [u8; 3] {
+        PALETTE[self.color % PALETTE.len()]
+    }
+
+    fn initials(&self) -> String {
+        self.name
+            .split_whitespace()
+            .filter_map(|word| word.chars().next())
+            .take(2)
+            .collect::<String>()
+            .to_uppercase()
+    }
+}
+
+fn owner_color(owner: &str) -> usize {
+    //! The same owner always gets the same color (black is skipped, it is hard to see)
+    let mut hasher = DefaultHasher::new();
+    owner.hash(&mut hasher);
+    1 + (hasher.finish() % (PALETTE.len() as u64 - 1)) as usize
+}
+
+// Which portals end up as waypoints
+pub struct WaypointFilter {
+    pub owner: String,
+    pub name: String,
+    pub tag: String,
+    pub overworld: bool,
+    pub nether: bool,
+    // The End & custom dimensions
+    pub other_dimensions: bool,
+    pub color_by_owner: bool,
+}
+
+impl Default for WaypointFilter {
+    fn default() -> Self {
+        Self {
+            owner: String::default(),
+            name: String::default(),
+            tag: String::default(),
+            overworld: true,
+            nether: true,
+            other_dimensions: true,
+            color_by_owner: false,
+        }
+    }
+}
+
+impl WaypointFilter {
+    fn allows(&self, dimension: &Dimension, pt: &PortalText) -> bool {
+        let contains =
+            |value: &String, part: &String| value.to_lowercase().contains(&part.to_lowercase());
+        let dimension_allowed = match Realm::from_dimension(dimension) {
+            Some(realm) => realm.matcher(self.overworld, self.nether),
+            None => self.other_dimensions,
+        };
+        dimension_allowed
+            && contains(pt.owner_ref(), &self.owner)
+            && contains(pt.true_name_ref(), &self.name)
+            && (self.tag.is_empty() || pt.has_tag(&self.tag))
+    }
+}
+
+pub fn to_waypoints(
+    npts: &[NetherPortalText],
+    dimensions: &Dimensions,
+    filter: &WaypointFilter,
+) -> Vec<Waypoint> {
+    //! One waypoint for each side of each portal that the filter allows
+    let mut waypoints = Vec::new();
+    for npt in npts {
+        let mut sides: Vec<(Dimension, &PortalText)> = [Realm::Overworld, Realm::Nether]
+            .iter()
+            .map(|realm| (realm.dimension(), npt.realm_ref(realm)))
+            .collect();
+        for (dimension_id, pt) in npt.sides_ref() {
+            // A side in a dimension that was removed still gets exported under its id
+            sides.push((dimensions.get_or_unknown(dimension_id), pt));
+        }
+
+        for (dimension, pt) in sides {
+            if !filter.allows(&dimension, pt) {
+                continue;
+            }
+            let color = match (filter.color_by_owner, Realm::from_dimension(&dimension)) {
+                (true, _) => owner_color(pt.owner_ref()),
+                (false, Some(realm)) => realm.matcher(10, 12),
+                (false, None) => 13,
+            };
+            waypoints.push(Waypoint {
+                name: pt.true_name_ref().clone(),
+                dimension,
+                cords: pt.cords(),
+                color,
+            });
+        }
+    }
+    waypoints
+}
+
+// Xaero's Minimap
+fn xaero_dim(dimension: &Dimension) -> String {
+    //! Vanilla dimensions are numbered, modded ones are "dim%namespace$path"
+    match dimension.id.as_str() {
+        "minecraft:overworld" => "dim%0".to_string(),
+        "minecraft:the_nether" => "dim%-1".to_string(),
+        "minecraft:the_end" => "dim%1".to_string(),
+        id => format!("dim%{}", id.replace(':', "$")),
+    }
+}
+
+fn xaero_dimension(folder: &str) -> Option<Dimension> {
+    //! The other way around from xaero_dim; None when (folder) is not a "dim%" folder
+    let id = folder.strip_prefix("dim%")?;
+    let dimension = match id {
+        "0" => Dimension::overworld(),
+        "-1" => Dimension::nether(),
+        "1" => Dimension::the_end(),
+        id => {
+            // Old modded dimensions are numbered, newer ones are "namespace$path"
+            let id = match id.contains('$') {
+                true => id.replace('$', ":"),
+                false => format!("xaero:dim{}", id),
+            };
+            Dimension::new(&id, &id, 1.0, -64, 319)
+        }
+    };
+    Some(dimension)
+}
+
+pub fn xaero_lines(waypoints: &[&Waypoint]) -> String {
+    let mut lines = vec![
+        "#".to_string(),
+        "#waypoint:name:initials:x:y:z:color:disabled:type:set:rotate_on_tp:tp_yaw:visibility_type:destination".to_string(),
+        "#".to_string(),
+    ];
+    for waypoint in waypoints {
+        let (x, y, z) = waypoint.cords;
+        // Xaero's escapes ':' as "§§"
+        lines.push(format!(
+            "waypoint:{}:{}:{}:{}:{}:{}:false:0:gui.xaero_default:false:0:0:false",
+            waypoint.name.replace(':', "§§"),
+            waypoint.initials().replace(':', "§§"),
+            x,
+            y,
+            z,
+            waypoint.color
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+pub fn parse_xaero(text: &str, dimension: &Dimension) -> Vec<Waypoint> {
+    text.lines()
+        .filter(|line| line.starts_with("waypoint:"))
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split(':').collect();
+            let cord = |index: usize| parts.get(index)?.parse::<i32>().ok();
+            Some(Waypoint {
+                name: parts.get(1)?.replace("§§", ":"),
+                dimension: dimension.clone(),
+                // Xaero's writes '~' when it does not know the height
+                cords: (cord(3)?, cord(4).unwrap_or(64), cord(5)?),
+                color: cord(6).unwrap_or(0) as usize,
+            })
+        })
+        .collect()
+}
+
+// JourneyMap
+pub fn journeymap_json(waypoint: &Waypoint) -> Value {
+    let (x, y, z) = waypoint.cords;
+    let [r, g, b] = waypoint.rgb();
+    json!({
+        "id": format!("{}_{},{},{}", waypoint.name, x, y, z),
+        "name": waypoint.name,
+        "icon": "waypoint-normal.png",
+        "x": x,
+        "y": y,
+        "z": z,
+        "r": r,
+        "g": g,
+        "b": b,
+        "enable": true,
+        "type": "Normal",
+        "origin": "BorkCraft",
+        "dimensions": [waypoint.dimension.id],
+        "persistent": true,
+    })
+}
+
+fn realm_from_dimension(dimension: &Value) -> Option<Realm> {
+    //! Older JourneyMaps use numbers, newer ones use names
+    match dimension {
+        Value::Number(number) => match number.as_i64()? {
+            0 => Some(Realm::Overworld),
+            -1 => Some(Realm::Nether),
+            _ => None,
+        },
+        Value::String(name) => match name.trim_start_matches("minecraft:") {
+            "overworld" => Some(Realm::Overworld),
+            "the_nether" => Some(Realm::Nether),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn parse_journeymap(text: &str) -> Result<Vec<Waypoint>, BorkError> {
+    //! One waypoint per dimension it is shown in
+    let value: Value = serde_json::from_str(text)?;
+    let cord = |key: &str| value[key].as_i64().unwrap_or_default() as i32;
+    let name = value["name"].as_str().unwrap_or_default().to_string();
+    let rgb = [cord("r"), cord("g"), cord("b")];
+
+    let waypoints = value["dimensions"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(realm_from_dimension)
+        .map(|realm| Waypoint {
+            name: name.clone(),
+            dimension: realm.dimension(),
+            cords: (cord("x"), cord("y"), cord("z")),
+            color: nearest_color(rgb),
+        })
+        .collect();
+
+    Ok(waypoints)
+}
+
+fn nearest_color(rgb: [i32; 3]) -> usize {
+    let distance = |color: &[u8; 3]| -> i32 {
+        (0..3)
+            .map(|i| (color[i] as i32 - rgb[i]).pow(2))
+            .sum::<i32>()
+    };
+    (0..PALETTE.len())
+        .min_by_key(|index| distance(&PALETTE[*index]))
+        .unwrap_or_default()
+}
+
+// VoxelMap
+pub fn voxelmap_lines(waypoints: &[&Waypoint]) -> String {
+    let mut lines = vec![
+        "subworlds:".to_string(),
+        "oldNorthWorlds:".to_string(),
+        "seeds:".to_string(),
+    ];
+    for waypoint in waypoints {
+        let (x, y, z) = waypoint.cords;
+        let [r, g, b] = waypoint.rgb();
+        // VoxelMap escapes ',' and ':' with look-alike characters
+        let name = waypoint.name.replace(',', "﹐").replace(':', "˸");
+        lines.push(format!(
+            "name:{},x:{},z:{},y:{},enabled:true,red:{:.3},green:{:.3},blue:{:.3},suffix:,world:,dimensions:{}#",
+            name,
+            x,
+            z,
+            y,
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            waypoint.dimension.path()
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+pub fn parse_voxelmap(text: &str) -> Vec<Waypoint> {
+    text.lines()
+        .filter(|line| line.starts_with("name:"))
+        .flat_map(|line| {
+            let value = |key: &str| -> Option<String> {
+                line.split(',')
+                    .find_map(|part| part.strip_prefix(&format!("{}:", key)))
+                    .map(|value| value.to_string())
+            };
+            let cord = |key: &str| value(key)?.parse::<i32>().ok();
+            let shade = |key: &str| -> i32 {
+                let shade = value(key).and_then(|s| s.parse::<f32>().ok());
+                (shade.unwrap_or_default() * 255.0) as i32
+            };
+
+            let name = value("name")
+                .unwrap_or_default()
+                .replace('﹐', ",")
+                .replace('˸', ":");
+            let cords = match (cord("x"), cord("y"), cord("z")) {
+                (Some(x), Some(y), Some(z)) => Some((x, y, z)),
+                _ => None,
+            };
+            let color = nearest_color([shade("red"), shade("green"), shade("blue")]);
+
+            value("dimensions")
+                .unwrap_or_default()
+                .split('#')
+                .filter_map(|dimension| realm_from_dimension(&Value::from(dimension)))
+                .filter_map(|realm| {
+                    Some(Waypoint {
+                        name: name.clone(),
+                        dimension: realm.dimension(),
+                        cords: cords?,
+                        color,
+                    })
+                })
+                .collect::<Vec<Waypoint>>()
+        })
+        .collect()
+}
+
+pub fn export_waypoints(
+    waypoints: &[Waypoint],
+    format: WaypointFormat,
+    dir: &Path,
+) -> Result<usize, BorkError> {
+    //! Write (waypoints) into (dir) the way (format) stores them. Returns how many files were written
+    // Waypoints grouped by dimension, in the order the dimensions first show up
+    let mut by_dimension: Vec<(&Dimension, Vec<&Waypoint>)> = Vec::new();
+    for waypoint in waypoints {
+        match by_dimension
+            .iter_mut()
+            .find(|(dimension, _)| dimension.id == waypoint.dimension.id)
+        {
+            Some((_, group)) => group.push(waypoint),
+            None => by_dimension.push((&waypoint.dimension, vec![waypoint])),
+        }
+    }
+    let mut files = 0;
+
+    match format {
+        WaypointFormat::Xaero => {
+            // One folder per dimension
+            for (dimension, waypoints) in by_dimension {
+                let dimension_dir = dir.join(xaero_dim(dimension));
+                fs::create_dir_all(&dimension_dir)?;
+                fs::write(
+                    dimension_dir.join("mw$default_1.txt"),
+                    xaero_lines(&waypoints),
+                )?;
+                files += 1;
+            }
+        }
+        WaypointFormat::JourneyMap => {
+            // One file per waypoint, its dimension is inside of it
+            let waypoint_dir = dir.join("waypoints");
+            fs::create_dir_all(&waypoint_dir)?;
+            for waypoint in waypoints {
+                let json = journeymap_json(waypoint);
+                let file_name = format!("{}.json", json["id"].as_str().unwrap_or_default());
+                let file_name = file_name.replace(['/', '\\', ':'], "_");
+                fs::write(
+                    waypoint_dir.join(file_name),
+                    serde_json::to_vec_pretty(&json)?,
+                )?;
+                files += 1;
+            }
+        }
+        WaypointFormat::VoxelMap => {
+            // One file, every line says which dimension it belongs to
+            fs::create_dir_all(dir)?;
+            let sorted: Vec<&Waypoint> = by_dimension
+                .into_iter()
+                .flat_map(|(_, group)| group)
+                .collect();
+            fs::write(dir.join("borkcraft.points"), voxelmap_lines(&sorted))?;
+            files += 1;
+        }
+    }
+
+    Ok(files)
+}
+
+fn waypoint_paths(path: &Path) -> Result<Vec<PathBuf>, BorkError> {
+    //! (path) itself, or every file under it (Xaero's keeps a folder per dimension)
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(path)? {
+        paths.extend(waypoint_paths(&entry?.path())?);
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn xaero_file_dimension(path: &Path) -> Option<Dimension> {
+    //! The closest "dim%" folder (path) is in; a file outside of one is taken as the overworld
+    let folders: Vec<String> = path
+        .parent()?
+        .components()
+        .map(|folder| folder.as_os_str().to_string_lossy().to_string())
+        .collect();
+    match folders
+        .iter()
+        .rev()
+        .find(|folder| folder.starts_with("dim%"))
+    {
+        Some(folder) => xaero_dimension(folder),
+        None => Some(Dimension::overworld()),
+    }
+}
+
+// Waypoints that were read, & the files that were skipped (with why)
+type WaypointFiles = (Vec<Waypoint>, Vec<String>);
+
+fn read_waypoint_files(path: &Path, format: WaypointFormat) -> Result<WaypointFiles, BorkError> {
+    //! (path) can be a single file or a folder (JourneyMap keeps one file per waypoint)
+    //!
+    //! One bad file does not stop the others from being read
+    let mut waypoints = Vec::new();
+    let mut skipped = Vec::new();
+    for path in waypoint_paths(path)? {
+        let name = path.to_string_lossy().to_string();
+        // The folder tells us the dimension: dim%-1 == nether, dim%1 == the end
+        let dimension = match format {
+            WaypointFormat::Xaero => match xaero_file_dimension(&path) {
+                Some(dimension) if Realm::from_dimension(&dimension).is_some() => Some(dimension),
+                Some(dimension) => {
+                    skipped.push(format!(
+                        "|{}|: |{}| has no portal pairs",
+                        name, dimension.name
+                    ));
+                    continue;
+                }
+                None => None,
+            },
+            _ => None,
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => {
+                skipped.push(format!("|{}|: {}", name, BorkError::from(err)));
+                continue;
+            }
+        };
+        match format {
+            WaypointFormat::Xaero => waypoints.extend(parse_xaero(
+                &text,
+                &dimension.unwrap_or_else(Dimension::overworld),
+            )),
+            WaypointFormat::JourneyMap => match parse_journeymap(&text) {
+                Ok(read) => waypoints.extend(read),
+                Err(err) => skipped.push(format!("|{}|: {}", name, err)),
+            },
+            WaypointFormat::VoxelMap => waypoints.extend(parse_voxelmap(&text)),
+        }
+    }
+
+    Ok((waypoints, skipped))
+}
+
+pub fn waypoint_to_npt(waypoint: &Waypoint, realm: Realm) -> NetherPortalText {
+    //! A new portal; the side the waypoint is not in is worked out with the 8:1 ratio
+    let other = realm.other();
+    let here = PortalText::build(waypoint.name.clone(), waypoint.cords);
+    let there = PortalText::build(
+        waypoint.name.clone(),
+        realm.convert_cords(&other, waypoint.cords),
+    );
+    let (overworld, nether) = match realm {
+        Realm::Overworld => (here, there),
+        Realm::Nether => (there, here),
+    };
+    NetherPortalText::build_from(0, overworld, nether)
+}
+
+pub struct WaypointTransfer {
+    format: ModalMachine,
+    filter: WaypointFilter,
+    export_dir: String,
+    import_path: String,
+    // Waypoints that were read but not submitted yet, and if they will be submitted
+    proposals: Vec<(bool, Realm, Waypoint)>,
+    message: String,
+    submit_request: Request<(), BorkError>,
+    pub display: GenericWindow,
+}
+
+impl Default for WaypointTransfer {
+    fn default() -> Self {
+        Self {
+            format: WaypointFormat::modal(),
+            filter: WaypointFilter::default(),
+            export_dir: world_path("waypoints"),
+            import_path: String::default(),
+            proposals: Vec::new(),
+            message: String::default(),
+            submit_request: Request::default(),
+            display: GenericWindow::new("Waypoints"),
+        }
+    }
+}
+
+impl WaypointTransfer {
+    fn selected_format(&self) -> WaypointFormat {
+        WaypointFormat::from_name(&self.format.get_selected_option())
+    }
+
+    pub fn export(&mut self, nether_portals: &NetherPortals) -> Result<(), BorkError> {
+        //! Every loaded portal that passes the filter, written into (export_dir)
+        let waypoints = to_waypoints(
+            &nether_portals.to_npts(),
+            nether_portals.dimensions_ref(),
+            &self.filter,
+        );
+        if waypoints.is_empty() {
+            return Err(BorkError::validation("No portals pass the filter"));
+        }
+        let files = export_waypoints(
+            &waypoints,
+            self.selected_format(),
+            Path::new(&self.export_dir),
+        )?;
+        self.message = format!(
+            "Wrote {} waypoint(s) into {} file(s)",
+            waypoints.len(),
+            files
+        );
+
+        Ok(())
+    }
+
+    pub fn import(&mut self, nether_portals: &NetherPortals) -> Result<(), BorkError> {
+        //! Read waypoints from (import_path); the ones that are already portals start unchecked
+        let (waypoints, skipped) =
+            read_waypoint_files(Path::new(&self.import_path), self.selected_format())?;
+        // Only waypoints in the overworld or the nether can become portals
+        self.proposals = waypoints
+            .into_iter()
+            .filter_map(|waypoint| {
+                let realm = waypoint.realm()?;
+                let exists = nether_portals.has_true_name(&realm, &waypoint.name);
+                Some((!exists, realm, waypoint))
+            })
+            .collect();
+        self.message = format!("Read {} waypoint(s)", self.proposals.len());
+        if !skipped.is_empty() {
+            self.message = format!(
+                "{}, skipped {} file(s):\n{}",
+                self.message,
+                skipped.len(),
+                skipped.join("\n")
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn submit(&mut self, runtime: &Runtime, ctx: &Context) -> Result<(), BorkError> {
+        let npts: Vec<NetherPortalText> = self
+            .proposals
+            .iter()
+            .filter(|(checked, _, _)| *checked)
+            .map(|(_, realm, waypoint)| waypoint_to_npt(waypoint, *realm))
+            .collect();
+        if npts.is_empty() {
+            return Err(BorkError::validation("No waypoints are checked"));
+        }
+
+        submit_new_portals(&mut self.submit_request, npts, runtime, ctx);
+
+        Ok(())
+    }
+
+    fn show_filter(&mut self, ui: &mut Ui) {
+        Grid::new("waypoint filter").show(ui, |ui| {
+            ui.label("Owner contains:");
+            ui.text_edit_singleline(&mut self.filter.owner);
+            ui.end_row();
+            ui.label("Name contains:");
+            ui.text_edit_singleline(&mut self.filter.name);
+            ui.end_row();
+            ui.label("Has tag:");
+            ui.text_edit_singleline(&mut self.filter.tag);
+            ui.end_row();
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.filter.overworld, "Overworld");
+            ui.checkbox(&mut self.filter.nether, "Nether");
+            ui.checkbox(&mut self.filter.other_dimensions, "Other dimensions");
+            ui.checkbox(&mut self.filter.color_by_owner, "Color by owner");
+        });
+    }
+
+    fn show_proposals(&mut self, ui: &mut Ui) {
+        ScrollArea::vertical()
+            .id_source("waypoint proposals")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                Grid::new("waypoint proposals")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Add");
+                        ui.strong("Name");
+                        ui.strong("Realm");
+                        ui.strong("Cords");
+                        ui.strong("Other Side");
+                        ui.end_row();
+
+                        self.proposals
+                            .iter_mut()
+                            .for_each(|(checked, realm, waypoint)| {
+                                let other = realm.other();
+                                ui.checkbox(checked, "");
+                                ui.label(&waypoint.name);
+                                ui.label(realm.name());
+                                ui.label(format!("{:?}", waypoint.cords));
+                                ui.label(format!(
+                                    "{} {:?}",
+                                    other.name(),
+                                    realm.convert_cords(&other, waypoint.cords)
+                                ));
+                                ui.end_row();
+                            });
+                    });
+            });
+    }
+
+    fn show(
+        &mut self,
+        nether_portals: &NetherPortals,
+        runtime: &Runtime,
+        ui: &mut Ui,
+    ) -> Result<(), BorkError> {
+        let mut result = Ok(());
+        self.format.modal_machine(0, ui);
+        ui.separator();
+
+        // Export
+        ui.strong("Export");
+        self.show_filter(ui);
+        ui.horizontal(|ui| {
+            ui.label("Save to folder:");
+            ui.text_edit_singleline(&mut self.export_dir);
+            if ui.button("Export Waypoints").clicked() {
+                result = self.export(nether_portals);
+            }
+        });
+        ui.separator();
+
+        // Import
+        ui.strong("Import");
+        ui.horizontal(|ui| {
+            ui.label("File or folder:");
+            ui.text_edit_singleline(&mut self.import_path);
+            if ui.button("Read Waypoints").clicked() {
+                result = self.import(nether_portals);
+            }
+        });
+        if !self.proposals.is_empty() {
+            ui.label("Waypoints with a name that is already a portal start unchecked");
+            self.show_proposals(ui);
+            ui.horizontal(|ui| {
+                if ui.button("Add Checked As Portals").clicked() {
+                    result = self.submit(runtime, ui.ctx());
+                }
+                show_submit_status(&mut self.submit_request, ui);
+            });
+        }
+
+        if !self.message.is_empty() {
+            ui.separator();
+            ui.label(&self.message);
+        }
+
+        result
+    }
+}
+
+pub fn waypoint_window(
+    nether_portals: &mut NetherPortals,
+    runtime: &Runtime,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! Shows the "Waypoints" window (if its open)
+    let mut transfer = std::mem::take(nether_portals.waypoints_mut());
+    let mut display = std::mem::take(&mut transfer.display);
+
+    let mut result = Ok(());
+    display.show_with(ctx, |ui| {
+        result = transfer.show(nether_portals, runtime, ui);
+    });
+
+    transfer.display = display;
+    *nether_portals.waypoints_mut() = transfer;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("borkcraft-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn xaero_lines_are_read_into_the_given_dimension() {
+        let text = "#\n#waypoint:name:initials:x:y:z:color\n#\n\
+            waypoint:Home§§Base:H:100:64:-200:6:false:0:gui.xaero_default:false:0:0:false\n\
+            waypoint:Cave:C:5:~:7:2:false:0:gui.xaero_default:false:0:0:false\n\
+            waypoint:Broken:B:x:1:2:0:false\n";
+        let waypoints = parse_xaero(text, &Dimension::nether());
+        assert_eq!(waypoints.len(), 2);
+        assert_eq!(waypoints[0].name, "Home:Base");
+        assert_eq!(waypoints[0].cords, (100, 64, -200));
+        assert_eq!(waypoints[0].color, 6);
+        assert_eq!(waypoints[0].dimension, Dimension::nether());
+        // Unknown height
+        assert_eq!(waypoints[1].cords, (5, 64, 7));
+    }
+
+    #[test]
+    fn xaero_folders_map_to_their_dimension() {
+        assert_eq!(xaero_dimension("dim%0"), Some(Dimension::overworld()));
+        assert_eq!(xaero_dimension("dim%-1"), Some(Dimension::nether()));
+        assert_eq!(xaero_dimension("dim%1"), Some(Dimension::the_end()));
+        assert_eq!(
+            xaero_dimension("dim%twilightforest$twilight_forest").map(|d| d.id),
+            Some("twilightforest:twilight_forest".to_string())
+        );
+        assert_eq!(xaero_dimension("waypoints"), None);
+        // Every dimension makes it back from the folder it is exported to
+        for dimension in [
+            Dimension::overworld(),
+            Dimension::nether(),
+            Dimension::the_end(),
+        ] {
+            assert_eq!(xaero_dimension(&xaero_dim(&dimension)), Some(dimension));
+        }
+    }
+
+    #[test]
+    fn end_and_modded_xaero_folders_are_skipped() {
+        let dir = temp_dir("xaero");
+        let line = "waypoint:Spot:S:8:70:16:1:false:0:gui.xaero_default:false:0:0:false\n";
+        for folder in ["dim%0", "dim%-1", "dim%1", "dim%aether$the_aether"] {
+            fs::create_dir_all(dir.join(folder)).unwrap();
+            fs::write(dir.join(folder).join("mw$default_1.txt"), line).unwrap();
+        }
+
+        let (waypoints, skipped) = read_waypoint_files(&dir, WaypointFormat::Xaero).unwrap();
+        let realms: Vec<Option<Realm>> = waypoints.iter().map(|w| w.realm()).collect();
+        assert_eq!(realms.len(), 2);
+        assert!(realms.contains(&Some(Realm::Overworld)));
+        assert!(realms.contains(&Some(Realm::Nether)));
+        assert_eq!(skipped.len(), 2, "{:?}", skipped);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn journeymap_gets_a_waypoint_per_portal_dimension() {
+        let text = r#"{"name":"Base","x":1,"y":2,"z":3,"r":255,"g":0,"b":0,
+            "dimensions":["minecraft:the_nether", 0, "minecraft:the_end"]}"#;
+        let waypoints = parse_journeymap(text).unwrap();
+        let realms: Vec<Option<Realm>> = waypoints.iter().map(|w| w.realm()).collect();
+        assert_eq!(realms, vec![Some(Realm::Nether), Some(Realm::Overworld)]);
+        assert_eq!(waypoints[0].cords, (1, 2, 3));
+        assert_eq!(PALETTE[waypoints[0].color], [170, 0, 0]);
+        assert!(parse_journeymap("{not json").is_err());
+    }
+
+    #[test]
+    fn one_bad_journeymap_file_does_not_stop_the_import() {
+        let dir = temp_dir("journeymap");
+        fs::write(
+            dir.join("good.json"),
+            r#"{"name":"Good","x":1,"y":2,"z":3,"dimensions":["minecraft:overworld"]}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("bad.json"), "{ truncated").unwrap();
+
+        let (waypoints, skipped) = read_waypoint_files(&dir, WaypointFormat::JourneyMap).unwrap();
+        assert_eq!(waypoints.len(), 1);
+        assert_eq!(waypoints[0].name, "Good");
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("bad.json"));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn voxelmap_lines_are_read() {
+        let text = "subworlds:\nname:Far﹐Away,x:10,z:20,y:64,enabled:true,red:1.0,green:0.0,blue:0.0,suffix:,world:,dimensions:overworld#the_nether#\n\
+            name:NoCords,x:1,enabled:true,dimensions:overworld#\n";
+        let waypoints = parse_voxelmap(text);
+        assert_eq!(waypoints.len(), 2);
+        assert_eq!(waypoints[0].name, "Far,Away");
+        assert_eq!(waypoints[0].cords, (10, 64, 20));
+        assert_eq!(waypoints[1].realm(), Some(Realm::Nether));
+    }
+}
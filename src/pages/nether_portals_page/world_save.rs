@@ -0,0 +1,607 @@
+use super::{
+    portal_csv::{show_submit_status, submit_new_portals},
+    portals::{NetherPortalText, NetherPortals, PortalText},
+};
+use crate::{
+    eframe_tools::request_status,
+    nbt::{self, Tag},
+    thread_tools::{Request, Responder},
+    windows::client_windows::GenericWindow,
+    BorkError, Realm,
+};
+use eframe::egui::{Context, Grid, ScrollArea, Ui};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+use tokio::runtime::Runtime;
+
+const PORTAL_BLOCK: &str = "minecraft:nether_portal";
+// A nether portal looks for a partner this many blocks away (in the nether)
+const PAIR_RADIUS: i32 = 16;
+// Closer than this to a portal that is already loaded == the same portal
+pub const KNOWN_RADIUS: i32 = 8;
+
+type Cords = (i32, i32, i32);
+
+pub struct WorldInfo {
+    pub name: String,
+    pub data_version: i64,
+}
+
+fn read_level_dat(world: &Path) -> Result<WorldInfo, BorkError> {
+    //! level.dat is a gzipped NBT file; the world's name is in Data.LevelName
+    let mut bytes = Vec::new();
+    GzDecoder::new(fs::File::open(world.join("level.dat"))?).read_to_end(&mut bytes)?;
+    let root = nbt::parse(&bytes)?;
+    let data = root
+        .get("Data")
+        .ok_or_else(|| BorkError::decode("level.dat has no Data"))?;
+
+    Ok(WorldInfo {
+        name: data
+            .get("LevelName")
+            .and_then(|name| name.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        data_version: data
+            .get("DataVersion")
+            .and_then(|version| version.as_i64())
+            .unwrap_or_default(),
+    })
+}
+
+fn region_dir(world: &Path, realm: &Realm) -> PathBuf {
+    realm.matcher(world.join("region"), world.join("DIM-1").join("region"))
+}
+
+fn read_chunks(region: &[u8]) -> (Vec<Tag>, usize) {
+    //! Every chunk in an Anvil region file, and how many chunks could not be read
+    //!
+    //! The first 4KiB says where each of the 32x32 chunks is; 3 bytes of offset & 1 byte of size, in 4KiB sectors
+    let mut chunks = Vec::new();
+    let mut failed = 0;
+
+    for index in 0..1024 {
+        let location = match region.get(index * 4..index * 4 + 4) {
+            Some(location) => location,
+            None => break,
+        };
+        let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize * 4096;
+        if offset == 0 {
+            continue;
+        }
+
+        let chunk = || -> Result<Tag, BorkError> {
+            let header = region
+                .get(offset..offset + 5)
+                .ok_or_else(|| BorkError::decode("Chunk is outside of the region file"))?;
+            let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            let compressed = region
+                .get(offset + 5..offset + 4 + length)
+                .ok_or_else(|| BorkError::decode("Chunk is outside of the region file"))?;
+
+            let mut bytes = Vec::new();
+            match header[4] {
+                1 => GzDecoder::new(compressed).read_to_end(&mut bytes)?,
+                2 => ZlibDecoder::new(compressed).read_to_end(&mut bytes)?,
+                3 => {
+                    bytes.extend_from_slice(compressed);
+                    bytes.len()
+                }
+                kind => {
+                    return Err(BorkError::decode(&format!(
+                        "Unsupported chunk compression: |{}|",
+                        kind
+                    )))
+                }
+            };
+            nbt::parse(&bytes)
+        };
+
+        match chunk() {
+            Ok(chunk) => chunks.push(chunk),
+            Err(_) => failed += 1,
+        }
+    }
+
+    (chunks, failed)
+}
+
+fn section_portals(section: &Tag, chunk_x: i32, chunk_z: i32, blocks: &mut Vec<Cords>) {
+    //! Find the portal blocks in a 16x16x16 chunk section
+    //!
+    //! 1.18+ keeps the palette in "block_states", 1.13-1.17 keeps it right in the section
+    let states = section.get("block_states");
+    let palette = states
+        .and_then(|states| states.get("palette"))
+        .or_else(|| section.get("Palette"))
+        .and_then(|palette| palette.as_list());
+    let palette = match palette {
+        Some(palette) => palette,
+        None => return,
+    };
+    let portal_ids: Vec<u64> = palette
+        .iter()
+        .enumerate()
+        .filter(|(_, block)| block.get("Name").and_then(|n| n.as_str()) == Some(PORTAL_BLOCK))
+        .map(|(id, _)| id as u64)
+        .collect();
+    if portal_ids.is_empty() {
+        return;
+    }
+
+    let section_y = section
+        .get("Y")
+        .and_then(|y| y.as_i64())
+        .unwrap_or_default() as i32;
+    let data = states
+        .and_then(|states| states.get("data"))
+        .or_else(|| section.get("BlockStates"))
+        .and_then(|data| data.as_longs());
+
+    // At least 4 bits per block; before 1.16 a block could be split between two longs
+    let bits = (usize::BITS - (palette.len() - 1).leading_zeros()).max(4) as usize;
+    let per_long = 64 / bits;
+    let mask = (1u64 << bits) - 1;
+    let spanning = data.is_some_and(|data| data.len() < 4096_usize.div_ceil(per_long));
+
+    for index in 0..4096 {
+        let id = match data {
+            // A palette with one block has no data, every block is that block
+            None => 0,
+            Some(data) if spanning => {
+                let bit = index * bits;
+                let (long, offset) = (bit / 64, bit % 64);
+                let mut id = *data.get(long).unwrap_or(&0) as u64 >> offset;
+                if offset + bits > 64 {
+                    id |= (*data.get(long + 1).unwrap_or(&0) as u64) << (64 - offset);
+                }
+                id & mask
+            }
+            Some(data) => {
+                let long = *data.get(index / per_long).unwrap_or(&0) as u64;
+                (long >> ((index % per_long) * bits)) & mask
+            }
+        };
+        if portal_ids.contains(&id) {
+            blocks.push((
+                chunk_x * 16 + (index % 16) as i32,
+                section_y * 16 + (index / 256) as i32,
+                chunk_z * 16 + ((index / 16) % 16) as i32,
+            ));
+        }
+    }
+}
+
+fn chunk_portals(chunk: &Tag, blocks: &mut Vec<Cords>) {
+    // Before 1.18 everything was inside of "Level"
+    let root = chunk.get("Level").unwrap_or(chunk);
+    let position = |name: &str| root.get(name).and_then(|n| n.as_i64());
+    let (chunk_x, chunk_z) = match (position("xPos"), position("zPos")) {
+        (Some(x), Some(z)) => (x as i32, z as i32),
+        _ => return,
+    };
+    let sections = root
+        .get("sections")
+        .or_else(|| root.get("Sections"))
+        .and_then(|sections| sections.as_list());
+
+    sections
+        .into_iter()
+        .flatten()
+        .for_each(|section| section_portals(section, chunk_x, chunk_z, blocks));
+}
+
+// Portal blocks that touch each other
+#[derive(Clone, Debug)]
+pub struct PortalFrame {
+    pub realm: Realm,
+    pub min: Cords,
+    pub max: Cords,
+    pub blocks: usize,
+}
+
+fn cluster(realm: Realm, blocks: Vec<Cords>) -> Vec<PortalFrame> {
+    //! Flood fill the portal blocks into frames
+    let mut remaining: HashSet<Cords> = blocks.into_iter().collect();
+    let mut frames = Vec::new();
+
+    while let Some(start) = remaining.iter().next().copied() {
+        remaining.remove(&start);
+        let mut queue = VecDeque::from([start]);
+        let mut frame = PortalFrame {
+            realm,
+            min: start,
+            max: start,
+            blocks: 0,
+        };
+
+        while let Some((x, y, z)) = queue.pop_front() {
+            frame.blocks += 1;
+            frame.min = (frame.min.0.min(x), frame.min.1.min(y), frame.min.2.min(z));
+            frame.max = (frame.max.0.max(x), frame.max.1.max(y), frame.max.2.max(z));
+            let neighbours = [
+                (x + 1, y, z),
+                (x - 1, y, z),
+                (x, y + 1, z),
+                (x, y - 1, z),
+                (x, y, z + 1),
+                (x, y, z - 1),
+            ];
+            for neighbour in neighbours {
+                if remaining.remove(&neighbour) {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        frames.push(frame);
+    }
+
+    frames.sort_by_key(|frame| frame.min);
+    frames
+}
+
+#[derive(Default)]
+pub struct WorldScan {
+    pub name: String,
+    pub data_version: i64,
+    pub frames: Vec<PortalFrame>,
+    pub chunks: usize,
+    pub failed: usize,
+}
+
+fn region_files(world: &Path, realm: &Realm) -> Result<Vec<PathBuf>, BorkError> {
+    let dir = region_dir(world, realm);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("mca") {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+pub fn scan_world(
+    world: &Path,
+    responder: &Responder<WorldScan, BorkError>,
+) -> Result<WorldScan, BorkError> {
+    //! Read every region file of the overworld & nether and find every portal frame
+    //!
+    //! Stops between region files once the scan is cancelled
+    let info = read_level_dat(world)?;
+    let mut scan = WorldScan {
+        name: info.name,
+        data_version: info.data_version,
+        ..Default::default()
+    };
+
+    let realms = [Realm::Overworld, Realm::Nether];
+    let files = realms
+        .iter()
+        .map(|realm| region_files(world, realm))
+        .collect::<Result<Vec<Vec<PathBuf>>, BorkError>>()?;
+    let total = files.iter().map(|files| files.len()).sum::<usize>().max(1);
+    let mut done = 0;
+
+    for (realm, files) in realms.into_iter().zip(files) {
+        let mut blocks = Vec::new();
+        for path in files {
+            responder.check_cancelled()?;
+            let (chunks, failed) = read_chunks(&fs::read(path)?);
+            scan.chunks += chunks.len();
+            scan.failed += failed;
+            chunks
+                .iter()
+                .for_each(|chunk| chunk_portals(chunk, &mut blocks));
+            done += 1;
+            responder.progress(done as f32 / total as f32);
+        }
+        scan.frames.extend(cluster(realm, blocks));
+    }
+
+    Ok(scan)
+}
+
+// A portal found in the world, waiting to be reviewed
+pub struct FoundPortal {
+    pub add: bool,
+    pub name: String,
+    pub overworld: Cords,
+    pub nether: Cords,
+    pub note: String,
+}
+
+pub fn distance(a: Cords, b: Cords) -> i32 {
+    (a.0 - b.0)
+        .abs()
+        .max((a.1 - b.1).abs())
+        .max((a.2 - b.2).abs())
+}
+
+pub fn is_known(known: &[NetherPortalText], realm: &Realm, cords: Cords) -> bool {
+    known
+        .iter()
+        .any(|npt| distance(npt.realm_ref(realm).cords(), cords) <= KNOWN_RADIUS)
+}
+
+pub fn pair_frames(scan: &WorldScan, known: &[NetherPortalText]) -> Vec<FoundPortal> {
+    //! Match overworld frames with nether frames; a frame without a partner gets a worked out one
+    let (overworld, mut nether): (Vec<&PortalFrame>, Vec<&PortalFrame>) = scan
+        .frames
+        .iter()
+        .partition(|frame| frame.realm == Realm::Overworld);
+    let mut found = Vec::new();
+
+    for frame in overworld {
+        let target = Realm::Overworld.convert_cords(&Realm::Nether, frame.min);
+        let partner = nether
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| distance(other.min, target) <= PAIR_RADIUS)
+            .min_by_key(|(_, other)| distance(other.min, target))
+            .map(|(index, _)| index);
+        let (nether_cords, note) = match partner {
+            Some(index) => (nether.remove(index).min, "Both sides found"),
+            None => (target, "Nether side worked out"),
+        };
+        found.push((frame.min, nether_cords, note));
+    }
+    for frame in nether {
+        let overworld_cords = Realm::Nether.convert_cords(&Realm::Overworld, frame.min);
+        found.push((overworld_cords, frame.min, "Overworld side worked out"));
+    }
+
+    found
+        .into_iter()
+        .enumerate()
+        .map(|(index, (overworld, nether, note))| {
+            let known = is_known(known, &Realm::Overworld, overworld)
+                || is_known(known, &Realm::Nether, nether);
+            FoundPortal {
+                add: !known,
+                name: format!("{} Portal {}", scan.name, index + 1),
+                overworld,
+                nether,
+                note: match known {
+                    true => format!("{}; already loaded", note),
+                    false => note.to_string(),
+                },
+            }
+        })
+        .collect()
+}
+
+pub struct WorldImport {
+    world_path: String,
+    scan: Request<WorldScan, BorkError>,
+    found: Vec<FoundPortal>,
+    submit_request: Request<(), BorkError>,
+    pub display: GenericWindow,
+}
+
+impl Default for WorldImport {
+    fn default() -> Self {
+        Self {
+            world_path: String::default(),
+            scan: Request::default(),
+            found: Vec::new(),
+            submit_request: Request::default(),
+            display: GenericWindow::new("World Import"),
+        }
+    }
+}
+
+impl WorldImport {
+    pub fn scan(&mut self, runtime: &Runtime, ctx: &Context) {
+        //! Scan the world folder in the background, it can take a while
+        self.found = Vec::new();
+        let world = PathBuf::from(&self.world_path);
+
+        self.scan
+            .spawn(runtime, ctx, "Scan World", move |responder| {
+                scan_world(&world, responder)
+            });
+    }
+
+    pub fn submit(&mut self, runtime: &Runtime, ctx: &Context) -> Result<(), BorkError> {
+        let npts: Vec<NetherPortalText> = self
+            .found
+            .iter()
+            .filter(|found| found.add)
+            .map(|found| {
+                NetherPortalText::build_from(
+                    0,
+                    PortalText::build(found.name.clone(), found.overworld),
+                    PortalText::build(found.name.clone(), found.nether),
+                )
+            })
+            .collect();
+        if npts.is_empty() {
+            return Err(BorkError::validation("No portals are checked"));
+        }
+
+        submit_new_portals(&mut self.submit_request, npts, runtime, ctx);
+
+        Ok(())
+    }
+
+    fn show_found(&mut self, ui: &mut Ui) {
+        ScrollArea::vertical()
+            .id_source("found portals")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                Grid::new("found portals").striped(true).show(ui, |ui| {
+                    ui.strong("Add");
+                    ui.strong("Name");
+                    ui.strong("Overworld");
+                    ui.strong("Nether");
+                    ui.strong("Note");
+                    ui.end_row();
+
+                    self.found.iter_mut().for_each(|found| {
+                        ui.checkbox(&mut found.add, "");
+                        ui.text_edit_singleline(&mut found.name);
+                        ui.label(format!("{:?}", found.overworld));
+                        ui.label(format!("{:?}", found.nether));
+                        ui.label(&found.note);
+                        ui.end_row();
+                    });
+                });
+            });
+    }
+
+    fn show(
+        &mut self,
+        nether_portals: &NetherPortals,
+        runtime: &Runtime,
+        ui: &mut Ui,
+    ) -> Result<(), BorkError> {
+        let mut result = self.scan.poll_error();
+        ui.horizontal(|ui| {
+            ui.label("World folder:");
+            ui.text_edit_singleline(&mut self.world_path);
+            if ui.button("Scan World").clicked() {
+                self.scan(runtime, ui.ctx());
+            }
+            request_status(ui, &mut self.scan);
+        });
+
+        let scan = match self.scan.ready() {
+            Some(scan) => scan,
+            None => return result,
+        };
+
+        ui.label(format!(
+            "|{}| (data version {}): {} chunk(s) read, {} unreadable, {} portal frame(s)",
+            scan.name,
+            scan.data_version,
+            scan.chunks,
+            scan.failed,
+            scan.frames.len()
+        ));
+        if self.found.is_empty() && !scan.frames.is_empty() {
+            self.found = pair_frames(scan, &nether_portals.to_npts());
+        }
+        if self.found.is_empty() {
+            return result;
+        }
+
+        ui.label("Portals near one that is already loaded start unchecked");
+        self.show_found(ui);
+        ui.horizontal(|ui| {
+            if ui.button("Add Checked As Portals").clicked() {
+                result = self.submit(runtime, ui.ctx());
+            }
+            show_submit_status(&mut self.submit_request, ui);
+        });
+
+        result
+    }
+}
+
+pub fn world_import_window(
+    nether_portals: &mut NetherPortals,
+    runtime: &Runtime,
+    ctx: &Context,
+) -> Result<(), BorkError> {
+    //! Shows the "World Import" window (if its open)
+    let mut world_import = std::mem::take(nether_portals.world_import_mut());
+    let mut display = std::mem::take(&mut world_import.display);
+
+    let mut result = Ok(());
+    display.show_with(ctx, |ui| {
+        result = world_import.show(nether_portals, runtime, ui);
+    });
+
+    world_import.display = display;
+    *nether_portals.world_import_mut() = world_import;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tests::{compound, write};
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    fn block(name: &str) -> Tag {
+        compound(vec![("Name", Tag::String(name.to_string()))])
+    }
+
+    fn chunk() -> Tag {
+        //! A 1.18 chunk at 1,-1 with two portal blocks at the bottom of section 4
+        let mut data = vec![0i64; 256];
+        // 4 bits per block; block 0 is x0 z0, block 16 is x0 z1
+        data[0] = 1;
+        data[1] = 1;
+        let section = compound(vec![
+            ("Y", Tag::Byte(4)),
+            (
+                "block_states",
+                compound(vec![
+                    (
+                        "palette",
+                        Tag::List(vec![block("minecraft:air"), block(PORTAL_BLOCK)]),
+                    ),
+                    ("data", Tag::LongArray(data)),
+                ]),
+            ),
+        ]);
+        compound(vec![
+            ("xPos", Tag::Int(1)),
+            ("zPos", Tag::Int(-1)),
+            ("sections", Tag::List(vec![section])),
+        ])
+    }
+
+    fn region() -> Vec<u8> {
+        //! Chunk 0 is in sector 2; chunk 1 says it is in sector 9, past the end of the file
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&write(&chunk())).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut region = vec![0u8; 8192];
+        region[0..4].copy_from_slice(&[0, 0, 2, 1]);
+        region[4..8].copy_from_slice(&[0, 0, 9, 1]);
+        region.extend((compressed.len() as u32 + 1).to_be_bytes());
+        region.push(2);
+        region.extend(compressed);
+        region.resize(region.len().div_ceil(4096) * 4096, 0);
+        region
+    }
+
+    #[test]
+    fn reads_region_chunks() {
+        let (chunks, failed) = read_chunks(&region());
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(failed, 1);
+
+        let mut blocks = Vec::new();
+        chunk_portals(&chunks[0], &mut blocks);
+        assert_eq!(blocks, [(16, 64, -16), (16, 64, -15)]);
+    }
+
+    #[test]
+    fn touching_blocks_are_one_frame() {
+        let frames = cluster(
+            Realm::Nether,
+            vec![(16, 64, -16), (16, 64, -15), (40, 70, 0)],
+        );
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].min, (16, 64, -16));
+        assert_eq!(frames[0].max, (16, 64, -15));
+        assert_eq!(frames[0].blocks, 2);
+        assert_eq!(frames[1].blocks, 1);
+    }
+}
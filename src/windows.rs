@@ -1,2 +1,4 @@
 pub mod client_windows;
 pub mod error_messages;
+pub mod settings;
+pub mod tasks;
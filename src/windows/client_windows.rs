@@ -1,9 +1,11 @@
-use crate::{
-    eframe_tools::{scroll_and_vert, space_vert},
-    string_tools::*,
-    time_of_day,
+use crate::{borkcraft::profiles::file_name, string_tools::*, time_of_day, BorkError};
+use eframe::egui::{Color32, ComboBox, Context, DragValue, RichText, ScrollArea, Ui};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    panic::Location,
+    path::{Path, PathBuf},
 };
-use eframe::egui::{Context, Ui};
 
 #[derive(Default)]
 pub struct GenericWindow {
@@ -68,103 +70,456 @@ impl GenericWindow {
         is_window_shut
     }
 
+    pub fn show_with(&mut self, ctx: &Context, add_contents: impl FnOnce(&mut Ui)) -> bool {
+        //! Shows the window to the screen like GenericWindow::show
+        //!
+        //! But for windows that need to change things outside of their MessageLog
+        let mut is_window_shut: bool = self.is_window_open;
+        eframe::egui::Window::new(&self.name)
+            .resizable(true)
+            .open(&mut is_window_shut)
+            .show(ctx, |ui| add_contents(ui));
+
+        self.is_window_open = is_window_shut;
+
+        is_window_shut
+    }
+
     pub fn push_loglet(&mut self, loglet: Loglet) {
-        //! Push loglet to the end of the vector
+        //! Push loglet to the end of the log; only problems pop the window open
+        let is_problem = loglet.level >= Level::Warn;
         self.log.push(loglet);
-        self.open_window();
+        if is_problem {
+            self.open_window();
+        }
     }
 
     pub fn display_generic_window(gw: &mut GenericWindow, id: i64, ui: &mut Ui, ctx: Context) {
         //! Shows to the screen GenericWindow in all its Loggy glory!
         //!
         //! Meaning... it shows it specifically with all its log properties
-        gw.show(ctx.clone(), |ui, _, log| {
-            scroll_and_vert(ui, id, |ui| {
-                Self::clear(log, ui);
-                log.show(ui);
-            })
-        });
+        let name = gw.get_name();
+        gw.show(ctx.clone(), |ui, _, log| log.show(&name, id, ui));
         gw.show_open_window_on_click_button(ui, &gw.get_name());
     }
-    fn clear(log: &mut MessageLog, ui: &mut Ui) {
-        if ui.button("Clear Window").clicked() {
-            *log = MessageLog::default();
+}
+
+// borkcraft.log (& its rotations) is kept in here, along with bug report exports
+pub const LOGS_DIR: &str = "logs";
+const DEFAULT_CAPACITY: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warn, Level::Error];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Debug => "Debug",
+            Level::Info => "Info",
+            Level::Warn => "Warn",
+            Level::Error => "Error",
+        }
+    }
+    fn color(&self, ui: &Ui) -> Color32 {
+        match self {
+            Level::Debug => Color32::GRAY,
+            Level::Info => ui.visuals().text_color(),
+            Level::Warn => ui.visuals().warn_fg_color,
+            Level::Error => ui.visuals().error_fg_color,
+        }
+    }
+}
+
+// What the log window shows
+struct LogFilter {
+    min_level: Level,
+    // Matched against the message & the source, ignoring case
+    search: String,
+    // Only errors of this BorkError kind
+    error_kind: Option<&'static str>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            min_level: Level::Debug,
+            search: String::default(),
+            error_kind: None,
         }
-        space_vert(2, ui);
     }
 }
 
-#[derive(Default)]
 pub struct MessageLog {
-    log: Vec<Loglet>,
+    // Oldest first
+    log: VecDeque<Loglet>,
+    // The oldest entries are dropped past this
+    capacity: usize,
+    filter: LogFilter,
+}
+
+impl Default for MessageLog {
+    fn default() -> Self {
+        Self {
+            log: VecDeque::new(),
+            capacity: DEFAULT_CAPACITY,
+            filter: LogFilter::default(),
+        }
+    }
 }
 
 impl MessageLog {
-    pub fn default() -> Self {
-        Default::default()
+    pub fn push(&mut self, loglet: Loglet) {
+        //! Only kept in memory; logs/borkcraft.log already gets it through tracing
+        while self.log.len() >= self.capacity.max(1) {
+            self.log.pop_front();
+        }
+        self.log.push_back(loglet)
     }
-    pub fn show(&self, ui: &mut Ui) {
+
+    fn shown(&self) -> Vec<&Loglet> {
+        //! The entries that pass the filter, newest first
+        let search = self.filter.search.to_lowercase();
         self.log
             .iter()
             .rev()
-            .enumerate()
-            .for_each(|(index, loglet)| {
-                let formated_loglet =
-                    format!("{}):{}{}", index, newliner(1), loglet.format_loglet());
-                ui.label(formated_loglet);
-                ui.label(newliner(3));
-                ui.end_row()
+            .filter(|loglet| loglet.level >= self.filter.min_level)
+            .filter(|loglet| {
+                search.is_empty()
+                    || loglet.msg.to_lowercase().contains(&search)
+                    || loglet.source.to_lowercase().contains(&search)
+            })
+            .filter(|loglet| match self.filter.error_kind {
+                Some(kind) => loglet.error.as_ref().is_some_and(|err| err.kind() == kind),
+                None => true,
             })
+            .collect()
     }
-    pub fn push(&mut self, loglet: Loglet) {
-        // Max length of the Log
-        while self.log.len() >= 30 {
-            self.log.remove(0);
+
+    pub fn export(&self, name: &str) -> Result<PathBuf, BorkError> {
+        //! Every entry (filtered out or not) in a file that can be attached to a bug report
+        std::fs::create_dir_all(LOGS_DIR)?;
+        let stamp = chrono::Utc::now().format("%Y-%m-%d_%H-%M-%S");
+        let path = Path::new(LOGS_DIR).join(format!("{}-report-{}.txt", file_name(name), stamp));
+
+        let mut report = format!(
+            "BorkCraft {} bug report\nWindow: {}\nExported: {}\nEntries: {}\n\n",
+            env!("CARGO_PKG_VERSION"),
+            name,
+            chrono::Utc::now().to_rfc3339(),
+            self.log.len()
+        );
+        for loglet in &self.log {
+            report.push_str(&loglet.format_loglet());
+            report.push('\n');
         }
-        // Push
-        self.log.push(loglet)
+        std::fs::write(&path, report)?;
+
+        Ok(path)
+    }
+
+    fn show_controls(&mut self, name: &str, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ComboBox::from_id_source(format!("{} level", name))
+                .selected_text(format!("{}+", self.filter.min_level.name()))
+                .show_ui(ui, |ui| {
+                    for level in Level::ALL {
+                        ui.selectable_value(&mut self.filter.min_level, level, level.name());
+                    }
+                });
+            ComboBox::from_id_source(format!("{} error kind", name))
+                .selected_text(self.filter.error_kind.unwrap_or("(every kind)"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.filter.error_kind, None, "(every kind)");
+                    for kind in ERROR_KINDS {
+                        ui.selectable_value(&mut self.filter.error_kind, Some(kind), kind);
+                    }
+                });
+            ui.label("Search:");
+            ui.text_edit_singleline(&mut self.filter.search);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Keep:");
+            ui.add(DragValue::new(&mut self.capacity).clamp_range(10..=10_000));
+            if ui.button("Copy Shown").clicked() {
+                let text: Vec<String> = self.shown().iter().map(|loglet| loglet.line()).collect();
+                ui.output_mut(|o| o.copied_text = text.join("\n"));
+            }
+            if ui.button("Export").clicked() {
+                let loglet = match self.export(name) {
+                    Ok(path) => Loglet::new(
+                        Level::Info,
+                        &format!("Exported to |{}|", path.to_string_lossy()),
+                    ),
+                    Err(err) => Loglet::err(err),
+                };
+                self.push(loglet);
+            }
+            if ui.button("Clear Window").clicked() {
+                self.log.clear();
+            }
+        });
+    }
+
+    pub fn show(&mut self, name: &str, id: i64, ui: &mut Ui) {
+        self.show_controls(name, ui);
+        ui.separator();
+
+        let shown = self.shown();
+        ui.label(format!("{} of {}", shown.len(), self.log.len()));
+        ScrollArea::vertical().id_source(id).show(ui, |ui| {
+            for loglet in shown {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(
+                        RichText::new(loglet.level.name())
+                            .strong()
+                            .color(loglet.level.color(ui)),
+                    );
+                    ui.label(RichText::new(&loglet.time).weak());
+                    ui.label(RichText::new(&loglet.source).weak());
+                    if let Some(err) = &loglet.error {
+                        ui.label(RichText::new(err.kind()).italics());
+                    }
+                    if ui.small_button("Copy").clicked() {
+                        ui.output_mut(|o| o.copied_text = loglet.line());
+                    }
+                });
+                ui.label(&loglet.msg);
+                ui.separator();
+            }
+        });
     }
 }
+
+// Every BorkError::kind(), for the log filter
+const ERROR_KINDS: [&str; 7] = [
+    "Network",
+    "Http",
+    "Auth",
+    "Decode",
+    "Validation",
+    "Io",
+    "Aborted",
+];
+
+#[derive(Clone)]
 pub struct Loglet {
-    kind: String,
+    level: Level,
+    // Where it came from; the file of whoever made it ("pages/login")
+    source: String,
     msg: String,
     time: String,
+    // Kept whole so the log can be filtered by what went wrong
+    error: Option<BorkError>,
+}
+
+fn source_of(location: &Location) -> String {
+    location
+        .file()
+        .trim_start_matches("src/")
+        .trim_end_matches(".rs")
+        .to_string()
 }
 
 impl Loglet {
-    pub fn new(kind: &str, msg: &str, time: &str) -> Loglet {
-        Self {
-            kind: kind.to_string(),
-            msg: msg.to_string(),
-            time: time.to_string(),
-        }
+    #[track_caller]
+    pub fn new(level: Level, msg: &str) -> Loglet {
+        Self::with_source(level, &source_of(Location::caller()), msg)
     }
-    pub fn err_s(msg: &str) -> Loglet {
+    pub fn with_source(level: Level, source: &str, msg: &str) -> Loglet {
         Self {
-            kind: "Error".to_string(),
+            level,
+            source: source.to_string(),
             msg: msg.to_string(),
             time: time_of_day(),
+            error: None,
         }
     }
-    pub fn err(err: crate::MagicError) -> Loglet {
+    #[track_caller]
+    pub fn err_s(msg: &str) -> Loglet {
+        Self::new(Level::Error, msg)
+    }
+    #[track_caller]
+    pub fn err(err: BorkError) -> Loglet {
         Self {
-            kind: "Error".to_string(),
-            msg: err.to_string(),
-            time: time_of_day(),
+            error: Some(err.clone()),
+            ..Self::new(Level::Error, &err.to_string())
         }
     }
 
+    pub fn level(&self) -> Level {
+        self.level
+    }
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+    pub fn error_ref(&self) -> Option<&BorkError> {
+        self.error.as_ref()
+    }
+
+    pub fn line(&self) -> String {
+        //! One line, for log files & the clipboard
+        format!(
+            "[{}] {} {}: {}",
+            self.time,
+            self.level.name(),
+            self.source,
+            self.msg.replace('\n', " | ")
+        )
+    }
+
     pub fn format_loglet(&self) -> String {
         let lyne = |elem: &String| -> String {
             format!("{}{}{}{}", newliner(1), tabber(1), elem, newliner(2))
         };
         let lyne_2 = "/================================================/";
+        let kind = match &self.error {
+            Some(err) if err.is_retriable() => format!(
+                "{} ({}, trying again may work)",
+                self.level.name(),
+                err.kind()
+            ),
+            Some(err) => format!("{} ({})", self.level.name(), err.kind()),
+            None => self.level.name().to_string(),
+        };
         format!(
-            "Kind:{}Message:{}Time:{}{}",
-            lyne(&self.kind),
+            "Kind:{}Source:{}Message:{}Time:{}{}",
+            lyne(&kind),
+            lyne(&self.source),
             lyne(&self.msg),
             lyne(&self.time),
             lyne_2
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> MessageLog {
+        let mut log = MessageLog::default();
+        log.push(Loglet::with_source(
+            Level::Debug,
+            "pages/login",
+            "Sent the login form",
+        ));
+        log.push(Loglet::with_source(
+            Level::Info,
+            "connection",
+            "Pinged |Text|",
+        ));
+        log.push(Loglet::with_source(
+            Level::Warn,
+            "pages/login",
+            "Slow LOGIN",
+        ));
+        log.push(Loglet::err(BorkError::Network("reset".to_string())));
+        log.push(Loglet::err(BorkError::validation("A name is required")));
+        log
+    }
+
+    fn msgs(log: &MessageLog) -> Vec<&str> {
+        log.shown().iter().map(|loglet| loglet.msg()).collect()
+    }
+
+    #[test]
+    fn filter_by_level() {
+        let mut log = log();
+        // Newest first
+        assert_eq!(msgs(&log).len(), 5);
+        assert_eq!(msgs(&log)[0], "A name is required");
+
+        log.filter.min_level = Level::Warn;
+        assert_eq!(
+            msgs(&log),
+            [
+                "A name is required",
+                "Could not reach the server: reset",
+                "Slow LOGIN"
+            ]
+        );
+        log.filter.min_level = Level::Error;
+        assert_eq!(msgs(&log).len(), 2);
+    }
+
+    #[test]
+    fn search_the_message_and_source() {
+        let mut log = log();
+        log.filter.search = "login".to_string();
+        // "Slow LOGIN" matches by its message, the debug entry by its source
+        assert_eq!(msgs(&log), ["Slow LOGIN", "Sent the login form"]);
+
+        log.filter.search = "CONNECTION".to_string();
+        assert_eq!(msgs(&log), ["Pinged |Text|"]);
+
+        log.filter.min_level = Level::Info;
+        log.filter.search = "pages/login".to_string();
+        assert_eq!(msgs(&log), ["Slow LOGIN"]);
+    }
+
+    #[test]
+    fn filter_by_error_kind() {
+        let mut log = log();
+        log.filter.error_kind = Some("Network");
+        assert_eq!(msgs(&log), ["Could not reach the server: reset"]);
+        log.filter.error_kind = Some("Auth");
+        assert!(msgs(&log).is_empty());
+
+        // Every kind can be picked
+        let kinds = [
+            BorkError::Network(String::new()),
+            BorkError::Http {
+                status: 500,
+                reason: None,
+            },
+            BorkError::Auth(String::new()),
+            BorkError::decode(""),
+            BorkError::validation(""),
+            BorkError::Io(String::new()),
+            BorkError::cancelled(),
+        ]
+        .map(|err| err.kind());
+        assert_eq!(kinds, ERROR_KINDS);
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped_past_the_capacity() {
+        let mut log = MessageLog {
+            capacity: 3,
+            ..MessageLog::default()
+        };
+        for n in 0..5 {
+            log.push(Loglet::new(Level::Info, &n.to_string()));
+        }
+        assert_eq!(msgs(&log), ["4", "3", "2"]);
+        assert_eq!(log.shown()[0].source(), "windows/client_windows");
+
+        // Made smaller from the window
+        log.capacity = 1;
+        log.push(Loglet::new(Level::Info, "5"));
+        assert_eq!(msgs(&log), ["5"]);
+    }
+
+    #[test]
+    fn only_problems_open_the_window() {
+        let mut window = GenericWindow::new("Error Messages");
+        window.push_loglet(Loglet::new(Level::Info, "Logged in"));
+        assert!(!window.is_window_open);
+        window.push_loglet(Loglet::err_s("Failed"));
+        assert!(window.is_window_open);
+
+        let loglet = Loglet::with_source(Level::Warn, "nbt", "two\nlines");
+        assert!(loglet.line().ends_with("] Warn nbt: two | lines"));
+    }
+}
@@ -1,16 +1,18 @@
-use crate::time_of_day;
-use crate::windows::client_windows::{GenericWindow, Loglet};
-use std::sync::mpsc::{channel, Receiver, Sender};
+use crate::logging::trace_loglet;
+use crate::thread_tools::{waking_channel, WakingSender};
+use crate::windows::client_windows::{GenericWindow, Level, Loglet};
+use crate::BorkError;
+use std::sync::mpsc::Receiver;
 
 pub struct ErrorMessage {
     pub display: GenericWindow,
     pub receiver: Receiver<Loglet>,
-    pub sender: Sender<Loglet>,
+    pub sender: WakingSender<Loglet>,
 }
 
 impl ErrorMessage {
     pub fn new() -> Self {
-        let (sender, receiver) = channel();
+        let (sender, receiver) = waking_channel();
         ErrorMessage {
             display: GenericWindow::default(),
             receiver,
@@ -24,13 +26,22 @@ impl ErrorMessage {
             GenericWindow::push_loglet(&mut self.display, loglet);
         });
     }
-    pub fn sender_clone(&self) -> Sender<Loglet> {
+    pub fn sender_clone(&self) -> WakingSender<Loglet> {
         //! Provide a sender for async functions or new Threads/Tokios...
         self.sender.clone()
     }
+    #[track_caller]
+    pub fn push_error(&mut self, err: BorkError) {
+        // Sync err appending that keeps the error whole
+        let loglet = Loglet::err(err);
+        trace_loglet(&loglet);
+        GenericWindow::push_loglet(&mut self.display, loglet);
+    }
+    #[track_caller]
     pub fn push_err(&mut self, msg: &str) {
         // Sync err appending, for sending errors on the main thread
-        let loglet = Loglet::new("Error", msg, &time_of_day());
+        let loglet = Loglet::new(Level::Error, msg);
+        trace_loglet(&loglet);
         GenericWindow::push_loglet(&mut self.display, loglet);
     }
 }
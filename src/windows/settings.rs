@@ -0,0 +1,95 @@
+use crate::{
+    logging::{log_level, set_log_level},
+    windows::client_windows::{GenericWindow, Level, LOGS_DIR},
+};
+use eframe::egui::{ComboBox, Context, Grid, Ui, Visuals};
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Dark, Theme::Light];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+        }
+    }
+    pub fn apply(&self, ctx: &Context) {
+        ctx.set_visuals(match self {
+            Theme::Dark => Visuals::dark(),
+            Theme::Light => Visuals::light(),
+        });
+    }
+}
+
+pub struct Settings {
+    pub theme: Theme,
+    pub display: GenericWindow,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            display: GenericWindow::new("Settings"),
+        }
+    }
+}
+
+pub fn settings_button(settings: &mut Settings, ui: &mut Ui) {
+    if ui.button("Settings").clicked() {
+        settings.display.is_window_open = !settings.display.is_window_open;
+    }
+}
+
+fn show(settings: &mut Settings, ui: &mut Ui) {
+    Grid::new("settings").show(ui, |ui| {
+        ui.label("Theme");
+        let theme = settings.theme;
+        ComboBox::from_id_source("theme")
+            .selected_text(settings.theme.name())
+            .show_ui(ui, |ui| {
+                for choice in Theme::ALL {
+                    ui.selectable_value(&mut settings.theme, choice, choice.name());
+                }
+            });
+        if settings.theme != theme {
+            settings.theme.apply(ui.ctx());
+        }
+        ui.end_row();
+
+        ui.label("Log Level");
+        let mut level = log_level();
+        ComboBox::from_id_source("log level")
+            .selected_text(level.name())
+            .show_ui(ui, |ui| {
+                for choice in Level::ALL {
+                    ui.selectable_value(&mut level, choice, choice.name());
+                }
+            });
+        if level != log_level() {
+            set_log_level(level);
+        }
+        ui.end_row();
+    });
+    ui.label(format!(
+        "Everything at or above the log level goes to stderr, the |{}| folder & the Error Messages window",
+        LOGS_DIR
+    ));
+}
+
+pub fn settings_window(settings: &mut Settings, ctx: &Context) {
+    //! Shows the "Settings" window (if its open)
+    let mut display = std::mem::take(&mut settings.display);
+
+    display.show_with(ctx, |ui| show(settings, ui));
+
+    settings.display = display;
+}
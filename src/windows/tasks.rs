@@ -0,0 +1,73 @@
+use crate::{thread_tools::tasks, windows::client_windows::GenericWindow};
+use eframe::egui::{Context, Grid, ProgressBar, ScrollArea, Spinner, Ui};
+use std::time::Duration;
+
+pub struct TaskManager {
+    pub display: GenericWindow,
+}
+
+impl Default for TaskManager {
+    fn default() -> Self {
+        Self {
+            display: GenericWindow::new("Tasks"),
+        }
+    }
+}
+
+pub fn tasks_button(manager: &mut TaskManager, ui: &mut Ui) {
+    //! The top panel button; says how many jobs are running
+    let running = tasks().len();
+    let label = match running {
+        0 => "Tasks".to_string(),
+        _ => format!("Tasks ({})", running),
+    };
+    if ui.button(label).clicked() {
+        manager.display.is_window_open = !manager.display.is_window_open;
+    }
+}
+
+fn show(ui: &mut Ui) {
+    let running = tasks();
+    if running.is_empty() {
+        ui.label("Nothing is running");
+        return;
+    }
+    if ui.button("Cancel All").clicked() {
+        running.iter().for_each(|task| task.cancel());
+    }
+    // Keep the "Running For" column ticking
+    ui.ctx().request_repaint_after(Duration::from_secs(1));
+
+    ScrollArea::vertical()
+        .id_source("tasks")
+        .max_height(400.0)
+        .show(ui, |ui| {
+            Grid::new("tasks").striped(true).show(ui, |ui| {
+                ui.strong("Task");
+                ui.strong("Running For");
+                ui.strong("Progress");
+                ui.end_row();
+
+                for task in &running {
+                    ui.label(&task.name);
+                    ui.label(format!("{:.1}s", task.started.elapsed().as_secs_f32()));
+                    match task.progress {
+                        Some(done) => ui.add(ProgressBar::new(done).show_percentage()),
+                        None => ui.add(Spinner::new()),
+                    };
+                    // A job only stops once it checks, or its current step is done
+                    if task.is_cancelled() {
+                        ui.label("Cancelling...");
+                    } else if ui.button("Cancel").clicked() {
+                        task.cancel();
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+pub fn tasks_window(manager: &mut TaskManager, ctx: &Context) {
+    //! Shows the "Tasks" window (if its open); every background job with a way to cancel it
+    manager.display.show_with(ctx, show);
+}